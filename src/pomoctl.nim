@@ -0,0 +1,38 @@
+## pomoctl sends commands to a running pomod through its control socket.
+
+import std/os
+
+import pomod/client
+
+const
+  Usage = """
+usage: pomoctl [--host <host:port> [--token <token>]] <command> [arguments]
+
+commands:
+  start, pause, resume, toggle, skip, abandon, ack, reload, next-profile,
+  toggle-output, override
+  reset, reset-interval, restart, reset-all, lap
+  status [--json]
+  set-profile <name>
+  task [text] [--tags <tags>]
+  task add <name> [--estimate <n>], task list [--json], task remove <name>
+  set <setting> <value>
+  set-state pomodoro|short-break|long-break
+  extend [minutes]
+  set-remaining <mm:ss>
+  interrupt internal|external [note]
+  snooze [minutes]
+  timer <name> [length], cancel <name>, timers [--json]
+  @<name> <command>   send the command to a named timer
+
+options:
+  --host <host:port>  control a pomod running on another machine
+  --token <token>     the token it expects, POMOD_TOKEN by default
+"""
+
+when isMainModule:
+  let args = commandLineParams()
+  if args.len == 0 or args[0] in ["-h", "--help"]:
+    stdout.write(Usage)
+    quit(if args.len == 0: QuitFailure else: QuitSuccess)
+  quit(runClient("pomoctl", args))
@@ -1,135 +1,64 @@
 ## pomod is a dead-simple and super-lightweight Pomodoro timer for Polybar.
 
-import std/monotimes
 import std/options
 import std/posix
 import std/strformat
 import std/tables
 import std/times
 
-
-# edit to configure
-
-const
-  # times are in seconds
-  PomodoroTime = 25 * 60
-  ShortBreakTime = 5 * 60
-  LongBreakTime = 30 * 60
-  BreakCycle = 4           # the amount of short breaks before a long break
-  # icons
-  IconPlanned = ""
-  IconPomodoro = ""
-  IconShortBreak = ""
-  IconLongBreak = ""
-
-
-# implementation
-
-proc minutes(duration: Duration): int64 =
-  ## Get the amount of minutes in the duration.
-  duration.inSeconds div 60
-
-proc seconds(duration: Duration): int64 =
-  ## Get the amount of seconds in the current minute.
-  duration.inSeconds mod 60
-
-type
-  TimerState = enum ## the state of a pomodoro timer
-    tsNone        ## planned/not started yet
-    tsPomodoro = "pomodoro"
-    tsShortBreak = "short break"
-    tsLongBreak = "long break"
-  Timer = object
-    running: bool                     ## is the timer running or paused
-    state: TimerState                 ## the current state
-    stateStartTime: Option[MonoTime]  ## when the state was started
-    remainingTime: Duration
-    lastPoll: MonoTime                ## last time when poll() was called
-    breakCounter: int
-    stateChangeProc: proc (newState: TimerState)
-
-proc time(state: TimerState): Duration =
-  ## Returns the amount of time a given state should take (in seconds).
-  let seconds =
-    case state
-    of tsNone, tsPomodoro: PomodoroTime
-    of tsShortBreak: ShortBreakTime
-    of tsLongBreak: LongBreakTime
-
-  result = initDuration(seconds = seconds)
-
-proc pomicon(state: TimerState): string =
-  ## Returns the Nerd Font/Pomicons icon associated with the given state.
-  result =
-    case state
-    of tsNone: IconPlanned
-    of tsPomodoro: IconPomodoro
-    of tsShortBreak: IconShortBreak
-    of tsLongBreak: IconLongBreak
-
-proc next(state: var TimerState, breakCounter: var int) =
-  ## Sets the next state according to the break counter, then increments the
-  ## break counter.
-  case state
-  of tsNone, tsShortBreak, tsLongBreak: state = tsPomodoro
-  of tsPomodoro:
-    if breakCounter < BreakCycle - 1:
-      state = tsShortBreak
-    else:
-      state = tsLongBreak
-    breakCounter = (breakCounter + 1) mod BreakCycle
-
-proc initTimer(): Timer =
-  ## Initializes a new timer.
-  result = Timer()
-  result.remainingTime = result.state.time
-  result.lastPoll = getMonoTime()
-
-proc onStateChange(timer: var Timer, callback: proc (newState: TimerState)) =
-  ## Sets the timer's state change callback.
-  timer.stateChangeProc = callback
-
-proc nextState(timer: var Timer) =
-  ## Skips to the next state and sets the timer's remaining time accordingly.
-  timer.state.next(timer.breakCounter)
-  timer.remainingTime = timer.state.time
-
-proc start(timer: var Timer) =
-  ## Starts the timer.
-  if not timer.running:
-    if timer.stateStartTime.isNone:
-      timer.stateStartTime = some(getMonoTime())
-      timer.nextState()
-    timer.running = true
-
-proc stop(timer: var Timer) =
-  ## Stops the timer.
-  timer.running = false
-
-proc toggle(timer: var Timer) =
-  ## Toggles the timer.
-  if not timer.running: timer.start()
-  else: timer.stop()
-
-proc poll(timer: var Timer) =
-  ## Polls the timer and updates its state. This must be called monotonically
-  ## (slight deviations are allowed).
-  if timer.running:
-    if timer.remainingTime.inSeconds <= 0:
-      # time's up
-      timer.nextState()
-      if timer.stateChangeProc != nil:
-        timer.stateChangeProc(timer.state)
-    else:
-      timer.remainingTime -= getMonoTime() - timer.lastPoll
-  timer.lastPoll = getMonoTime()
+import pomod/adaptive
+import pomod/autostart
+import pomod/backup
+import pomod/cli
+import pomod/client
+import pomod/commands
+import pomod/config
+import pomod/control
+import pomod/dailysummary
+import pomod/dbusservice
+import pomod/dispatch
+import pomod/events
+import pomod/exchange
+import pomod/eventsocket
+import pomod/fifo
+import pomod/httpapi
+import pomod/heatmap
+import pomod/history
+import pomod/historysync
+import pomod/i3blocks
+import pomod/instance
+import pomod/interactive
+import pomod/journal
+import pomod/jsonevents
+import pomod/metrics
+import pomod/mqtt
+import pomod/osc
+import pomod/output
+import pomod/paths
+import pomod/persistence
+import pomod/prompt
+import pomod/quiethours
+import pomod/reminders
+import pomod/report
+import pomod/server
+import pomod/sinks
+import pomod/stats
+import pomod/streaks
+import pomod/tasks
+import pomod/timer
+import pomod/timers
+import pomod/tmux
+import pomod/worklog
 
 
 # CLI
 
 when isMainModule:
 
+  import std/json
+  import std/monotimes
   import std/os
+  import std/strutils
 
   import dbus
   import rapid/audio/device
@@ -138,8 +67,8 @@ when isMainModule:
   # common
 
   proc notification(appName, summary, body: string,
-                    hints: Table[string, auto], timeout = 0) =
-    ## Sends a notification via dbus.
+                    hints: Table[string, auto], timeout = 0): uint32 =
+    ## Sends a notification via dbus. Returns the notification's ID.
 
     # stolen from disruptek who stole it from solitudesf
     # simplified because I don't need everything.
@@ -156,14 +85,295 @@ when isMainModule:
     message.append(newSeq[string]())  # actions
     message.append(hints)
     message.append(timeout.int32)
+    let reply = bus.sendMessageWithReply(message).waitForReply()
+    defer: reply.close()
+    reply.raiseIfError()
+    var iter = reply.iterate
+    result = iter.unpackCurrent(uint32)
+
+  proc closeNotification(id: uint32) =
+    ## Closes a notification sent earlier.
+    let bus = getBus(DBUS_BUS_SESSION)
+    var message = makeCall("org.freedesktop.Notifications",
+                           ObjectPath"/org/freedesktop/Notifications",
+                           "org.freedesktop.Notifications",
+                           "CloseNotification")
+    message.append(id)
     bus.sendMessage(message)
 
-  proc configDir(): string =
-    result = getConfigDir()/"pomod"
+  # configuration
+
+  # ctl passes its arguments through to the daemon untouched
+  if paramCount() > 0 and paramStr(1) == "ctl":
+    quit(runClient("pomod", commandLineParams()[1..^1]))
+
+  let options =
+    try: parseCli()
+    except CliError as e:
+      stderr.writeLine("pomod: " & e.msg)
+      stderr.write(Usage)
+      quit(QuitFailure)
+  if options.help:
+    stdout.write(Usage)
+    quit(QuitSuccess)
+
+  proc checkConfigCommand(): int =
+    ## Prints the problems found in the config file. Returns the exit code.
+    let path = configFile()
+    if not fileExists(path):
+      echo "no config file at " & path & ", the defaults will be used"
+      return QuitSuccess
+    let issues = checkConfig(path)
+    result = QuitSuccess
+    for issue in issues:
+      let
+        location = if issue.line > 0: path & ":" & $issue.line else: path
+        severity = if issue.fatal: "error" else: "warning"
+      stderr.writeLine(location & ": " & severity & ": " & issue.message)
+      if issue.fatal: result = QuitFailure
+    if issues.len == 0:
+      echo path & " is ok"
+
+  proc tryOpenHistory(): History =
+    ## Opens the history, or says why it can't be and returns a closed one.
+    try: result = openHistory()
+    except DbError as e:
+      stderr.writeLine("pomod: cannot open the history: " & e.msg)
+
+  proc sinceDay(dayStart: Duration): Option[DateTime] =
+    ## Returns when the day given with --since began, if any.
+    if options.since.isSome: result = some(options.since.get + dayStart)
+
+  proc selected(sessions: seq[Session]): seq[Session] =
+    ## Leaves out the sessions not carrying the tag asked for, if any.
+    result =
+      if options.tag.isSome: sessions.tagged(options.tag.get)
+      else: sessions
+
+  proc exportCommand(): int =
+    ## Prints the history in the format asked for. Returns the exit code.
+    let history = tryOpenHistory()
+    if not history.isOpen: return QuitFailure
+    let
+      day = sinceDay(loadConfigOrDefault().dayStart)
+      since = if day.isSome: day.get.toTime else: fromUnix(0)
+    stdout.write(history.sessions(since).selected
+                 .exportHistory(options.exchangeFormat))
+    history.close()
+    result = QuitSuccess
+
+  proc importCommand(): int =
+    ## Adds the sessions of an exported history to the history, leaving out
+    ## those it has already. Returns the exit code.
+    if options.arguments.len != 1:
+      stderr.writeLine("pomod: import expects the file to import")
+      return QuitFailure
+    let path = options.arguments[0]
+    let sessions =
+      try: importHistory(readFile(path))
+      except IOError, ValueError:
+        stderr.writeLine("pomod: cannot import " & path & ": " &
+                         getCurrentExceptionMsg())
+        return QuitFailure
+    let history = tryOpenHistory()
+    if not history.isOpen: return QuitFailure
+    var imported = 0
+    result = QuitSuccess
+    try:
+      for session in sessions:
+        if session in history: continue
+        history.record(session)
+        inc imported
+    except DbError as e:
+      stderr.writeLine("pomod: cannot record the history: " & e.msg)
+      result = QuitFailure
+    history.close()
+    echo "imported " & $imported & " of " & $sessions.len & " sessions"
+
+  proc statsCommand(): int =
+    ## Prints the statistics of the period asked for. Returns the exit code.
+    let period =
+      if options.arguments.len == 0: spToday
+      else:
+        try: parseEnum[StatsPeriod](options.arguments[0])
+        except ValueError:
+          stderr.writeLine("pomod: stats expects today, week or month")
+          return QuitFailure
+    let history = tryOpenHistory()
+    if not history.isOpen: return QuitFailure
+    let dayStart = loadConfigOrDefault().dayStart
+    if options.heatmap:
+      let
+        today = now()
+        start = heatmapStart(today, dayStart)
+        counts = history.sessions(start.toTime).selected
+                 .completedByDay(dayStart)
+        color =
+          case options.colorMode.get(cmAuto)
+          of cmAlways: true
+          of cmNever: false
+          of cmAuto: isatty(STDOUT_FILENO) != 0
+      history.close()
+      stdout.write(renderHeatmap(counts, today, color, dayStart))
+      return QuitSuccess
+    let
+      start = period.periodStart(now(), dayStart)
+      summary = history.sessions(start.toTime).selected.summarize
+    history.close()
+    if options.json: echo summary.toJson(period)
+    else: stdout.write(summary.render(period))
+    result = QuitSuccess
+
+  proc reportCommand(): int =
+    ## Writes the report asked for. Returns the exit code.
+    if not options.weekly:
+      stderr.writeLine("pomod: report expects --weekly")
+      return QuitFailure
+    let history = tryOpenHistory()
+    if not history.isOpen: return QuitFailure
+    let
+      dayStart = loadConfigOrDefault().dayStart
+      start = spWeek.periodStart(sinceDay(dayStart).get(now()), dayStart)
+      sessions = history.sessions(start.toTime, (start + 7.days).toTime)
+                 .selected
+      path = options.outputPath.get("")
+      format = if path.len == 0: rfMarkdown else: path.reportFormat
+      report = weeklyReport(sessions, start, format)
+    history.close()
+    if path.len == 0:
+      stdout.write(report)
+      return QuitSuccess
+    try: writeFile(path.expandTilde, report)
+    except IOError as e:
+      stderr.writeLine("pomod: cannot write the report: " & e.msg)
+      return QuitFailure
+    result = QuitSuccess
+
+  proc syncCommand(): int =
+    ## Syncs the history through the directory asked for, or the configured
+    ## one. Returns the exit code.
+    let dir =
+      if options.arguments.len > 0: options.arguments[0].expandTilde
+      else: loadConfigOrDefault().syncDir
+    if dir.len == 0:
+      stderr.writeLine("pomod: sync expects a directory, or sync_dir in " &
+                       "the config file")
+      return QuitFailure
+    let history = tryOpenHistory()
+    if not history.isOpen: return QuitFailure
+    let synced =
+      try: history.sync(dir)
+      except DbError, IOError, OSError:
+        stderr.writeLine("pomod: cannot sync: " & getCurrentExceptionMsg())
+        history.close()
+        return QuitFailure
+    history.close()
+    for error in synced.errors:
+      stderr.writeLine("pomod: skipped " & error)
+    echo "merged " & $synced.merged & " sessions from " & $synced.machines &
+         " other machines"
+    result = QuitSuccess
+
+  proc backupCommand(): int =
+    ## Archives pomod's data to the file asked for. Returns the exit code.
+    if options.arguments.len != 1:
+      stderr.writeLine("pomod: backup expects the file to write")
+      return QuitFailure
+    let path = options.arguments[0].expandTilde
+    try: backup(path)
+    except BackupError, IOError, OSError:
+      stderr.writeLine("pomod: cannot back up: " & getCurrentExceptionMsg())
+      return QuitFailure
+    echo "backed up to " & path
+    result = QuitSuccess
+
+  proc restoreCommand(): int =
+    ## Replaces pomod's data with the backup asked for. Returns the exit code.
+    if options.arguments.len != 1:
+      stderr.writeLine("pomod: restore expects the backup to restore")
+      return QuitFailure
+    # the running instance would overwrite the restored state on exit, so
+    # it's kept from starting while the backup is restored; the pidfile is
+    # left naming whichever instance last ran
+    ensureDir(runtimeDir(), private = true)
+    let hadPidFile = fileExists(pidFile())
+    var lock =
+      try: lockInstance(writePid = false)
+      except AlreadyRunningError as e:
+        stderr.writeLine("pomod: " & e.msg & "; quit it before restoring")
+        return QuitFailure
+      except OSError as e:
+        stderr.writeLine("pomod: cannot lock " & pidFile() & ": " & e.msg)
+        return QuitFailure
+    defer:
+      lock.release()
+      if not hadPidFile: removeFile(pidFile())
+    let path = options.arguments[0].expandTilde
+    let restored =
+      try: restore(path)
+      except BackupError, IOError, OSError:
+        stderr.writeLine("pomod: cannot restore " & path & ": " &
+                         getCurrentExceptionMsg())
+        return QuitFailure
+    echo "restored " & restored.join(", ")
+    result = QuitSuccess
+
+  case options.command
+  of "run": discard
+  of "check-config": quit(checkConfigCommand())
+  of "sync": quit(syncCommand())
+  of "backup": quit(backupCommand())
+  of "restore": quit(restoreCommand())
+  of "send-summary": quit(runDelivery())
+  of "report": quit(reportCommand())
+  of "stats": quit(statsCommand())
+  of "export": quit(exportCommand())
+  of "import": quit(importCommand())
+  of "i3blocks": quit(runI3blocks())
+  of "tmux": quit(runTmux())
+  of "prompt": quit(runPrompt())
+  of "once", "at", "stopwatch", "serve": discard
+  elif options.command.isCommand:
+    # the daemon's commands are sent to the running instance
+    var words = @[options.command] & options.arguments
+    if options.json: words.add("json")
+    if options.estimate.isSome:
+      words.add(["--estimate", $options.estimate.get])
+    if options.tags.len > 0: words.add(["--tags", options.tags.join(",")])
+    quit(runClient("pomod", words))
+  else:
+    stderr.writeLine("pomod: unknown command: " & options.command)
+    stderr.write(Usage)
+    quit(QuitFailure)
+
+  proc readConfig(): Config =
+    ## Loads the config file and applies the command line overrides on top.
+    result = loadConfig()
+    result.apply(options)
+    result.stopwatch = options.command == "stopwatch"
+
+  var currentConfig =
+    try: readConfig()
+    except CatchableError as e:
+      stderr.writeLine("error in " & configFile() & ": " & e.msg)
+      quit(QuitFailure)
 
-  if not dirExists(configDir()):
-    stderr.writeLine("config dir does not exist, creating at " & configDir())
-    createDir(configDir())
+  ensureDirs()
+
+  # the server mode hosts other users' timers instead of running one of its own
+  if options.command == "serve": quit(serve(currentConfig))
+
+  # one-shot countdowns run alongside the daemon
+  let instanceLock =
+    if options.command in ["once", "at"]: initInstanceLock()
+    else:
+      try: lockInstance(takeover = options.takeover)
+      except AlreadyRunningError as e:
+        stderr.writeLine("pomod: " & e.msg & "; use --takeover to replace it")
+        quit(QuitFailure)
+      except OSError as e:
+        stderr.writeLine("pomod: cannot lock " & pidFile() & ": " & e.msg)
+        quit(QuitFailure)
 
   if not fileExists(configDir()/"sound.ogg"):
     stderr.writeLine("default sound does not exist, creating")
@@ -176,49 +386,917 @@ when isMainModule:
   audioDevice.attach(sound)
   audioDevice.start()
 
+  proc notify(summary, body: string, urgency = 1'u8, timeout = 5000,
+              playSound = false): uint32 =
+    ## Tells the user something through a desktop notification, optionally
+    ## playing the sound as well. During quiet hours, the notification is
+    ## downgraded to a low urgency or left out entirely, and there's no sound.
+    ## Returns the notification's ID, or 0 if none was sent.
+    var urgency = urgency
+    let quiet = currentConfig.quietHours.quietMode(now())
+    if quiet.isSome:
+      if quiet.get == qmSilent: return 0
+      urgency = 0
+    elif playSound:
+      sound.stop()
+      sound.play()
+    try:
+      result = notification(appName = "pomod", summary = summary, body = body,
+                            hints = {"urgency": newVariant(urgency)}.toTable,
+                            timeout = timeout)
+    except DbusException as e:
+      stderr.writeLine("cannot send a notification: " & e.msg)
+
+  proc runCountdown(length: Duration, body: string): int =
+    ## Counts down once, printing the status like the daemon does, then sends
+    ## a notification with the given body. Returns the exit code.
+    var config = currentConfig.countdownConfig(length)
+    if options.label.len > 0 and config.statusTemplate == DefaultTemplate:
+      config.statusTemplate = LabelTemplate
+    var timer = initTimer(config)
+    timer.label = options.label
+    timer.start()
+    let sinks = openSinks(config, overwrite = isatty(STDOUT_FILENO) != 0)
+    while timer.stats.pomodorosCompleted == 0:
+      for sink in sinks:
+        sink.render(TimerSnapshot(timer: timer))
+      sleep(int(config.tickInterval.inMilliseconds))
+      timer.poll()
+    # the schedule has wrapped around by now, so show the end of it instead
+    timer.pause()
+    timer.remainingTime = DurationZero
+    for sink in sinks:
+      sink.close(TimerSnapshot(timer: timer))
+    let title = if options.label.len > 0: options.label else: "time's up"
+    discard notify("pomod: " & title, body, urgency = 2, playSound = true)
+    # give the sound a chance to play before exiting
+    sleep(3000)
+    result = QuitSuccess
+
+  proc runOnce(): int =
+    ## Counts down for the length given on the command line.
+    if options.arguments.len != 1:
+      stderr.writeLine("pomod: once expects a length, eg. 12m")
+      return QuitFailure
+    let length =
+      try: parseLength(options.arguments[0])
+      except ValueError as e:
+        stderr.writeLine("pomod: " & e.msg)
+        return QuitFailure
+    result = runCountdown(length, "the " & length.hoursAndMinutes &
+                                  " countdown is over")
+
+  proc runAt(): int =
+    ## Counts down to the time of day given on the command line, which is
+    ## tomorrow if it has passed today already.
+    if options.arguments.len != 1:
+      stderr.writeLine("pomod: at expects a time of day, eg. 14:30")
+      return QuitFailure
+    let clock =
+      try: parse(options.arguments[0], "H:mm")
+      except TimeParseError:
+        stderr.writeLine("pomod: invalid time of day: " & options.arguments[0])
+        return QuitFailure
+    # going through the calendar takes daylight saving time into account
+    let current = now()
+    var target = dateTime(current.year, current.month, current.monthday,
+                          clock.hour, clock.minute, zone = local())
+    if target <= current:
+      target = target + 1.days
+    result = runCountdown(target - current,
+                          "it's " & target.format("HH:mm"))
+
+  if options.command == "once": quit(runOnce())
+  if options.command == "at": quit(runAt())
+
   # the timer
 
+  var
+    lastNotification = 0'u32
+    currentTask = ""  # outlives resets, like the counters
+    currentTags: seq[string]
+
+  proc notifyNext(summary: string, newState: TimerState, urgency = 2'u8,
+                  playSound = false, task = currentTask) =
+    ## Tells the user what's up next through a desktop notification, along
+    ## with the task at hand.
+    var body = "next up: " & newState.name
+    if task.len > 0: body = task & ", " & body
+    let id = notify(summary, body, urgency, playSound = playSound)
+    if id != 0: lastNotification = id
+
   proc reset(timer: var Timer) =
-    timer = initTimer()
+    # the counters outlive resets, they're only cleared by restarting pomod
+    timer.reset(currentConfig)
+    timer.label = currentTask
+    timer.tags = currentTags
     timer.onStateChange do (newState: TimerState):
-      # send a notification to the user's desktop
-      notification(appName = "pomod", summary = "pomod: time's up",
-                   body = "next up: " & $newState, hints = {
-                     "urgency": newVariant(2'u8)
-                   }.toTable, timeout = 5000)
-      # also, play a nice (user-definable) sound
-      sound.stop()
-      sound.play()
+      # send a notification to the user's desktop, and also play a nice
+      # (user-definable) sound
+      notifyNext("pomod: time's up", newState, playSound = true)
+
+  proc reloadConfig(timer: var Timer) =
+    ## Re-reads the config file. The currently running state is left alone, the
+    ## new durations only apply to the states that follow.
+    try:
+      var newConfig = readConfig()
+      # keep the profile that was switched to at runtime, if it's still there
+      if timer.config.profileName in newConfig.profiles:
+        newConfig.selectProfile(timer.config.profileName)
+        newConfig.applyOverrides(options)
+      currentConfig = newConfig
+      timer.setConfig(newConfig)
+      stderr.writeLine("config reloaded")
+    except CatchableError as e:
+      stderr.writeLine("error in " & configFile() & ": " & e.msg &
+                       "; keeping the old config")
+
+  proc switchProfile(timer: var Timer, name: string) =
+    ## Switches to another profile. Like with reloading, the current state is
+    ## left alone.
+    var newConfig = timer.config
+    newConfig.selectProfile(name)
+    newConfig.applyOverrides(options)
+    currentConfig = newConfig
+    timer.setConfig(newConfig)
+    stderr.writeLine("switched to profile " & name)
+
+  proc status(timer: Timer): string =
+    ## Describes the timer's status for the control socket.
+    let
+      minutes = timer.remainingTime.minutes
+      seconds = timer.remainingTime.seconds
+      state = timer.state.name
+      running = if timer.running: "running" else: "paused"
+    result = fmt"{state}, {minutes:02}:{seconds:02} left, {running}, " &
+             "profile " & timer.config.profileName
+
+  var
+    outputHidden = false
+    stateUnsaved = false  # set by every command, in case it changed the state
+    namedTimers: seq[NamedTimer]
+    taskList =
+      try: loadTasks()
+      except CatchableError as e:
+        stderr.writeLine("cannot read the task list: " & e.msg)
+        TaskList()
+    dayLog =
+      try: loadDays()
+      except CatchableError as e:
+        stderr.writeLine("cannot read the day log: " & e.msg)
+        DayLog()
+
+  proc saveTasks() =
+    try: taskList.save()
+    except IOError, OSError:
+      stderr.writeLine("cannot save the task list: " &
+                       getCurrentExceptionMsg())
+
+  proc taskCommand(timer: var Timer, arguments: seq[string]): string =
+    ## Executes the task command, which manages the task list when given a
+    ## subcommand, and otherwise sets the task at hand.
+    var arguments = arguments
+    let
+      estimateOption = arguments.takeOption("--estimate")
+      tagsOption = arguments.takeOption("--tags")
+      subcommand = if arguments.len > 0: arguments[0] else: ""
+    case subcommand
+    of "add":
+      let estimate =
+        try: parseInt(estimateOption.get("1"))
+        except ValueError: 0
+      if estimate <= 0:
+        raise newException(CommandError, "the estimate must be a positive " &
+                           "amount of pomodoros")
+      let name = arguments[1..^1].join(" ")
+      if name.len == 0:
+        raise newException(CommandError, "task add expects a name")
+      if taskList.find(name) >= 0:
+        raise newException(CommandError, "there's a task named " & name &
+                           " already")
+      taskList.tasks.add(Task(name: name, estimate: estimate))
+      saveTasks()
+    of "list":
+      if arguments.len == 1:
+        var summaries: seq[string]
+        for task in taskList.tasks:
+          summaries.add(task.summary)
+        result =
+          if summaries.len == 0: "no tasks"
+          else: summaries.join(", ")
+      elif arguments[1] == "json":
+        var list = newJArray()
+        for task in taskList.tasks:
+          list.add(task.toJson)
+        result = $list
+      else: raise newException(CommandError, "task list accepts only json")
+    of "remove":
+      let
+        name = arguments[1..^1].join(" ")
+        i = taskList.find(name)
+      if i < 0:
+        raise newException(CommandError, "no task named " & name)
+      taskList.tasks.delete(i)
+      saveTasks()
+    else:
+      currentTask = arguments.join(" ")
+      currentTags = parseTags(tagsOption.get(""))
+      timer.label = currentTask
+      timer.tags = currentTags
+
+  proc setting(timer: var Timer, key, value: string) =
+    ## Changes a setting of the active profile until it's switched or the
+    ## config is reloaded. Durations apply from the next state onwards.
+    if key == "cycle":
+      let breakCycle =
+        try: parseInt(value)
+        except ValueError: -1
+      if breakCycle <= 0:
+        raise newException(CommandError, "cycle expects a positive integer")
+      timer.setBreakCycle(breakCycle)
+    elif key in ["pomodoro", "short-break", "long-break"]:
+      let duration =
+        Command(kind: ckSet, arguments: @[value]).minutesArgument()
+      case key
+      of "pomodoro": timer.config.profile.pomodoroTime = duration
+      of "short-break": timer.config.profile.shortBreakTime = duration
+      else: timer.config.profile.longBreakTime = duration
+    else:
+      raise newException(CommandError, "unknown setting " & key & ", " &
+                         "expected cycle, pomodoro, short-break or long-break")
+    # resets start from the changed profile too
+    currentConfig.profile = timer.config.profile
+    if timer.stateStartTime.isNone:
+      timer.remainingTime = timer.stateTime
+
+  proc execute(timer: var Timer, command: Command): string =
+    ## Executes a command and returns the reply to it.
+    result = "ok"
+    timer.checkStrict(command)
+    timer.checkLimit(command)
+    if command.kind notin {ckStatus, ckTimers}: stateUnsaved = true
+    case command.kind
+    of ckReset, ckResetAll: timer.reset()
+    of ckLap:
+      if not timer.config.stopwatch:
+        raise newException(CommandError, "laps are only marked in stopwatch " &
+                           "mode")
+      if timer.stateStartTime.isNone:
+        raise newException(CommandError, "the stopwatch hasn't been started")
+      let lap = timer.lap()
+      result = "lap " & $timer.laps.len & ": " & lap.clockTime &
+               ", " & timer.laps[^1].clockTime & " in total"
+    of ckInterrupt:
+      let kind =
+        case command.arguments[0]
+        of "internal": irInternal
+        of "external": irExternal
+        else:
+          raise newException(CommandError, "interrupt expects internal or " &
+                             "external")
+      if timer.state != tsPomodoro or timer.preparing:
+        raise newException(CommandError, "interruptions are only recorded " &
+                           "during pomodoros")
+      timer.recordInterruption(kind, command.arguments[1..^1].join(" "))
+      result = "ok, " & $timer.pomodoroInterruptions & " interruption(s) " &
+               "this pomodoro"
+    of ckSkip:
+      let
+        skipped = timer.state
+        overtime = timer.overtime
+        flow = timer.countsUp and timer.stateStartTime.isSome
+        focused = -timer.remainingTime
+      discard timer.control(command)
+      # skipping is deliberate, so there's no sound and no urgency
+      if flow:
+        notifyNext("pomod: focused for " & focused.hoursAndMinutes,
+                   timer.state, urgency = 1)
+      elif skipped != tsNone and not overtime:
+        notifyNext("pomod: skipped the " & skipped.name, timer.state,
+                   urgency = 1)
+    of ckStatus:
+      if command.arguments.len == 0: result = timer.status
+      elif command.arguments[0] == "json": result = $timer.statusJson
+      else: raise newException(CommandError, "status accepts only json")
+    of ckSetProfile: timer.switchProfile(command.arguments[0])
+    of ckTask: result = timer.taskCommand(command.arguments)
+    of ckSet: timer.setting(command.arguments[0], command.arguments[1])
+    of ckReload: timer.reloadConfig()
+    of ckNextProfile: timer.switchProfile(timer.config.nextProfileName)
+    of ckToggleOutput: outputHidden = not outputHidden
+    of ckTimer:
+      let name = command.arguments[0]
+      if namedTimers.find(name) >= 0:
+        raise newException(CommandError, "there's a timer named " & name &
+                           " already")
+      let length =
+        if command.arguments.len == 1: none(Duration)
+        else:
+          try: some(parseLength(command.arguments[1]))
+          except ValueError as e: raise newException(CommandError, e.msg)
+      var named = initNamedTimer(name, currentConfig, length)
+      if not named.oneShot:
+        named.timer.onStateChange do (newState: TimerState):
+          notifyNext("pomod: " & name & ": time's up", newState,
+                     playSound = true, task = "")
+      namedTimers.add(named)
+    of ckCancel:
+      let i = namedTimers.find(command.arguments[0])
+      if i < 0:
+        raise newException(CommandError, "no timer named " &
+                           command.arguments[0])
+      namedTimers.delete(i)
+    of ckTimers:
+      if command.arguments.len == 0:
+        result =
+          if namedTimers.len == 0: "no timers"
+          else: namedTimers.combined
+      elif command.arguments[0] == "json":
+        var list = newJArray()
+        for named in namedTimers:
+          let node = named.timer.statusJson
+          node["name"] = %named.name
+          node["one_shot"] = %named.oneShot
+          list.add(node)
+        result = $list
+      else: raise newException(CommandError, "timers accepts only json")
+    else: discard timer.control(command)
+
+  const NamedTimerCommands = {ckStart, ckPause, ckResume, ckToggle,
+                              ckResetInterval, ckRestart, ckSkip, ckExtend,
+                              ckSetRemaining, ckStatus}
+    ## the commands that can be sent to named timers
 
+  proc executeNamed(command: Command): string =
+    ## Executes a command sent to a named timer and returns the reply to it.
+    let i = namedTimers.find(command.timer)
+    if i < 0:
+      raise newException(CommandError, "no timer named " & command.timer)
+    # one-shot timers are over once skipped, which is what cancel is for
+    if command.kind notin NamedTimerCommands or
+       command.kind == ckSkip and namedTimers[i].oneShot:
+      raise newException(CommandError, $command.kind & " only applies to " &
+                         "the main timer")
+    # named timers are left out of strict mode and the daily limit, and of
+    # what the main timer notifies about
+    template named: untyped = namedTimers[i].timer
+    result = "ok"
+    if command.kind == ckStatus:
+      if command.arguments.len == 0: result = named.status
+      elif command.arguments[0] == "json": result = $named.statusJson
+      else: raise newException(CommandError, "status accepts only json")
+    else: discard named.control(command)
+
+  proc persisting(): bool =
+    ## Returns whether the timer's state is kept across restarts. A batch and
+    ## the stopwatch always start from scratch.
+    result = currentConfig.persistState and options.count.isNone and
+             not currentConfig.stopwatch
 
   var timer: Timer
   timer.reset()
+  # the timer picks up where the last instance left it, even if it was killed
+  # or the machine rebooted
+  if instanceLock.tookOver or persisting() and fileExists(stateFile()):
+    try: timer.restoreState()
+    except CatchableError as e:
+      stderr.writeLine("cannot restore the previous instance's state: " & e.msg)
+    currentTask = timer.label
+    currentTags = timer.tags
+  let journaled =
+    try: lastEntry()
+    except IOError, OSError: nil
+  if persisting() and journaled.crashed:
+    # the last instance went away without saving its state, so the journal is
+    # the most recent account of it
+    try: timer.restore(journaled)
+    except CatchableError as e:
+      stderr.writeLine("cannot recover from the journal: " & e.msg)
+    if currentConfig.crashRecovery == crInterrupt and
+       timer.state == tsPomodoro and timer.stateStartTime.isSome:
+      timer.cutShort()
+      timer.resetInterval()
+      timer.running = false
+      stderr.writeLine("the pomodoro in progress during the crash was " &
+                       "recorded as cut short")
+    else: stderr.writeLine("recovered the timer's state after a crash")
+    currentTask = timer.label
+    currentTags = timer.tags
+  var journal =
+    if not persisting(): Journal()
+    else:
+      try: openJournal()
+      except IOError as e:
+        stderr.writeLine("cannot open the journal: " & e.msg)
+        Journal()
+  journal.write(jeStarted, timer)
+
+  var history = tryOpenHistory()
+
+  proc syncHistory() =
+    ## Syncs the history with other machines, if there's a directory for it.
+    let dir = timer.config.syncDir
+    if dir.len == 0: return
+    try:
+      for error in history.sync(dir).errors:
+        stderr.writeLine("cannot sync " & error)
+    except DbError, IOError, OSError:
+      stderr.writeLine("cannot sync the history: " & getCurrentExceptionMsg())
+
+  syncHistory()
+
+  proc recordSessions() =
+    ## Adds the pomodoros and breaks that have ended to the history.
+    let ended = timer.stats.endedSessions.len > 0
+    for session in timer.stats.endedSessions:
+      try: history.record(session)
+      except DbError as e:
+        stderr.writeLine("cannot record the history: " & e.msg)
+      if timer.config.sessionLog.len > 0:
+        try: timer.config.sessionLog.appendToLog(session)
+        except IOError as e:
+          stderr.writeLine("cannot write to " & timer.config.sessionLog &
+                           ": " & e.msg)
+    timer.stats.endedSessions.setLen(0)
+    if ended: syncHistory()
+
+  var summaryDelivery: Delivery
+
+  proc mailDailySummary(time: DateTime) =
+    ## Starts sending the summary of the day the time belongs to.
+    if summaryDelivery.sending: return
+    let
+      dayStart = timer.config.dayStart
+      start = spToday.periodStart(time, dayStart)
+    try:
+      let (subject, body) = history.sessions(start.toTime).summarize
+                            .composeSummary(time.dayOf(dayStart),
+                                            timer.config.dailyGoal)
+      summaryDelivery = currentConfig.dailySummary.sendSummary(subject, body)
+    except DbError, IOError, OSError:
+      stderr.writeLine("cannot send the daily summary: " &
+                       getCurrentExceptionMsg())
+
+  # a batch is meant for scripts, so it starts right away
+  let completedAtStart = timer.stats.pomodorosCompleted
+  if options.count.isSome: timer.start()
+
+  proc handleCommandLine(line: string): string =
+    ## Replies to a line received through the control socket.
+    try:
+      let command = parseCommand(line)
+      result =
+        if command.timer.len == 0: timer.execute(command)
+        else: executeNamed(command)
+    except CommandError, ConfigError:
+      result = "error: " & getCurrentExceptionMsg()
+
+  # under systemd socket activation, the control socket is passed in already
+  let activated = activatedSockets()
+  var controlServer =
+    if activated.len > 0: adoptControlServer(activated[0])
+    else: openControlServer(controlSocket())
+
+  var tcpControlServer =
+    if currentConfig.controlAddress.len == 0: ControlServer()
+    else:
+      try:
+        openTcpControlServer(currentConfig.controlAddress,
+                             currentConfig.controlToken)
+      except CatchableError as e:
+        stderr.writeLine("cannot serve the control socket on " &
+                         currentConfig.controlAddress & ": " & e.msg)
+        ControlServer()
+
+  var eventServer =
+    try: openEventServer(eventSocket())
+    except OSError as e:
+      stderr.writeLine("cannot open the event socket: " & e.msg)
+      EventServer()
+
+  var jsonEvents =
+    if not options.jsonEvents: initJsonEventStream()
+    else:
+      try: openJsonEvents()
+      except IOError as e:
+        stderr.writeLine("cannot write JSON events: " & e.msg)
+        initJsonEventStream()
+
+  var commandFifo =
+    if currentConfig.fifoPath.len == 0: CommandFifo()
+    else:
+      try: openCommandFifo(currentConfig.fifoPath)
+      except OSError as e:
+        stderr.writeLine("cannot open the command FIFO: " & e.msg)
+        CommandFifo()
+
+  var httpServer =
+    if currentConfig.httpAddress.len == 0: HttpServer()
+    else:
+      try: openHttpServer(currentConfig.httpAddress)
+      except CatchableError as e:
+        stderr.writeLine("cannot serve the REST API on " &
+                         currentConfig.httpAddress & ": " & e.msg)
+        HttpServer()
+
+  proc handleHttpRequest(request: HttpRequest): HttpResponse =
+    ## Responds to a REST API request.
+    let path = request.path.split('?')[0]
+    case path
+    of "/status":
+      if request.verb != "GET": return errorResponse(405, "use GET")
+      result = jsonResponse(200, timer.statusJson)
+    of "/start", "/pause", "/resume", "/toggle", "/skip", "/reset",
+       "/reset-interval", "/restart", "/reset-all":
+      if request.verb != "POST": return errorResponse(405, "use POST")
+      let command = Command(kind: parseEnum[CommandKind](path[1..^1]))
+      try: discard timer.execute(command)
+      except CommandError as e: return timer.errorResponse(e)
+      except ConfigError as e: return errorResponse(400, e.msg)
+      result = jsonResponse(200, timer.statusJson)
+    of "/metrics":
+      if request.verb != "GET": return errorResponse(405, "use GET")
+      result = HttpResponse(code: 200, body: timer.metrics,
+                            contentType: MetricsContentType)
+    of "/history":
+      if request.verb != "GET": return errorResponse(405, "use GET")
+      if not history.isOpen:
+        return errorResponse(501, "the history couldn't be opened")
+      var sessions = newJArray()
+      for session in history.sessions:
+        sessions.add(session.toJson)
+      result = jsonResponse(200, sessions)
+    else:
+      result = errorResponse(404, "no such endpoint: " & path)
+
+  proc connectMqtt(config: MqttConfig): MqttClient =
+    ## Connects to the configured MQTT broker, if any. Failures are logged and
+    ## a closed client is returned.
+    if config.broker.len == 0: return
+    try:
+      result = connectMqtt(config.broker, config.clientId, config.topicPrefix,
+                           config.username, config.password,
+                           subscribe = config.commands)
+    except CatchableError as e:
+      stderr.writeLine("cannot connect to the MQTT broker at " & config.broker &
+                       ": " & e.msg)
+
+  var
+    mqttClient = connectMqtt(currentConfig.mqtt)
+    lastMqttAttempt = getMonoTime()
+  mqttClient.publish("state", $ekStateChanged.toJson(timer), retain = true)
+
+  var dbusService =
+    try: openDbusService()
+    except IOError as e:
+      stderr.writeLine("D-Bus service unavailable: " & e.msg)
+      DbusService()
+  var
+    lastState = (timer.state, timer.running)
+    lastSecond = timer.remainingTime.inSeconds
+
+  proc handleUserAction(line, source: string) =
+    ## Runs a command given through a channel replies can't be sent back on,
+    ## such as a signal or a key. Errors are logged and shown as a
+    ## notification.
+    let reply = handleCommandLine(line)
+    if not reply.startsWith("error: "): return
+    stderr.writeLine(source & ": " & reply)
+    try:
+      lastNotification =
+        notification(appName = "pomod", summary = "pomod: cannot " & line,
+                     body = reply["error: ".len .. ^1], hints = {
+                       "urgency": newVariant(1'u8)
+                     }.toTable, timeout = 5000)
+    except DbusException as e:
+      stderr.writeLine("cannot send a notification: " & e.msg)
+
+  proc handleDbusCall(command: Command) =
+    # errors are passed on to the caller
+    discard timer.execute(command)
 
   # set up the signal trap, so that when we call ``kill -USR1 pomod`` the
-  # program doesn't stop
+  # program doesn't stop. all real-time signals are trapped, so that unmapped
+  # ones are ignored instead of killing pomod
+  var
+    sigRtMin {.importc: "SIGRTMIN", header: "<signal.h>".}: cint
+    sigRtMax {.importc: "SIGRTMAX", header: "<signal.h>".}: cint
   discard sighold(SIGUSR1)
   discard sighold(SIGUSR2)
+  discard sighold(SIGHUP)
+  discard sighold(SIGTERM)
+  discard sighold(SIGINT)
+  for signal in sigRtMin..sigRtMax:
+    discard sighold(signal)
+
+  proc handleRealtimeSignal(signal: cint) =
+    ## Runs the command mapped to SIGRTMIN+n, where n is the signal's offset.
+    let
+      offset = signal - sigRtMin
+      signals = currentConfig.signals
+    if offset >= signals.len or signals[offset].len == 0:
+      stderr.writeLine("ignoring unmapped signal SIGRTMIN+" & $offset)
+      return
+    handleUserAction(signals[offset], "SIGRTMIN+" & $offset)
+
+  var terminal: Terminal
+  if options.interactive:
+    terminal = enterRawMode()
+    stderr.writeLine(KeyHelp)
+  let overwriteLine = options.interactive and isatty(STDOUT_FILENO) != 0
+  # writing to a FIFO without a reader must not kill pomod
+  discard signal(SIGPIPE, SIG_IGN)
+  var outputSinks = openSinks(currentConfig, overwrite = overwriteLine)
+  # the title goes to whichever of stdout and stderr is the terminal, as stdout
+  # is usually read by a bar
+  let titleFile =
+    if not currentConfig.terminalTitle: nil
+    elif isatty(STDOUT_FILENO) != 0: stdout
+    elif isatty(STDERR_FILENO) != 0: stderr
+    else: nil
+  var lastTitle = ""
+
+  proc writeStatusFile() =
+    ## Writes the timer's status to the status file, for tools that can't
+    ## talk to a socket.
+    try: writeAtomically(statusFile(), $timer.statusJson)
+    except IOError, OSError:
+      stderr.writeLine("cannot write the status file: " &
+                       getCurrentExceptionMsg())
+
+  proc saveTimerState() =
+    try: timer.saveState()
+    except IOError, OSError:
+      stderr.writeLine("cannot save the timer's state: " &
+                       getCurrentExceptionMsg())
+    stateUnsaved = false
+
+  proc shutdown() =
+    ## Saves the timer's state, prints a final status line, and releases
+    ## everything pomod holds, then exits.
+    saveTimerState()
+    journal.write(jeShutdown, timer)
+    journal.close()
+    recordSessions()
+    history.close()
+    terminal.restore()
+    if lastNotification != 0:
+      try: closeNotification(lastNotification)
+      except DbusException: discard
+    controlServer.close()
+    tcpControlServer.close()
+    eventServer.close()
+    jsonEvents.close()
+    commandFifo.close()
+    httpServer.close()
+    mqttClient.close()
+    for sink in outputSinks:
+      sink.close(TimerSnapshot(timer: timer))
+    if titleFile != nil:
+      titleFile.write(clearProgressSequence())
+      titleFile.flushFile()
+    removeFile(statusFile())
+    quit(QuitSuccess)
+
+  proc remind(track: var ReminderTrack, reminder: ReminderConfig,
+              delta: Duration, counting: bool) =
+    ## Moves a reminder's track forward, showing the reminder when it's due and
+    ## taking it down once it's over.
+    case track.advance(reminder, delta, counting)
+    of reNone: discard
+    of reDue:
+      let timeout =
+        if reminder.length > DurationZero: reminder.length.inMilliseconds
+        else: 5000
+      track.notification = notify("pomod: " & reminder.name,
+                                  reminder.message, timeout = int(timeout))
+    of reOver:
+      if track.notification != 0:
+        try: closeNotification(track.notification)
+        except DbusException: discard
+        track.notification = 0
 
+  proc endedPomodoros(timer: Timer): int =
+    ## Returns the amount of pomodoros that have ended one way or another.
+    result = timer.stats.pomodorosCompleted + timer.stats.pomodorosSkipped +
+             timer.stats.pomodorosCutShort + timer.stats.pomodorosAbandoned
+
+  var
+    lastAdapted = timer.endedPomodoros
+    lastCompleted = timer.stats.pomodorosCompleted
+    lastWallTime = now()
+    lastDay, warnedDay = ""
+    microBreak: ReminderTrack
+    reminderTracks: seq[ReminderTrack]
+    lastLoop = getMonoTime()
+
+  writeStatusFile()
   while true:
     block catchSignals:
       # pomod is controlled using signals USR1 and USR2.
-      # USR1 toggles the timer, and USR2 resets it.
+      # USR1 toggles the timer, and USR2 resets it. HUP reloads the config,
+      # and the real-time signals run the commands mapped to them. TERM and
+      # INT shut pomod down gracefully.
       var
         signals: SigSet
         info: SigInfo
-        timespec = Timespec(tv_nsec: 250 * 1_000_000)
+        tick = timer.config.tickInterval.inMilliseconds
+        timespec = Timespec(tv_sec: posix.Time(clong(tick div 1000)),
+                            tv_nsec: int(tick mod 1000) * 1_000_000)
       discard sigemptyset(signals)
       discard sigaddset(signals, SIGUSR1)
       discard sigaddset(signals, SIGUSR2)
+      discard sigaddset(signals, SIGHUP)
+      discard sigaddset(signals, SIGTERM)
+      discard sigaddset(signals, SIGINT)
+      for signal in sigRtMin..sigRtMax:
+        discard sigaddset(signals, signal)
       let signal = sigtimedwait(signals, info, timespec)
-      if signal == SIGUSR1: timer.toggle()
+      if signal == SIGUSR1: handleUserAction("toggle", "SIGUSR1")
       elif signal == SIGUSR2: timer.reset()
+      elif signal == SIGHUP: timer.reloadConfig()
+      elif signal == SIGTERM or signal == SIGINT: shutdown()
+      elif signal in sigRtMin..sigRtMax: handleRealtimeSignal(signal)
 
+    controlServer.poll(handleCommandLine)
+    tcpControlServer.poll(handleCommandLine)
+    eventServer.poll()
+    commandFifo.poll(handleCommandLine)
+    httpServer.poll(handleHttpRequest)
+    if options.interactive:
+      for key in readKeys():
+        let (action, line) = key.keyAction
+        case action
+        of kaNone: discard
+        of kaCommand: handleUserAction(line, "key " & key)
+        of kaQuit: shutdown()
+    dbusService.poll(timer, handleDbusCall)
+    if currentConfig.mqtt.broker.len > 0 and not mqttClient.isOpen and
+       getMonoTime() - lastMqttAttempt > initDuration(minutes = 1):
+      # the broker went away, try to reconnect every once in a while
+      mqttClient = connectMqtt(currentConfig.mqtt)
+      lastMqttAttempt = getMonoTime()
+      if mqttClient.isOpen:
+        mqttClient.publish("state", $ekStateChanged.toJson(timer),
+                           retain = true)
+    mqttClient.poll(handleCommandLine)
     timer.poll()
 
+    var index = 0
+    while index < namedTimers.len:
+      namedTimers[index].timer.poll()
+      # named timers are left out of the history
+      namedTimers[index].timer.stats.endedSessions.setLen(0)
+      if not namedTimers[index].isOver:
+        inc index
+        continue
+      let name = namedTimers[index].name
+      namedTimers.delete(index)
+      let id = notify("pomod: " & name, "time's up", urgency = 2,
+                      playSound = true)
+      if id != 0: lastNotification = id
+
+    recordSessions()
+
+    if timer.stats.pomodorosCompleted > lastCompleted:
+      let completed = timer.stats.pomodorosCompleted - lastCompleted
+      # completed pomodoros count towards the task they were spent on
+      let i = taskList.find(currentTask)
+      if i >= 0:
+        taskList.tasks[i].actual += completed
+        saveTasks()
+      dayLog.record(timer.stats.day, completed)
+      try: dayLog.save()
+      except IOError, OSError:
+        stderr.writeLine("cannot save the day log: " &
+                         getCurrentExceptionMsg())
+      lastDay = ""  # the streak might've grown
+      let goal = timer.config.dailyGoal
+      if goal > 0 and timer.completedToday >= goal and
+         timer.completedToday - completed < goal:
+        discard notify("pomod: daily goal reached",
+                       "that's " & $goal & " pomodoros today, well done!",
+                       playSound = true)
+      if timer.limitReached and
+         timer.completedToday - completed < timer.config.dailyLimit:
+        discard notify("pomod: that's it for today",
+                       "no more pomodoros begin today, unless you override " &
+                       "the limit")
+    lastCompleted = timer.stats.pomodorosCompleted
+
+    if currentConfig.adaptive.enabled and
+       timer.endedPomodoros - lastAdapted >= currentConfig.adaptive.window:
+      # judge each window of pomodoros once, so that the same ones don't
+      # keep nudging the lengths further
+      lastAdapted = timer.endedPomodoros
+      let before = timer.config.profile.pomodoroTime
+      timer.adapt(currentConfig.adaptive)
+      if timer.config.profile.pomodoroTime != before:
+        # resets keep the adapted lengths, until the profile's switched
+        currentConfig.profile = timer.config.profile
+        stderr.writeLine("adapted pomodoros to " &
+                         timer.config.profile.pomodoroTime.hoursAndMinutes)
+
+    let
+      wallTime = now()
+      quiet = currentConfig.quietHours.quietMode(wallTime).isSome
+    for start in currentConfig.autoStarts:
+      if quiet or timer.running or not start.isDue(lastWallTime, wallTime):
+        continue
+      # an idle timer starts a pomodoro, and a paused one picks up where it
+      # was left
+      timer.start()
+      if timer.running:
+        let id = notify("pomod: scheduled start",
+                        "the " & timer.state.name & " has begun")
+        if id != 0: lastNotification = id
+    let dailySummary = currentConfig.dailySummary
+    if dailySummary.to.len > 0 and
+       dailySummary.schedule.isDue(lastWallTime, wallTime):
+      mailDailySummary(wallTime)
+    let summaryError = summaryDelivery.check()
+    if summaryError.len > 0:
+      stderr.writeLine("cannot send the daily summary: " & summaryError)
+    lastWallTime = wallTime
+
+    let
+      today = wallTime.dayOf(timer.config.dayStart)
+      # without a goal, any pomodoro at all keeps the streak going
+      needed = max(timer.config.dailyGoal, 1)
+    if today != lastDay:
+      timer.stats.streak = dayLog.streak(today, needed)
+      lastDay = today
+    # minutes are counted from when the day began, which may not be midnight
+    let
+      warning = timer.config.streakWarning
+      dayStart = int(timer.config.dayStart.inMinutes)
+      clock = wallTime.hour * 60 + wallTime.minute
+      elapsed = (clock - dayStart + 1440) mod 1440
+    if warning.isSome and warnedDay != today and
+       elapsed >= (warning.get - dayStart + 1440) mod 1440 and
+       timer.stats.streak > 0 and dayLog.completedOn(today) < needed:
+      warnedDay = today
+      let left = needed - dayLog.completedOn(today)
+      discard notify("pomod: your streak is about to break",
+                     $left & " more pomodoros today keep your " &
+                     $timer.stats.streak & " day streak going", urgency = 2)
+
+    let
+      loopTime = getMonoTime()
+      delta = loopTime - lastLoop
+    lastLoop = loopTime
+    if currentConfig.microBreaks.enabled:
+      # breaks rest the eyes already, so each pomodoro starts counting afresh
+      if timer.state != tsPomodoro: microBreak.restart()
+      microBreak.remind(currentConfig.microBreaks, delta,
+                        counting = timer.state == tsPomodoro and
+                                   timer.running and not timer.overtime)
+    # reloading the config may have added or removed reminders
+    reminderTracks.setLen(currentConfig.reminders.len)
+    for i, reminder in currentConfig.reminders:
+      if reminder.enabled:
+        reminderTracks[i].remind(reminder, delta, counting = timer.running)
+
+    let statusChanged = (timer.state, timer.running) != lastState or
+                        timer.remainingTime.inSeconds != lastSecond
+    if (timer.state, timer.running) != lastState: stateUnsaved = true
+    if stateUnsaved and persisting():
+      journal.write(jeChanged, timer)
+      saveTimerState()
+    if (timer.state, timer.running) != lastState:
+      dbusService.emitStateChanged(timer)
+      httpServer.broadcast($ekStateChanged.toJson(timer))
+      mqttClient.publish("state", $ekStateChanged.toJson(timer), retain = true)
+      if options.tmuxRefresh: refreshTmux()
+      for event in transitionEvents(lastState[0], lastState[1], timer):
+        eventServer.broadcast($event.toJson(timer))
+        jsonEvents.write($event.toJson(timer))
+      lastState = (timer.state, timer.running)
+    if timer.remainingTime.inSeconds != lastSecond:
+      httpServer.broadcast($ekTick.toJson(timer))
+      eventServer.broadcast($ekTick.toJson(timer))
+      jsonEvents.write($ekTick.toJson(timer))
+      mqttClient.publish("remaining", $timer.remainingTime.inSeconds,
+                         retain = true)
+      lastSecond = timer.remainingTime.inSeconds
+    if statusChanged: writeStatusFile()
+
     block printOutput:
-      let
-        icon = timer.state.pomicon
-        minutes = timer.remainingTime.minutes
-        seconds = timer.remainingTime.seconds
-      echo fmt"{icon} {minutes:02}:{seconds:02}"
+      let snapshot = TimerSnapshot(timer: timer, hidden: outputHidden,
+                                   timers: namedTimers)
+      for sink in outputSinks:
+        sink.render(snapshot)
+      if titleFile != nil:
+        let title = timer.statusLine
+        if title != lastTitle:
+          titleFile.write(titleSequence(title) & timer.progressSequence)
+          titleFile.flushFile()
+          lastTitle = title
+
+    if options.count.isSome and
+       timer.stats.pomodorosCompleted - completedAtStart >= options.count.get:
+      # not remembered as the last notification, which would be closed on
+      # shutting down
+      discard notify("pomod: session complete",
+                     $options.count.get & " pomodoros done")
+      shutdown()
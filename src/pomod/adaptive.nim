@@ -0,0 +1,65 @@
+## Adaptive interval lengths, which nudge the pomodoro's length towards what
+## has been working lately: shorter when pomodoros keep getting cut short, and
+## longer when they're all seen through.
+
+import std/algorithm
+import std/options
+import std/times
+
+import config
+import timer
+
+proc median(lengths: seq[Duration]): Duration =
+  let sorted = lengths.sorted
+  result = sorted[sorted.len div 2]
+
+proc scaled(duration: Duration, scale: float): Duration =
+  ## Scales the duration, rounding it to whole seconds.
+  result = initDuration(seconds = int64(duration.inSeconds.float * scale))
+
+proc nudgedLength*(length: Duration, recent: openArray[PomodoroOutcome],
+                   adaptive: AdaptiveConfig): Duration =
+  ## Returns the pomodoro length the recent outcomes suggest. When too few of
+  ## them were completed, it's shortened to where the others were typically
+  ## cut short, by a step at the very least; when enough of them were, it
+  ## grows by a step. The length is rounded to whole minutes, and kept within
+  ## the configured bounds.
+  result = length
+  if recent.len == 0: return
+  var
+    completed = 0
+    cutShort: seq[Duration]
+  for outcome in recent:
+    if outcome.completed: inc completed
+    else: cutShort.add(outcome.focused)
+  let rate = completed / recent.len
+  if rate < adaptive.shortenBelow and cutShort.len > 0:
+    result = min(length - adaptive.step, cutShort.median)
+  elif rate >= adaptive.lengthenAbove:
+    result = length + adaptive.step
+  result = initDuration(minutes = result.inMinutes)
+  result = max(min(result, adaptive.maxPomodoro), adaptive.minPomodoro)
+
+proc adapt*(timer: var Timer, adaptive: AdaptiveConfig) =
+  ## Nudges the length of the timer's pomodoros by how the last ones went.
+  ## Breaks keep their proportion to the pomodoro, and the state going on
+  ## keeps its length. Profiles that don't have a pomodoro length to nudge,
+  ## such as custom schedules and flowtime, are left alone.
+  let profile = timer.config.profile
+  if profile.flowtime or profile.schedule.len > 0 or timer.config.stopwatch:
+    return
+  let
+    recent = timer.stats.recentPomodoros
+    window = recent[max(recent.len - adaptive.window, 0) .. ^1]
+    length = nudgedLength(profile.pomodoroTime, window, adaptive)
+  if length == profile.pomodoroTime: return
+  let
+    before = timer.stateTime
+    scale = length.inSeconds.float / profile.pomodoroTime.inSeconds.float
+  timer.config.profile.pomodoroTime = length
+  timer.config.profile.shortBreakTime = profile.shortBreakTime.scaled(scale)
+  timer.config.profile.longBreakTime = profile.longBreakTime.scaled(scale)
+  if timer.stateStartTime.isNone:
+    timer.remainingTime = timer.stateTime
+  else:
+    timer.extension += before - timer.stateTime
@@ -0,0 +1,19 @@
+## Scheduled starts, at which the timer starts by itself, eg. every weekday at
+## 09:00. They're checked against the wall clock on every tick.
+
+import std/times
+
+import config
+
+proc occurrence(start: AutoStart, day: DateTime): DateTime =
+  ## Returns the time the start is scheduled for on the given day.
+  result = dateTime(day.year, day.month, day.monthday, start.hour,
+                    start.minute, zone = local())
+
+proc isDue*(start: AutoStart, last, current: DateTime): bool =
+  ## Returns whether the start's time has come between the last check and the
+  ## current one. Checks that span midnight look at both days.
+  for day in [last, current]:
+    let time = start.occurrence(day)
+    if time.weekday in start.days and last < time and time <= current:
+      return true
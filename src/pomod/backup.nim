@@ -0,0 +1,124 @@
+## Backups of pomod's data: the config file, the history, and the timer's
+## state, archived together in a single tarball.
+
+import std/json
+import std/os
+import std/osproc
+import std/posix
+import std/streams
+import std/strutils
+import std/times
+
+import history
+import paths
+
+const BackupSchema* = 1 ## the version of the backup's layout
+
+type
+  BackupError* = object of CatchableError
+  BackupFile = object ## a file kept in backups
+    name: string              ## its name in the archive
+    path: string              ## where it's kept
+
+proc backupFiles(): seq[BackupFile] =
+  ## Returns the files that are backed up, the history aside.
+  result = @[
+    BackupFile(name: "config.toml", path: configFile()),
+    BackupFile(name: "state.json", path: stateFile()),
+    BackupFile(name: "tasks.json", path: tasksFile()),
+    BackupFile(name: "days.json", path: daysFile()),
+  ]
+
+proc tar(args: openArray[string]) =
+  ## Runs tar, raising a ``BackupError`` if it fails.
+  let process =
+    try:
+      startProcess("tar", args = args, options = {poUsePath, poStdErrToStdOut})
+    except OSError as e:
+      raise newException(BackupError, "cannot run tar: " & e.msg)
+  let output = process.outputStream.readAll
+  let code = process.waitForExit
+  process.close()
+  if code != 0:
+    raise newException(BackupError, "tar failed: " & output.strip)
+
+proc stagingDir(): string =
+  ## Creates an empty directory to put the archive's files together in.
+  result = getTempDir()/("pomod-backup-" & $getpid())
+  removeDir(result)
+  createDir(result)
+
+proc backup*(path: string) =
+  ## Archives pomod's data into a gzipped tarball at the given path. Files
+  ## that don't exist are left out. Raises a ``BackupError`` if the archive
+  ## can't be made.
+  let staging = stagingDir()
+  defer: removeDir(staging)
+  var names: seq[string]
+  for file in backupFiles():
+    if fileExists(file.path):
+      copyFile(file.path, staging/file.name)
+      names.add(file.name)
+  if fileExists(historyFile()):
+    let history =
+      try: openHistory()
+      except DbError as e:
+        raise newException(BackupError, "cannot open the history: " & e.msg)
+    try: history.copyTo(staging/"history.db")
+    except DbError as e:
+      raise newException(BackupError, "cannot copy the history: " & e.msg)
+    finally: history.close()
+    names.add("history.db")
+  let manifest = %*{
+    "schema": BackupSchema,
+    "history_schema": HistorySchema,
+    "created_at": now().format("yyyy-MM-dd'T'HH:mm:sszzz"),
+    "files": names,
+  }
+  writeFile(staging/"manifest.json", $manifest)
+  tar(@["-czf", path.absolutePath, "-C", staging, "manifest.json"] & names)
+
+proc verify(staging: string): seq[string] =
+  ## Checks that the extracted backup can be restored by this pomod, and
+  ## returns the files in it.
+  let manifest =
+    try: parseFile(staging/"manifest.json")
+    except IOError, JsonParsingError:
+      raise newException(BackupError, "not a pomod backup")
+  let schema = manifest{"schema"}.getInt(0)
+  if schema notin 1..BackupSchema:
+    raise newException(BackupError, "the backup was made by a newer pomod " &
+                       "(schema " & $schema & ")")
+  for name in manifest{"files"}.getElems:
+    result.add(name.getStr)
+  if "history.db" in result:
+    let version =
+      try: schemaOf(staging/"history.db")
+      except DbError as e:
+        raise newException(BackupError, "the history is broken: " & e.msg)
+    if version > HistorySchema:
+      raise newException(BackupError, "the history was made by a newer " &
+                         "pomod (schema " & $version & ")")
+  for name in result:
+    if name.endsWith(".json"):
+      try: discard parseFile(staging/name)
+      except IOError, JsonParsingError:
+        raise newException(BackupError, name & " is broken: " &
+                           getCurrentExceptionMsg())
+
+proc restore*(path: string): seq[string] =
+  ## Replaces pomod's data with the backup at the given path, and returns the
+  ## names of the files restored. Nothing is replaced if the backup can't be
+  ## read, or was made by a newer pomod; a ``BackupError`` is raised instead.
+  let staging = stagingDir()
+  defer: removeDir(staging)
+  tar(["-xzf", path.absolutePath, "-C", staging])
+  result = verify(staging)
+  ensureDirs()
+  for file in backupFiles():
+    if file.name in result:
+      copyFile(staging/file.name, file.path)
+  if "history.db" in result:
+    copyFile(staging/"history.db", historyFile())
+  # the journal describes the state that was just replaced
+  removeFile(journalFile())
@@ -0,0 +1,363 @@
+## Command line argument parsing.
+
+import std/options
+import std/os
+import std/parseopt
+import std/strutils
+import std/times
+
+import config
+import exchange
+import timer
+
+const
+  Usage* = """
+usage: pomod [options] [command]
+
+commands:
+  run                      run the timer (the default)
+  check-config             check the config file for errors
+  stats [period]           sum up the history of today, the week or the month
+  report --weekly          write a report of the week, to --output if given
+  export                   print the history, see --format and --since
+  import <file>            add an exported history, pomod's or another app's
+  sync [dir]               merge the history with other machines' through a
+                           synced directory, sync_dir by default
+  backup <file>            archive the config, history and state to a tarball
+  restore <file>           replace the config, history and state with a backup
+  i3blocks                 print the status as an i3blocks block
+  tmux                     print the status as a tmux status line segment
+  prompt                   print a short status for shell prompts
+  once <length>            count down once, eg. 12m or 90s, notify and exit
+  at <hh:mm>               count down to a time of day, notify and exit
+  stopwatch                run a stopwatch instead of the pomodoro timer
+  serve                    host timers for several users on the REST API
+  ctl <command>            send a command to the running pomod
+  start, pause, resume, toggle, reset, reset-interval, restart, reset-all,
+  skip, abandon, set-state <state>, interrupt <kind> [note], ack, reload,
+  status, override, set-profile <name>, task [text] [--tags <tags>],
+  task add <name>, task list, task remove <name>, set <setting> <value>,
+  extend [minutes],
+  set-remaining <mm:ss>, snooze [minutes], lap, next-profile, toggle-output,
+  timer <name> [length], cancel <name>, timers
+                           shorthands for ctl <command>
+
+options:
+  --pomodoro <minutes>     length of a pomodoro
+  --short-break <minutes>  length of a short break
+  --long-break <minutes>   length of a long break
+  --cycle <n>              the amount of short breaks before a long break
+  --profile <name>         the profile to start with
+  --preset <name>          start with a built-in preset: classic (25/5/30),
+                           desktime (52/17), ultradian (90/20) or flowtime
+  --tick <milliseconds>    how often the timer is updated and printed
+  --count <n>              start right away, and exit after n pomodoros
+  --prepare <seconds>      count down to get ready before pomodoros begin
+  --goal <n>               the amount of pomodoros to complete every day
+  --output <path>          write the status to a file or FIFO, not stdout;
+                           for report, a .md or .html file
+  --on-change              only print the status when it changes
+  --heartbeat <seconds>    with --on-change, print at least this often anyway
+  --template <template>    what the status line shows, see the readme
+  --show-cycle             show the position in the cycle, eg. 2/4
+  --icons <set>            use a built-in set of icons: nerd, ascii or emoji
+  --terminal-title         show the status in the terminal's title and taskbar
+  --color <when>           color the status in the terminal: auto, always or
+                           never
+  --precision <precision>  how the remaining time is shown: seconds (mm:ss),
+                           minutes (24m) or tenths (ss.t in the last minute)
+  --format <format>        how the status is printed: plain, waybar, polybar,
+                           lemonbar, xmobar or pango; for export, csv (the
+                           default) or json
+  --since <yyyy-mm-dd>     with export, leave out what's older than the day;
+                           with report, the week of the day
+  --tags <tags>            with task, tag the task, eg. backend,clientX
+  --tag <tag>              with stats, report and export, only count sessions
+                           tagged so
+  --fifo <path>            also read commands from the given FIFO
+  --http <host:port>       serve the REST API on the given address
+  --mqtt <host:port>       publish the timer's state to the given MQTT broker
+  -i, --interactive        control the timer with keys pressed in the terminal
+  --tmux-refresh           refresh tmux's status line when the state changes
+  --json-events            write events as JSON lines to file descriptor 3
+  --strict                 don't allow pausing or skipping pomodoros
+  --takeover               replace an already running pomod, continuing where
+                           it left off
+  --label <text>           what a one-shot countdown is for
+  --estimate <n>           with task add, the pomodoros the task should take
+  --json                   make status and stats reply with JSON
+  --heatmap                make stats draw the last year as a heatmap
+  -h, --help               show this help
+
+most options can also be set through POMOD_* environment variables, such as
+POMOD_POMODORO_MINUTES or POMOD_FIFO; see the readme for the full list.
+options given on the command line take precedence over the environment, which
+takes precedence over the config file.
+"""
+
+type
+  CliOptions* = object ## options parsed from the command line
+    command*: string          ## the subcommand, "run" if none was given
+    arguments*: seq[string]   ## positional arguments following the command
+    help*: bool
+    json*: bool               ## whether client commands should reply in JSON
+    exchangeFormat*: ExchangeFormat ## what the history is exported as
+    since*: Option[DateTime]  ## where the exported history begins
+    tags*: seq[string]        ## the tags of the task being set
+    tag*: Option[string]      ## the tag the history is filtered by
+    heatmap*: bool            ## whether stats should draw a heatmap
+    weekly*: bool             ## whether a weekly report was asked for
+    interactive*: bool        ## whether to read keys from stdin
+    takeover*: bool           ## whether to replace the running instance
+    tmuxRefresh*: bool        ## whether to refresh tmux on state changes
+    jsonEvents*: bool         ## whether to write events to fd 3
+    pomodoroTime*, shortBreakTime*, longBreakTime*: Option[Duration]
+    breakCycle*: Option[int]
+    count*: Option[int]       ## the amount of pomodoros to run before exiting
+    prepareTime*: Option[Duration]
+    estimate*: Option[int]    ## the pomodoros a task is expected to take
+    dailyGoal*: Option[int]
+    profile*: Option[string]
+    tickInterval*: Option[Duration]
+    outputFormat*: Option[OutputFormat]
+    statusTemplate*: Option[string]
+    precision*: Option[Precision]
+    iconSet*: Option[IconSet]
+    colorMode*: Option[ColorMode]
+    outputPath*: Option[string]
+    onChange*: bool
+    showCycle*: bool
+    terminalTitle*: bool
+    strict*: bool
+    heartbeat*: Option[Duration]
+    fifoPath*: Option[string]
+    httpAddress*: Option[string]
+    mqttBroker*: Option[string]
+    label*: string            ## the label of a one-shot countdown
+  CliError* = object of ValueError
+
+proc parseMinutes(option, value: string): Duration =
+  ## Parses a positive amount of minutes given to the option.
+  let minutes =
+    try: parseFloat(value)
+    except ValueError: -1.0
+  if minutes <= 0:
+    raise newException(CliError,
+                       option & " expects a positive number of minutes")
+  result = initDuration(milliseconds = int64(minutes * 60_000))
+
+proc parsePositiveInt(option, value: string): int =
+  ## Parses a positive integer given to the option.
+  result =
+    try: parseInt(value)
+    except ValueError: -1
+  if result <= 0:
+    raise newException(CliError, option & " expects a positive integer")
+
+proc parseSwitch(option, value: string): bool =
+  ## Parses a switch given to the option, eg. ``true`` or ``0``.
+  try: result = parseBool(value)
+  except ValueError:
+    raise newException(CliError, option & " expects true or false")
+
+proc parsePreset(option, value: string): string =
+  ## Checks that the value given to the option names a built-in preset.
+  if value.preset.isNone:
+    raise newException(CliError, option & " expects one of: " &
+                       PresetNames.join(", "))
+  result = value
+
+proc parseChoice[T: enum](option, value: string): T =
+  ## Parses one of the enum's string values given to the option.
+  var choices: seq[string]
+  for choice in T:
+    if value == $choice: return choice
+    choices.add($choice)
+  raise newException(CliError, option & " expects one of: " &
+                     choices.join(", "))
+
+proc parseTemplate(option, value: string): string =
+  ## Checks the status template given to the option.
+  try: checkTemplate(value)
+  except ValueError as e:
+    raise newException(CliError, option & ": " & e.msg)
+  result = value
+
+proc parseEnv*(): CliOptions =
+  ## Reads the options set through ``POMOD_*`` environment variables. Raises a
+  ## ``CliError`` on invalid values.
+  template env(name: string, body: untyped) =
+    if existsEnv(name):
+      let value {.inject.} = getEnv(name)
+      body
+
+  env "POMOD_POMODORO_MINUTES":
+    result.pomodoroTime = some(parseMinutes("POMOD_POMODORO_MINUTES", value))
+  env "POMOD_SHORT_BREAK_MINUTES":
+    result.shortBreakTime =
+      some(parseMinutes("POMOD_SHORT_BREAK_MINUTES", value))
+  env "POMOD_LONG_BREAK_MINUTES":
+    result.longBreakTime = some(parseMinutes("POMOD_LONG_BREAK_MINUTES", value))
+  env "POMOD_BREAK_CYCLE":
+    result.breakCycle = some(parsePositiveInt("POMOD_BREAK_CYCLE", value))
+  env "POMOD_PROFILE":
+    result.profile = some(value)
+  env "POMOD_PRESET":
+    result.profile = some(parsePreset("POMOD_PRESET", value))
+  env "POMOD_FIFO":
+    result.fifoPath = some(value)
+  env "POMOD_HTTP":
+    result.httpAddress = some(value)
+  env "POMOD_MQTT":
+    result.mqttBroker = some(value)
+  env "POMOD_OUTPUT_FORMAT":
+    result.outputFormat =
+      some(parseChoice[OutputFormat]("POMOD_OUTPUT_FORMAT", value))
+  env "POMOD_TEMPLATE":
+    result.statusTemplate = some(parseTemplate("POMOD_TEMPLATE", value))
+  env "POMOD_OUTPUT":
+    result.outputPath = some(value)
+  env "POMOD_ICONS":
+    result.iconSet = some(parseChoice[IconSet]("POMOD_ICONS", value))
+  env "POMOD_TICK_MS":
+    result.tickInterval =
+      some(initDuration(milliseconds = parsePositiveInt("POMOD_TICK_MS", value)))
+  env "POMOD_PRECISION":
+    result.precision = some(parseChoice[Precision]("POMOD_PRECISION", value))
+  env "POMOD_COLOR":
+    result.colorMode = some(parseChoice[ColorMode]("POMOD_COLOR", value))
+  env "POMOD_STRICT":
+    result.strict = parseSwitch("POMOD_STRICT", value)
+  env "POMOD_DAILY_GOAL":
+    result.dailyGoal = some(parsePositiveInt("POMOD_DAILY_GOAL", value))
+  env "POMOD_PREPARE_SECONDS":
+    result.prepareTime = some(initDuration(
+      seconds = parsePositiveInt("POMOD_PREPARE_SECONDS", value)))
+  env "POMOD_HEARTBEAT_SECONDS":
+    result.heartbeat = some(initDuration(
+      seconds = parsePositiveInt("POMOD_HEARTBEAT_SECONDS", value)))
+  env "POMOD_ON_CHANGE":
+    result.onChange = parseSwitch("POMOD_ON_CHANGE", value)
+  env "POMOD_SHOW_CYCLE":
+    result.showCycle = parseSwitch("POMOD_SHOW_CYCLE", value)
+  env "POMOD_TERMINAL_TITLE":
+    result.terminalTitle = parseSwitch("POMOD_TERMINAL_TITLE", value)
+  env "POMOD_COUNT":
+    result.count = some(parsePositiveInt("POMOD_COUNT", value))
+
+proc parseCli*(args = commandLineParams()): CliOptions =
+  ## Parses the given command line arguments on top of the options set in the
+  ## environment. Raises a ``CliError`` on invalid input.
+  result = parseEnv()
+  var parser = initOptParser(args, shortNoVal = {'h', 'i'},
+                             longNoVal = @["help", "json", "interactive",
+                                           "takeover", "tmux-refresh",
+                                           "on-change", "show-cycle",
+                                           "terminal-title", "json-events",
+                                           "strict", "heatmap", "weekly"])
+  var format: Option[(string, string)]  # the flag it was given as, and value
+  for kind, key, value in parser.getopt():
+    case kind
+    of cmdLongOption, cmdShortOption:
+      let flag = (if kind == cmdLongOption: "--" else: "-") & key
+      case key
+      of "h", "help": result.help = true
+      of "json": result.json = true
+      of "heatmap": result.heatmap = true
+      of "weekly": result.weekly = true
+      of "i", "interactive": result.interactive = true
+      of "takeover": result.takeover = true
+      of "tmux-refresh": result.tmuxRefresh = true
+      of "json-events": result.jsonEvents = true
+      of "on-change": result.onChange = true
+      of "show-cycle": result.showCycle = true
+      of "terminal-title": result.terminalTitle = true
+      of "strict": result.strict = true
+      of "output": result.outputPath = some(value)
+      of "label": result.label = value
+      of "heartbeat":
+        result.heartbeat =
+          some(initDuration(seconds = parsePositiveInt(flag, value)))
+      of "pomodoro": result.pomodoroTime = some(parseMinutes(flag, value))
+      of "short-break": result.shortBreakTime = some(parseMinutes(flag, value))
+      of "long-break": result.longBreakTime = some(parseMinutes(flag, value))
+      of "cycle": result.breakCycle = some(parsePositiveInt(flag, value))
+      of "count": result.count = some(parsePositiveInt(flag, value))
+      of "estimate": result.estimate = some(parsePositiveInt(flag, value))
+      of "goal": result.dailyGoal = some(parsePositiveInt(flag, value))
+      of "prepare":
+        result.prepareTime =
+          some(initDuration(seconds = parsePositiveInt(flag, value)))
+      of "profile": result.profile = some(value)
+      of "preset": result.profile = some(parsePreset(flag, value))
+      of "fifo": result.fifoPath = some(value)
+      of "http": result.httpAddress = some(value)
+      of "mqtt": result.mqttBroker = some(value)
+      of "tags": result.tags = parseTags(value)
+      of "tag": result.tag = some(value)
+      of "since":
+        result.since =
+          try: some(parse(value, "yyyy-MM-dd"))
+          except TimeParseError:
+            raise newException(CliError, flag & " expects a date, eg. " &
+                               "2024-01-01")
+      of "format": format = some((flag, value))
+      of "template": result.statusTemplate = some(parseTemplate(flag, value))
+      of "color": result.colorMode = some(parseChoice[ColorMode](flag, value))
+      of "icons": result.iconSet = some(parseChoice[IconSet](flag, value))
+      of "precision":
+        result.precision = some(parseChoice[Precision](flag, value))
+      of "tick":
+        result.tickInterval =
+          some(initDuration(milliseconds = parsePositiveInt(flag, value)))
+      else: raise newException(CliError, "unknown option: " & flag)
+    of cmdArgument:
+      if result.command.len == 0: result.command = key
+      else: result.arguments.add(key)
+    of cmdEnd: discard
+  if result.command.len == 0:
+    result.command = "run"
+  # the history is exported in formats of its own, so what the format means
+  # depends on the command, wherever it was given
+  if format.isSome:
+    let (flag, value) = format.get
+    if result.command == "export":
+      result.exchangeFormat = parseChoice[ExchangeFormat](flag, value)
+    else:
+      result.outputFormat = some(parseChoice[OutputFormat](flag, value))
+
+proc applyOverrides*(config: var Config, options: CliOptions) =
+  ## Overrides the active profile with durations given on the command line.
+  if options.pomodoroTime.isSome:
+    config.profile.pomodoroTime = options.pomodoroTime.get
+  if options.shortBreakTime.isSome:
+    config.profile.shortBreakTime = options.shortBreakTime.get
+  if options.longBreakTime.isSome:
+    config.profile.longBreakTime = options.longBreakTime.get
+  if options.breakCycle.isSome:
+    config.profile.breakCycle = options.breakCycle.get
+
+proc apply*(config: var Config, options: CliOptions) =
+  ## Applies all overrides given on the command line. Raises a
+  ## ``ConfigError`` if the profile doesn't exist.
+  if options.tickInterval.isSome: config.tickInterval = options.tickInterval.get
+  if options.outputFormat.isSome: config.outputFormat = options.outputFormat.get
+  if options.onChange: config.onChange = true
+  if options.outputPath.isSome:
+    config.outputPath = options.outputPath.get.expandTilde
+  if options.heartbeat.isSome: config.heartbeat = options.heartbeat.get
+  if options.precision.isSome: config.precision = options.precision.get
+  if options.iconSet.isSome: config.icons = options.iconSet.get.icons
+  if options.colorMode.isSome: config.colorMode = options.colorMode.get
+  if options.showCycle: config.statusTemplate = CycleTemplate
+  if options.terminalTitle: config.terminalTitle = true
+  if options.strict: config.strict = true
+  if options.prepareTime.isSome: config.prepareTime = options.prepareTime.get
+  if options.dailyGoal.isSome: config.dailyGoal = options.dailyGoal.get
+  if options.statusTemplate.isSome:
+    config.statusTemplate = options.statusTemplate.get
+  if options.fifoPath.isSome: config.fifoPath = options.fifoPath.get.expandTilde
+  if options.httpAddress.isSome: config.httpAddress = options.httpAddress.get
+  if options.mqttBroker.isSome: config.mqtt.broker = options.mqttBroker.get
+  if options.profile.isSome: config.selectProfile(options.profile.get)
+  config.applyOverrides(options)
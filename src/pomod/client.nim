@@ -0,0 +1,100 @@
+## The client side of the control socket.
+
+import std/json
+import std/net
+import std/options
+import std/os
+import std/strutils
+import std/times
+
+import commands
+import control
+import paths
+import timer
+
+type
+  StatusReply* = object ## the status reported by the running pomod
+    state*: TimerState
+    remaining*: Duration
+    running*: bool
+
+proc sendCommand*(line: string, path = controlSocket()): string =
+  ## Sends a command line to the running pomod and returns its reply. Raises an
+  ## ``OSError`` if nothing is listening on the socket.
+  let socket = newSocket(AF_UNIX, SOCK_STREAM, IPPROTO_IP)
+  defer: socket.close()
+  socket.connectUnix(path)
+  socket.send(line & "\n")
+  result = socket.recvLine()
+
+proc sendRemoteCommand*(line, address, token: string): string =
+  ## Sends a command line to a pomod listening on the given ``host:port``
+  ## address, authenticating with the token first. Raises an ``OSError`` if
+  ## the connection fails, or a ``ValueError`` if the address is malformed.
+  let (host, port) = parseAddress(address)
+  let socket = newSocket()
+  defer: socket.close()
+  socket.connect(host, port)
+  socket.send("auth " & token & "\n")
+  result = socket.recvLine()
+  if result != "ok": return
+  socket.send(line & "\n")
+  result = socket.recvLine()
+
+proc queryStatus*(path = controlSocket()): StatusReply =
+  ## Asks the running pomod for its status. Raises an ``OSError`` if it can't be
+  ## reached, or a ``ValueError`` if the reply is malformed.
+  let status =
+    try: parseJson(sendCommand("status json", path))
+    except JsonParsingError as e:
+      raise newException(ValueError, "malformed status: " & e.msg)
+  try:
+    result.state = parseState(status["state"].getStr)
+    result.remaining = initDuration(seconds = status["remaining"].getBiggestInt)
+    result.running = status["running"].getBool
+  except KeyError as e:
+    raise newException(ValueError, "malformed status: " & e.msg)
+
+proc runClient*(name: string, words: seq[string]): int =
+  ## Sends the command made up of the given words, prints the reply, and
+  ## returns the exit code. ``--json`` is accepted as a shorthand for passing
+  ## ``json`` to the command. With ``--host <host:port>``, the command is sent
+  ## over TCP, authenticated with ``--token`` or ``POMOD_TOKEN``. The name is
+  ## used for prefixing error messages.
+  var words = words
+  let
+    host = words.takeOption("--host").get("")
+    token = words.takeOption("--token").get("")
+  let json = words.find("--json")
+  if json >= 0:
+    words.delete(json)
+    words.add("json")
+  if host.len > 0:
+    let token = if token.len > 0: token else: getEnv("POMOD_TOKEN")
+    try:
+      let reply = sendRemoteCommand(words.join(" "), host, token)
+      if reply.startsWith("error: "):
+        stderr.writeLine(name & ": " & reply[len("error: ") .. ^1])
+        return QuitFailure
+      echo reply
+      return QuitSuccess
+    except OSError, ValueError:
+      stderr.writeLine(name & ": could not connect to pomod at " & host & ": " &
+                       getCurrentExceptionMsg())
+      return QuitFailure
+  let path = controlSocket()
+  if not fileExists(path):
+    stderr.writeLine(name & ": pomod doesn't seem to be running " &
+                     "(there's no control socket at " & path & ")")
+    return QuitFailure
+  try:
+    let reply = sendCommand(words.join(" "), path)
+    if reply.startsWith("error: "):
+      stderr.writeLine(name & ": " & reply[len("error: ") .. ^1])
+      return QuitFailure
+    echo reply
+    result = QuitSuccess
+  except OSError as e:
+    stderr.writeLine(name & ": could not connect to pomod at " & path & ": " &
+                     e.msg & " (is it still running?)")
+    result = QuitFailure
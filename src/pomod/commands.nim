@@ -0,0 +1,107 @@
+## Commands for controlling a running pomod.
+
+import std/options
+import std/strutils
+
+type
+  CommandKind* = enum ## what a command does
+    ckStart = "start"             ## start or resume the timer
+    ckPause = "pause"             ## pause the timer
+    ckResume = "resume"           ## resume a paused timer
+    ckToggle = "toggle"           ## start or pause, depending on the state
+    ckReset = "reset"             ## reset the timer to its initial state
+    ckResetInterval = "reset-interval"  ## put the current state's full length
+                                        ## back on the clock
+    ckRestart = "restart"         ## the same as reset-interval
+    ckResetAll = "reset-all"      ## the same as reset
+    ckSkip = "skip"               ## move on to the next state right away
+    ckAbandon = "abandon"         ## void the current pomodoro, starting it
+                                  ## over without counting it
+    ckInterrupt = "interrupt"     ## record an internal or external
+                                  ## interruption, optionally with a note
+    ckSetState = "set-state"      ## jump to the given state right away
+    ckAcknowledge = "ack"         ## end overtime and move on
+    ckLap = "lap"                 ## mark a lap of the stopwatch
+    ckSnooze = "snooze"           ## postpone a break that has just begun by
+                                  ## some minutes, 5 by default
+    ckStatus = "status"           ## reply with the timer's status, optionally
+                                  ## as JSON
+    ckSetProfile = "set-profile"  ## switch to a different profile
+    ckTask = "task"               ## set the task being worked on, or clear it
+    ckSet = "set"                 ## change a setting of the active profile
+    ckReload = "reload"           ## reload the config file
+    ckOverride = "override"       ## lift the daily limit for the rest of the
+                                  ## day
+    ckExtend = "extend"           ## add some minutes to the current state,
+                                  ## 5 by default
+    ckSetRemaining = "set-remaining"  ## put the given time on the clock
+    ckNextProfile = "next-profile"  ## switch to the next profile
+    ckToggleOutput = "toggle-output"  ## hide or show the status line
+    ckTimer = "timer"             ## start a named timer, counting down once
+                                  ## if given a length
+    ckCancel = "cancel"           ## stop and remove a named timer
+    ckTimers = "timers"           ## reply with the named timers, optionally
+                                  ## as JSON
+  Command* = object ## a parsed command
+    kind*: CommandKind
+    arguments*: seq[string]
+    timer*: string                ## the named timer the command is for, the
+                                  ## main one if empty
+  CommandError* = object of ValueError
+  StrictModeError* = object of CommandError ## a command strict mode forbids
+  LimitError* = object of CommandError ## a command past the daily limit
+
+const
+  # the minimum and maximum amount of arguments
+  Arity: array[CommandKind, Slice[int]] = [
+    ckStart: 0..0, ckPause: 0..0, ckResume: 0..0, ckToggle: 0..0,
+    ckReset: 0..0, ckResetInterval: 0..0, ckRestart: 0..0, ckResetAll: 0..0,
+    ckSkip: 0..0, ckAbandon: 0..0, ckInterrupt: 1..int.high, ckSetState: 1..1,
+    ckAcknowledge: 0..0, ckLap: 0..0, ckSnooze: 0..1,
+    ckStatus: 0..1, ckSetProfile: 1..1, ckTask: 0..int.high, ckSet: 2..2,
+    ckReload: 0..0, ckOverride: 0..0,
+    ckExtend: 0..1, ckSetRemaining: 1..1,
+    ckNextProfile: 0..0, ckToggleOutput: 0..0,
+    ckTimer: 1..2, ckCancel: 1..1, ckTimers: 0..1,
+  ]
+
+proc isCommand*(word: string): bool =
+  ## Returns whether the word names a command.
+  for kind in CommandKind:
+    if word == $kind: return true
+
+proc takeOption*(words: var seq[string], name: string): Option[string] =
+  ## Removes an option and its value from the words, and returns the value, if
+  ## the option is there.
+  let i = words.find(name)
+  if i >= 0 and i + 1 < words.len:
+    result = some(words[i + 1])
+    words.delete(i + 1)
+    words.delete(i)
+
+proc parseCommand*(line: string): Command =
+  ## Parses a command from a line of whitespace-separated words. The command
+  ## may be preceded by ``@name`` to send it to a named timer. Raises a
+  ## ``CommandError`` if the command is unknown or has the wrong amount of
+  ## arguments.
+  var words = line.splitWhitespace
+  if words.len > 0 and words[0].startsWith('@'):
+    result.timer = words[0][1..^1]
+    if result.timer.len == 0:
+      raise newException(CommandError, "expected a timer's name after @")
+    words.delete(0)
+  if words.len == 0:
+    raise newException(CommandError, "empty command")
+  try:
+    result.kind = parseEnum[CommandKind](words[0])
+  except ValueError:
+    raise newException(CommandError, "unknown command: " & words[0])
+  result.arguments = words[1..^1]
+  let arity = Arity[result.kind]
+  if result.arguments.len notin arity:
+    let expected =
+      if arity.a == arity.b: $arity.a
+      elif arity.b == int.high: "at least " & $arity.a
+      else: $arity.a & " to " & $arity.b
+    raise newException(CommandError,
+                       $result.kind & " expects " & expected & " argument(s)")
@@ -0,0 +1,1025 @@
+## Loading of pomod's configuration file.
+
+import std/options
+import std/os
+import std/strutils
+import std/tables
+import std/times
+
+import parsetoml
+
+import commands
+import paths
+
+type
+  IntervalKind* = enum ## the kind of a scheduled interval
+    ikFocus
+    ikBreak
+    ikLongBreak
+  Interval* = object ## one entry in a custom schedule
+    kind*: IntervalKind
+    duration*: Duration
+  Profile* = object ## a set of interval durations
+    pomodoroTime*: Duration
+    shortBreakTime*: Duration
+    longBreakTime*: Duration
+    breakCycle*: int          ## the amount of short breaks before a long break
+    schedule*: seq[Interval]  ## replaces the above cycle if not empty
+    flowtime*: bool           ## pomodoros count up until ended, and earn a
+                              ## break proportional to their length
+    flowtimeRatio*: float     ## how many times shorter the earned break is
+  Icons* = object ## the icons shown for each state
+    planned*, pomodoro*, shortBreak*, longBreak*: string
+  IconSet* = enum ## a built-in set of icons
+    isNerdFont = "nerd"       ## Nerd Font glyphs
+    isAscii = "ascii"         ## plain ASCII, for TTYs, SSH, and logs
+    isEmoji = "emoji"         ## color emoji, for when there's no patched font
+  Colors* = object ## the colors each state is shown in, where supported
+    planned*, pomodoro*, shortBreak*, longBreak*, paused*: string
+  MqttConfig* = object ## where and how to publish the timer's state
+    broker*: string           ## host:port of the broker; MQTT is off if empty
+    topicPrefix*: string
+    clientId*: string
+    username*, password*: string
+    commands*: bool           ## whether to accept commands over MQTT
+  OutputFormat* = enum ## how the status is printed
+    ofPlain = "plain"         ## the icon and countdown, for Polybar
+    ofWaybar = "waybar"       ## JSON for Waybar's custom modules
+    ofPolybar = "polybar"     ## the plain status wrapped in Polybar format tags
+    ofLemonbar = "lemonbar"   ## colored with lemonbar's %{F} tags
+    ofXmobar = "xmobar"       ## colored with xmobar's <fc> markup
+    ofPango = "pango"         ## colored with Pango markup
+  Precision* = enum ## how precisely the remaining time is shown
+    pSeconds = "seconds"      ## mm:ss
+    pMinutes = "minutes"      ## 24m
+    pTenths = "tenths"        ## mm:ss, and ss.t during the final minute
+  ColorMode* = enum ## when to color terminal output
+    cmAuto = "auto"           ## when stdout is a terminal
+    cmAlways = "always"
+    cmNever = "never"
+  GlyphStyle* = enum ## the characters the {glyph} placeholder is drawn with
+    gsBlocks = "blocks"       ## ▁▂▃▄▅▆▇█
+    gsBraille = "braille"     ## ⡀⡄⡆⡇⣇⣧⣷⣿
+  ProgressBar* = object ## how the {bar} and {glyph} placeholders look
+    width*: int               ## the amount of characters
+    filled*, empty*: string   ## the characters for elapsed and remaining time
+    glyph*: GlyphStyle
+  PolybarConfig* = object ## Polybar format tags and IPC
+    planned*, pomodoro*, shortBreak*, longBreak*, paused*: string
+                              ## format tags put before the status per state
+    ipcModule*: string        ## a custom/ipc module to push the status to
+  XrootConfig* = object ## setting the X root window's name, for dwm
+    enabled*: bool
+    append*: bool             ## keep the existing name, adding the status
+    separator*: string        ## put between the existing name and the status
+  ReminderConfig* = object ## a reminder shown every so often
+    name*: string             ## shown as the notification's summary
+    enabled*: bool
+    period*: Duration         ## the time between reminders
+    length*: Duration         ## how long a reminder lasts; if zero, it's over
+                              ## as soon as it's shown
+    message*: string
+  AdaptiveConfig* = object ## how pomodoro lengths adapt to recent history
+    enabled*: bool
+    window*: int              ## the amount of recent pomodoros looked at
+    shortenBelow*: float      ## shorten if fewer of them were completed
+    lengthenAbove*: float     ## lengthen if at least as many were completed
+    step*: Duration           ## the least a length is changed by
+    minPomodoro*, maxPomodoro*: Duration
+  AutoStart* = object ## a time of day at which the timer starts by itself
+    hour*, minute*: int
+    days*: set[WeekDay]       ## the days of the week it applies to
+  QuietMode* = enum ## what happens to notifications during quiet hours
+    qmLow = "low"             ## they're sent with a low urgency, without sound
+    qmSilent = "silent"       ## they're not sent at all
+  CrashRecovery* = enum ## what happens to a pomodoro pomod crashed during
+    crResume = "resume"       ## it carries on once pomod is started again
+    crInterrupt = "interrupt" ## it's recorded as cut short, and starts over
+  QuietHours* = object ## a period of the day during which pomod keeps quiet
+    start*, stop*: int        ## minutes since midnight; the period goes past
+                              ## midnight if it stops before it starts
+    days*: set[WeekDay]       ## the days the period begins on
+    notifications*: QuietMode
+  DailySummaryConfig* = object ## an end-of-day summary sent by email
+    schedule*: AutoStart      ## when it's sent
+    to*: string               ## who it's sent to; it isn't sent if empty
+    sender*: string           ## who it's sent from
+    smtp*: string             ## host:port of the SMTP server to send it
+                              ## through; if empty, it's piped to sendmail
+    username*, password*: string ## for the SMTP server, if it needs them
+    starttls*: bool           ## whether to encrypt the SMTP connection
+    sendmail*: string         ## the command it's piped to
+  UserConfig* = object ## a user of the server mode
+    name*: string             ## also names the directory their data is in
+    token*: string            ## what they authenticate with
+  SinkKind* = enum ## where a sink sends the status
+    skStdout = "stdout"
+    skFile = "file"           ## a file or FIFO
+    skXroot = "xroot"         ## the X root window's name
+    skPolybarIpc = "polybar_ipc"
+  SinkConfig* = object ## an extra place to send the status to
+    kind*: SinkKind
+    format*: OutputFormat
+    path*: string             ## for file sinks
+    module*: string           ## for Polybar IPC sinks
+    timer*: string            ## the named timer shown, the main one if empty
+  Config* = object ## pomod's configuration
+    tickInterval*: Duration   ## how often the timer is polled and printed
+    outputFormat*: OutputFormat
+    statusTemplate*: string   ## what the status line is made up of
+    idleTemplate*: Option[string]   ## replaces the template before starting
+    pausedTemplate*: Option[string] ## replaces the template while paused
+    precision*: Precision
+    colorMode*: ColorMode     ## when to color the plain status with ANSI codes
+    progressBar*: ProgressBar
+    terminalTitle*: bool      ## show the status in the terminal's title and
+                              ## taskbar
+    stopwatch*: bool          ## count up with laps instead of running
+                              ## pomodoros
+    strict*: bool             ## forbid pausing and skipping pomodoros
+    overtime*: bool           ## count up once time is up, until the user moves
+                              ## on
+    autoStartBreaks*: bool    ## start breaks as soon as pomodoros end
+    autoStartPomodoros*: bool ## start pomodoros as soon as breaks end
+    persistState*: bool       ## save the timer's state as it changes, and
+                              ## pick it up again on startup
+    crashRecovery*: CrashRecovery
+    prepareTime*: Duration    ## how long to get ready for before a started
+                              ## pomodoro begins
+    readyTemplate*: string    ## replaces the template while getting ready
+    dayStart*: Duration       ## the time of day at which a new day begins,
+                              ## for daily totals, streaks and statistics
+    dailyGoal*: int           ## the pomodoros to complete every day; none if
+                              ## zero
+    dailyLimit*: int          ## the pomodoros after which no more begin for
+                              ## the day; no limit if zero
+    streakWarning*: Option[int] ## when to warn that the streak is about to
+                                ## break, in minutes since midnight
+    outputPath*: string       ## a file or FIFO to write the status to instead
+                              ## of stdout, if not empty
+    sessionLog*: string       ## a work log completed sessions are appended
+                              ## to, if not empty
+    syncDir*: string          ## a directory the history is synced with other
+                              ## machines through, if not empty
+    onChange*: bool           ## only print the status when it changes
+    heartbeat*: Duration      ## when printing on change, print at least this
+                              ## often anyway; never if zero
+    fifoPath*: string         ## a FIFO to read commands from, if not empty
+    httpAddress*: string      ## where to serve the REST API, if not empty
+    controlAddress*: string   ## where to serve the control socket over TCP,
+                              ## if not empty
+    controlToken*: string     ## the token TCP clients have to present
+    mqtt*: MqttConfig
+    polybar*: PolybarConfig
+    xroot*: XrootConfig
+    sinks*: seq[SinkConfig]   ## sent the status besides the main output
+    microBreaks*: ReminderConfig ## short eye breaks during pomodoros
+    reminders*: seq[ReminderConfig] ## shown while the timer is running
+    adaptive*: AdaptiveConfig
+    autoStarts*: seq[AutoStart]
+    quietHours*: seq[QuietHours] ## no scheduled starts, and hushed
+                                 ## notifications
+    users*: seq[UserConfig]   ## the users ``pomod serve`` hosts timers for
+    dailySummary*: DailySummaryConfig
+    icons*: Icons
+    colors*: Colors
+    profile*: Profile         ## the active profile
+    profileName*: string      ## the name of the active profile
+    profiles*: OrderedTable[string, Profile]
+    weekdays*: array[WeekDay, string] ## profiles to use on given days, if any
+    signals*: seq[string]     ## the command lines run on SIGRTMIN+n, by n
+  ConfigError* = object of ValueError
+    key*: string              ## the dotted path of the offending key
+  ConfigIssue* = object ## a problem found by ``checkConfig``
+    line*: int                ## the line of the problem, 0 if unknown
+    message*: string
+    fatal*: bool              ## whether the config can't be loaded because of it
+
+const
+  DefaultProfileName* = "default"
+  DefaultTickMilliseconds = 250
+  OffDay* = "off"             ## disables pomodoros on a given weekday
+  # Nerd Font/Pomicons glyphs
+  DefaultIcons* = Icons(planned: "", pomodoro: "",
+                        shortBreak: "", longBreak: "")
+  ProfileKeys = ["pomodoro", "short_break", "long_break", "break_cycle",
+                 "schedule", "flowtime", "flowtime_ratio"]
+  AsciiIcons* = Icons(planned: "[-]", pomodoro: "[P]",
+                      shortBreak: "[b]", longBreak: "[B]")
+  EmojiIcons* = Icons(planned: "⏸", pomodoro: "🍅",
+                      shortBreak: "☕", longBreak: "🛌")
+  IconKeys = ["planned", "pomodoro", "short_break", "long_break"]
+  ColorKeys = ["planned", "pomodoro", "short_break", "long_break", "paused"]
+  DefaultColors* = Colors(planned: "#6272a4", pomodoro: "#ff5555",
+                          shortBreak: "#50fa7b", longBreak: "#50fa7b",
+                          paused: "#6272a4")
+  MqttKeys = ["broker", "topic_prefix", "client_id", "username", "password",
+              "commands"]
+  PolybarKeys = ["planned", "pomodoro", "short_break", "long_break", "paused",
+                 "ipc"]
+  ProgressBarKeys = ["width", "filled", "empty", "glyph"]
+  XrootKeys = ["enabled", "append", "separator"]
+  SinkKeys = ["kind", "format", "path", "module", "timer"]
+  MicroBreakKeys = ["enabled", "every", "length", "message"]
+  ReminderKeys = ["name", "enabled", "every", "length", "message"]
+  AutoStartKeys = ["at", "days"]
+  QuietHoursKeys = ["from", "to", "days", "notifications"]
+  DailySummaryKeys = ["at", "days", "to", "from", "smtp", "username",
+                      "password", "starttls", "sendmail"]
+  AdaptiveKeys = ["enabled", "window", "shorten_below", "lengthen_above",
+                  "step", "min_pomodoro", "max_pomodoro"]
+  DefaultAdaptive = AdaptiveConfig(window: 4, shortenBelow: 0.5,
+                                   lengthenAbove: 1.0,
+                                   step: initDuration(minutes = 5),
+                                   minPomodoro: initDuration(minutes = 15),
+                                   maxPomodoro: initDuration(minutes = 50))
+  # the 20-20-20 rule
+  DefaultMicroBreaks = ReminderConfig(name: "micro-break",
+                                      period: initDuration(minutes = 20),
+                                      length: initDuration(seconds = 20),
+                                      message: "Look at something 20 feet " &
+                                               "away for 20 seconds")
+  WeekdayKeys = ["monday", "tuesday", "wednesday", "thursday", "friday",
+                 "saturday", "sunday"]
+  TopLevelKeys = @ProfileKeys & @["tick_interval", "fifo", "http", "profile",
+                                  "profiles", "icons", "colors", "weekdays",
+                                  "mqtt", "signals", "control_tcp",
+                                  "control_token", "format", "polybar",
+                                  "xroot", "template", "on_change",
+                                  "heartbeat", "output", "precision",
+                                  "icon_set", "color", "progress_bar",
+                                  "show_cycle", "day_start", "terminal_title",
+                                  "idle_template", "paused_template", "sinks",
+                                  "auto_start_breaks", "auto_start_pomodoros",
+                                  "strict", "overtime", "micro_breaks",
+                                  "reminders", "adaptive", "auto_start",
+                                  "quiet_hours", "prepare", "ready_template",
+                                  "daily_goal", "streak_warning",
+                                  "daily_limit", "persist_state",
+                                  "crash_recovery", "session_log",
+                                  "sync_dir", "users", "daily_summary"]
+  DefaultTemplate* = "{icon} {remaining}{label_suffix}{profile_suffix}"
+  CycleTemplate* =
+    "{icon} {remaining} {position}{label_suffix}{profile_suffix}"
+    ## the default template with the cycle position shown
+  LabelTemplate* = "{icon} {remaining} {label}"
+    ## the default template for labelled one-shot timers
+  ReadyTemplate* = "{icon} get ready {ready}"
+    ## the default template while getting ready for a pomodoro
+  TemplatePlaceholders* = ["icon", "state", "mm", "ss", "remaining", "elapsed",
+                           "cycle", "total", "profile", "profile_suffix",
+                           "completed", "bar", "percent", "position",
+                           "today", "long_break", "glyph", "label", "lap",
+                           "laps", "timers", "ready", "interruptions",
+                           "label_suffix", "goal", "streak"]
+  DefaultSignals = @["next-profile", "skip", "extend 5", "toggle-output"]
+
+proc newConfigError(key, message: string): ref ConfigError =
+  result = newException(ConfigError, key & " " & message)
+  result.key = key
+
+proc defaultProfile*(): Profile =
+  ## Returns the classic 25/5/30 pomodoro profile.
+  result = Profile(pomodoroTime: initDuration(minutes = 25),
+                   shortBreakTime: initDuration(minutes = 5),
+                   longBreakTime: initDuration(minutes = 30),
+                   breakCycle: 4, flowtimeRatio: 5)
+
+const PresetNames* = ["classic", "desktime", "ultradian", "flowtime"]
+
+proc preset*(name: string): Option[Profile] =
+  ## Returns the built-in preset with the given name, if there is one.
+  case name
+  of "classic":
+    result = some(defaultProfile())
+  of "desktime":
+    # 52 minutes of work followed by 17 minutes of rest
+    result = some(Profile(pomodoroTime: initDuration(minutes = 52),
+                          shortBreakTime: initDuration(minutes = 17),
+                          longBreakTime: initDuration(minutes = 17),
+                          breakCycle: 4))
+  of "ultradian":
+    # 90 minute focus blocks following the body's ultradian rhythm
+    result = some(Profile(pomodoroTime: initDuration(minutes = 90),
+                          shortBreakTime: initDuration(minutes = 20),
+                          longBreakTime: initDuration(minutes = 20),
+                          breakCycle: 4))
+  of "flowtime":
+    # open-ended focus, with a fifth of it as a break
+    var profile = defaultProfile()
+    profile.flowtime = true
+    result = some(profile)
+  else:
+    result = none(Profile)
+
+proc defaultConfig*(): Config =
+  ## Returns the configuration used when no config file is present.
+  result = Config(tickInterval: initDuration(milliseconds =
+                                               DefaultTickMilliseconds),
+                  mqtt: MqttConfig(topicPrefix: "pomod", clientId: "pomod"),
+                  xroot: XrootConfig(separator: " | "),
+                  statusTemplate: DefaultTemplate,
+                  readyTemplate: ReadyTemplate,
+                  autoStartBreaks: true, autoStartPomodoros: true,
+                  persistState: true,
+                  microBreaks: DefaultMicroBreaks, adaptive: DefaultAdaptive,
+                  progressBar: ProgressBar(width: 8, filled: "▰", empty: "▱"),
+                  icons: DefaultIcons, colors: DefaultColors,
+                  profile: defaultProfile(),
+                  profileName: DefaultProfileName, signals: DefaultSignals)
+  result.profiles[DefaultProfileName] = defaultProfile()
+
+proc hasProfile*(config: Config, name: string): bool =
+  ## Returns whether there's a profile or preset with the given name.
+  result = name in config.profiles or name.preset.isSome
+
+proc selectProfile*(config: var Config, name: string) =
+  ## Makes the profile or built-in preset with the given name active. Profiles
+  ## defined in the config take precedence over presets. Raises a
+  ## ``ConfigError`` if there is no such profile.
+  if name in config.profiles:
+    config.profile = config.profiles[name]
+  elif name.preset.isSome:
+    config.profile = name.preset.get
+  else:
+    raise newConfigError("profile", "refers to a nonexistent profile: " & name)
+  config.profileName = name
+
+proc weekdayKey(day: WeekDay): string =
+  result = toLowerAscii($day)
+
+proc nextProfileName*(config: Config): string =
+  ## Returns the name of the profile following the active one, wrapping around
+  ## to the first profile. Presets are not included.
+  var found = false
+  for name in config.profiles.keys:
+    if found: return name
+    found = name == config.profileName
+  for name in config.profiles.keys:
+    return name
+
+proc getMinutes(toml: TomlValueRef, section, key: string,
+                default: Duration): Duration =
+  ## Reads a duration given in minutes from the table, or returns the default
+  ## if the key is not present.
+  if not toml.hasKey(key): return default
+  let minutes = toml[key].getFloat(-1.0)
+  if minutes <= 0:
+    raise newConfigError(section & key, "must be a positive number of minutes")
+  result = initDuration(milliseconds = int64(minutes * 60_000))
+
+proc getPositiveInt(toml: TomlValueRef, section, key: string,
+                    default: int): int =
+  ## Reads a positive integer from the table, or returns the default if the key
+  ## is not present.
+  if not toml.hasKey(key): return default
+  result = toml[key].getInt(-1)
+  if result <= 0:
+    raise newConfigError(section & key, "must be a positive integer")
+
+proc getString(toml: TomlValueRef, section, key: string,
+               default: string): string =
+  ## Reads a string from the table, or returns the default if the key is not
+  ## present.
+  if not toml.hasKey(key): return default
+  if toml[key].kind != TomlValueKind.String:
+    raise newConfigError(section & key, "must be a string")
+  result = toml[key].getStr()
+
+proc icons*(iconSet: IconSet): Icons =
+  ## Returns the icons of a built-in set.
+  result =
+    case iconSet
+    of isNerdFont: DefaultIcons
+    of isAscii: AsciiIcons
+    of isEmoji: EmojiIcons
+
+proc parseIcons(toml: TomlValueRef, default: Icons): Icons =
+  ## Reads the icon table. Icons that are not present are taken from the
+  ## default ones.
+  const section = "icons."
+  result.planned = toml.getString(section, "planned", default.planned)
+  result.pomodoro = toml.getString(section, "pomodoro", default.pomodoro)
+  result.shortBreak = toml.getString(section, "short_break", default.shortBreak)
+  result.longBreak = toml.getString(section, "long_break", default.longBreak)
+
+proc parseColors(toml: TomlValueRef): Colors =
+  ## Reads the color table. Colors that are not present are left at their
+  ## defaults.
+  const section = "colors."
+  result.planned = toml.getString(section, "planned", DefaultColors.planned)
+  result.pomodoro = toml.getString(section, "pomodoro", DefaultColors.pomodoro)
+  result.shortBreak =
+    toml.getString(section, "short_break", DefaultColors.shortBreak)
+  result.longBreak =
+    toml.getString(section, "long_break", DefaultColors.longBreak)
+  result.paused = toml.getString(section, "paused", DefaultColors.paused)
+
+proc parseLength*(text: string): Duration =
+  ## Parses a length in minutes, or in seconds or hours when suffixed with
+  ## ``s`` or ``h``. ``m`` may be given for minutes explicitly. Raises a
+  ## ``ValueError`` when the length is malformed.
+  var
+    length = text.strip
+    multiplier = 60.0
+  if length.len > 0 and length[^1] in {'s', 'm', 'h'}:
+    if length[^1] == 's': multiplier = 1.0
+    elif length[^1] == 'h': multiplier = 3600.0
+    length.setLen(length.len - 1)
+  let amount =
+    try: parseFloat(length)
+    except ValueError: -1.0
+  if amount <= 0:
+    raise newException(ValueError, "invalid length: " & text)
+  result = initDuration(milliseconds = int64(amount * multiplier * 1000))
+
+proc parseClockTime*(text: string): Duration =
+  ## Parses a duration written like on a clock, as ``mm:ss`` or ``h:mm:ss``,
+  ## or a length as understood by ``parseLength``. Raises a ``ValueError``
+  ## when it's malformed.
+  if ':' notin text: return parseLength(text)
+  let parts = text.strip.split(':')
+  if parts.len > 3:
+    raise newException(ValueError, "invalid time: " & text)
+  var seconds = 0
+  for i, part in parts:
+    let value =
+      try: parseInt(part)
+      except ValueError: -1
+    if value < 0 or (i > 0 and value >= 60):
+      raise newException(ValueError, "invalid time: " & text)
+    seconds = seconds * 60 + value
+  if seconds == 0:
+    raise newException(ValueError, "invalid time: " & text)
+  result = initDuration(seconds = seconds)
+
+proc parseInterval*(text: string): Interval =
+  ## Parses a schedule entry of the form ``kind:length``. The kind is one of
+  ## ``focus``, ``break``, or ``long_break``, and the length is as understood
+  ## by ``parseLength``. Raises a ``ValueError`` when the entry is malformed.
+  let parts = text.split(':')
+  if parts.len != 2:
+    raise newException(ValueError, "expected kind:length, got " & text)
+  result.kind =
+    case parts[0].strip
+    of "focus", "pomodoro": ikFocus
+    of "break", "short_break": ikBreak
+    of "long_break": ikLongBreak
+    else:
+      raise newException(ValueError, "unknown interval kind: " & parts[0])
+  result.duration = parseLength(parts[1])
+
+proc getSchedule(toml: TomlValueRef, section: string,
+                 default: seq[Interval]): seq[Interval] =
+  ## Reads a schedule (an array of ``kind:length`` strings) from the table, or
+  ## returns the default if the key is not present.
+  if not toml.hasKey("schedule"): return default
+  let schedule = toml["schedule"]
+  if schedule.kind != TomlValueKind.Array:
+    raise newConfigError(section & "schedule", "must be an array of strings")
+  for entry in schedule.getElems:
+    if entry.kind != TomlValueKind.String:
+      raise newConfigError(section & "schedule", "must be an array of strings")
+    try:
+      result.add(parseInterval(entry.getStr))
+    except ValueError as e:
+      raise newConfigError(section & "schedule", "is invalid: " & e.msg)
+
+proc getEnum[T: enum](toml: TomlValueRef, section, key: string,
+                     default: T): T =
+  ## Reads one of the enum's string values from the table, or returns the
+  ## default if the key is not present.
+  if not toml.hasKey(key): return default
+  let value = toml[key]
+  if value.kind == TomlValueKind.String:
+    for option in T:
+      if value.getStr == $option: return option
+  var options: seq[string]
+  for option in T:
+    options.add($option)
+  raise newConfigError(section & key, "must be one of: " & options.join(", "))
+
+proc checkTemplate*(text: string) =
+  ## Checks that the status template only uses known placeholders. Raises a
+  ## ``ValueError`` otherwise.
+  var i = 0
+  while i < text.len:
+    if text[i] == '{':
+      if i + 1 < text.len and text[i + 1] == '{':
+        i += 2
+        continue
+      let close = text.find('}', i)
+      if close < 0:
+        raise newException(ValueError, "unclosed placeholder at " & $i)
+      let name = text[i + 1 ..< close]
+      if name notin TemplatePlaceholders:
+        raise newException(ValueError, "unknown placeholder {" & name & "}")
+      i = close
+    inc i
+
+proc getBool(toml: TomlValueRef, section, key: string, default: bool): bool =
+  ## Reads a boolean from the table, or returns the default if the key is not
+  ## present.
+  if not toml.hasKey(key): return default
+  if toml[key].kind != TomlValueKind.Bool:
+    raise newConfigError(section & key, "must be true or false")
+  result = toml[key].getBool()
+
+proc getTable(toml: TomlValueRef, key: string): TomlValueRef =
+  ## Returns the subtable with the given key, or nil if it's not present.
+  if not toml.hasKey(key): return nil
+  result = toml[key]
+  if result.kind != TomlValueKind.Table:
+    raise newConfigError(key, "must be a table")
+
+proc parseMqtt(toml: TomlValueRef, default: MqttConfig): MqttConfig =
+  const section = "mqtt."
+  result.broker = toml.getString(section, "broker", default.broker)
+  result.topicPrefix =
+    toml.getString(section, "topic_prefix", default.topicPrefix)
+  result.clientId = toml.getString(section, "client_id", default.clientId)
+  result.username = toml.getString(section, "username", default.username)
+  result.password = toml.getString(section, "password", default.password)
+  result.commands = toml.getBool(section, "commands", default.commands)
+
+proc getSignals(toml: TomlValueRef, default: seq[string]): seq[string] =
+  ## Reads the signal mapping (an array of command lines, empty for unmapped
+  ## signals) from the table, or returns the default if the key is not present.
+  if not toml.hasKey("signals"): return default
+  let signals = toml["signals"]
+  if signals.kind != TomlValueKind.Array:
+    raise newConfigError("signals", "must be an array of strings")
+  for i, entry in signals.getElems:
+    if entry.kind != TomlValueKind.String:
+      raise newConfigError("signals", "must be an array of strings")
+    let line = entry.getStr.strip
+    if line.len > 0:
+      try: discard parseCommand(line)
+      except CommandError as e:
+        raise newConfigError("signals", "has an invalid command for SIGRTMIN+" &
+                             $i & ": " & e.msg)
+    result.add(line)
+
+proc parsePolybar(toml: TomlValueRef): PolybarConfig =
+  const section = "polybar."
+  result.planned = toml.getString(section, "planned", "")
+  result.pomodoro = toml.getString(section, "pomodoro", "")
+  result.shortBreak = toml.getString(section, "short_break", "")
+  result.longBreak = toml.getString(section, "long_break", "")
+  result.paused = toml.getString(section, "paused", "")
+  result.ipcModule = toml.getString(section, "ipc", "")
+
+proc parseProgressBar(toml: TomlValueRef, default: ProgressBar): ProgressBar =
+  const section = "progress_bar."
+  result.width = toml.getPositiveInt(section, "width", default.width)
+  result.filled = toml.getString(section, "filled", default.filled)
+  result.empty = toml.getString(section, "empty", default.empty)
+  result.glyph = toml.getEnum(section, "glyph", default.glyph)
+
+proc parseXroot(toml: TomlValueRef, default: XrootConfig): XrootConfig =
+  const section = "xroot."
+  result.enabled = toml.getBool(section, "enabled", default.enabled)
+  result.append = toml.getBool(section, "append", default.append)
+  result.separator = toml.getString(section, "separator", default.separator)
+
+proc parseMicroBreaks(toml: TomlValueRef,
+                      default: ReminderConfig): ReminderConfig =
+  const section = "micro_breaks."
+  result.name = default.name
+  result.enabled = toml.getBool(section, "enabled", true)
+  result.period = toml.getMinutes(section, "every", default.period)
+  result.length = initDuration(seconds =
+    toml.getPositiveInt(section, "length", int(default.length.inSeconds)))
+  result.message = toml.getString(section, "message", default.message)
+
+proc getRate(toml: TomlValueRef, section, key: string, default: float): float =
+  ## Reads a rate from 0 to 1 from the table, or returns the default if the key
+  ## is not present.
+  if not toml.hasKey(key): return default
+  result = toml[key].getFloat(-1.0)
+  if result notin 0.0..1.0:
+    raise newConfigError(section & key, "must be a number from 0 to 1")
+
+proc parseAdaptive(toml: TomlValueRef,
+                   default: AdaptiveConfig): AdaptiveConfig =
+  const section = "adaptive."
+  result.enabled = toml.getBool(section, "enabled", true)
+  result.window = toml.getPositiveInt(section, "window", default.window)
+  result.shortenBelow =
+    toml.getRate(section, "shorten_below", default.shortenBelow)
+  result.lengthenAbove =
+    toml.getRate(section, "lengthen_above", default.lengthenAbove)
+  result.step = toml.getMinutes(section, "step", default.step)
+  result.minPomodoro =
+    toml.getMinutes(section, "min_pomodoro", default.minPomodoro)
+  result.maxPomodoro =
+    toml.getMinutes(section, "max_pomodoro", default.maxPomodoro)
+  if result.minPomodoro > result.maxPomodoro:
+    raise newConfigError(section & "min_pomodoro",
+                         "must not be longer than max_pomodoro")
+
+proc getDays(toml: TomlValueRef, section: string): set[WeekDay] =
+  ## Reads an array of day names, or ``weekdays`` and ``weekends``, from the
+  ## table. Every day is included if the key is not present.
+  if not toml.hasKey("days"): return {dMon .. dSun}
+  let days = toml["days"]
+  if days.kind != TomlValueKind.Array:
+    raise newConfigError(section & "days", "must be an array of day names")
+  for entry in days.getElems:
+    let name = entry.getStr("")
+    case name
+    of "weekdays": result.incl({dMon .. dFri})
+    of "weekends": result.incl({dSat, dSun})
+    else:
+      block found:
+        for day in WeekDay:
+          if name == day.weekdayKey:
+            result.incl(day)
+            break found
+        raise newConfigError(section & "days", "has an unknown day: " & name)
+
+proc getTimeOfDay(toml: TomlValueRef, section, key: string): (int, int) =
+  ## Reads a time of day, such as ``09:00``, from the table as an hour and a
+  ## minute.
+  let text = toml.getString(section, key, "")
+  let clock =
+    try: parse(text, "H:mm")
+    except TimeParseError:
+      raise newConfigError(section & key, "must be a time of day, eg. " &
+                           "09:00, got " & text)
+  result = (clock.hour, clock.minute)
+
+proc parseDailySummary(toml: TomlValueRef): DailySummaryConfig =
+  const section = "daily_summary."
+  for key in ["at", "to"]:
+    if not toml.hasKey(key):
+      raise newConfigError(section & key, "is required")
+  let (hour, minute) = toml.getTimeOfDay(section, "at")
+  result.schedule = AutoStart(hour: hour, minute: minute,
+                              days: toml.getDays(section))
+  result.to = toml.getString(section, "to", "")
+  result.sender = toml.getString(section, "from", result.to)
+  result.smtp = toml.getString(section, "smtp", "")
+  if result.smtp.len > 0 and ':' notin result.smtp:
+    raise newConfigError(section & "smtp", "must be host:port, got " &
+                         result.smtp)
+  result.username = toml.getString(section, "username", "")
+  result.password = toml.getString(section, "password", "")
+  result.starttls = toml.getBool(section, "starttls", false)
+  result.sendmail = toml.getString(section, "sendmail", "sendmail -t")
+
+proc getAutoStarts(toml: TomlValueRef): seq[AutoStart] =
+  ## Reads the array of scheduled starts.
+  if not toml.hasKey("auto_start"): return
+  let starts = toml["auto_start"]
+  if starts.kind != TomlValueKind.Array:
+    raise newConfigError("auto_start", "must be an array of tables")
+  for i, table in starts.getElems:
+    let section = "auto_start." & $i & "."
+    if table.kind != TomlValueKind.Table:
+      raise newConfigError("auto_start", "must be an array of tables")
+    if not table.hasKey("at"):
+      raise newConfigError(section & "at", "is required")
+    let (hour, minute) = table.getTimeOfDay(section, "at")
+    result.add(AutoStart(hour: hour, minute: minute,
+                         days: table.getDays(section)))
+
+proc getUsers(toml: TomlValueRef): seq[UserConfig] =
+  ## Reads the array of the server mode's users.
+  if not toml.hasKey("users"): return
+  let users = toml["users"]
+  if users.kind != TomlValueKind.Array:
+    raise newConfigError("users", "must be an array of tables")
+  for i, table in users.getElems:
+    let section = "users." & $i & "."
+    if table.kind != TomlValueKind.Table:
+      raise newConfigError("users", "must be an array of tables")
+    for key in ["name", "token"]:
+      if not table.hasKey(key):
+        raise newConfigError(section & key, "is required")
+    let user = UserConfig(name: table.getString(section, "name", ""),
+                          token: table.getString(section, "token", ""))
+    # the name is used for a directory, so it mustn't lead out of it
+    if user.name.len == 0 or
+       not user.name.allCharsInSet({'a'..'z', 'A'..'Z', '0'..'9', '_', '-'}):
+      raise newConfigError(section & "name", "may only have letters, " &
+                           "digits, - and _")
+    if user.token.len == 0:
+      raise newConfigError(section & "token", "must not be empty")
+    for other in result:
+      if other.name == user.name:
+        raise newConfigError(section & "name", "is taken by another user")
+      if other.token == user.token:
+        raise newConfigError(section & "token", "is taken by another user")
+    result.add(user)
+
+proc getQuietHours(toml: TomlValueRef): seq[QuietHours] =
+  ## Reads the array of quiet periods.
+  if not toml.hasKey("quiet_hours"): return
+  let periods = toml["quiet_hours"]
+  if periods.kind != TomlValueKind.Array:
+    raise newConfigError("quiet_hours", "must be an array of tables")
+  for i, table in periods.getElems:
+    let section = "quiet_hours." & $i & "."
+    if table.kind != TomlValueKind.Table:
+      raise newConfigError("quiet_hours", "must be an array of tables")
+    for key in ["from", "to"]:
+      if not table.hasKey(key):
+        raise newConfigError(section & key, "is required")
+    let
+      (startHour, startMinute) = table.getTimeOfDay(section, "from")
+      (stopHour, stopMinute) = table.getTimeOfDay(section, "to")
+    result.add(QuietHours(start: startHour * 60 + startMinute,
+                          stop: stopHour * 60 + stopMinute,
+                          days: table.getDays(section),
+                          notifications: table.getEnum(section,
+                                                       "notifications",
+                                                       qmLow)))
+
+proc getReminders(toml: TomlValueRef): seq[ReminderConfig] =
+  ## Reads the array of recurring reminders.
+  if not toml.hasKey("reminders"): return
+  let reminders = toml["reminders"]
+  if reminders.kind != TomlValueKind.Array:
+    raise newConfigError("reminders", "must be an array of tables")
+  for i, table in reminders.getElems:
+    let section = "reminders." & $i & "."
+    if table.kind != TomlValueKind.Table:
+      raise newConfigError("reminders", "must be an array of tables")
+    for key in ["every", "message"]:
+      if not table.hasKey(key):
+        raise newConfigError(section & key, "is required")
+    var reminder = ReminderConfig(
+      name: table.getString(section, "name", "reminder"),
+      enabled: table.getBool(section, "enabled", true),
+      period: table.getMinutes(section, "every", DurationZero),
+      message: table.getString(section, "message", ""))
+    if table.hasKey("length"):
+      reminder.length =
+        initDuration(seconds = table.getPositiveInt(section, "length", 1))
+    result.add(reminder)
+
+proc getSinks(toml: TomlValueRef, format: OutputFormat): seq[SinkConfig] =
+  ## Reads the array of extra sinks. Sinks without a format of their own use the
+  ## top-level one.
+  if not toml.hasKey("sinks"): return
+  let sinks = toml["sinks"]
+  if sinks.kind != TomlValueKind.Array:
+    raise newConfigError("sinks", "must be an array of tables")
+  for i, table in sinks.getElems:
+    let section = "sinks." & $i & "."
+    if table.kind != TomlValueKind.Table:
+      raise newConfigError("sinks", "must be an array of tables")
+    if not table.hasKey("kind"):
+      raise newConfigError(section & "kind", "is required")
+    var sink = SinkConfig(kind: table.getEnum(section, "kind", skStdout))
+    sink.format = table.getEnum(section, "format", format)
+    sink.path = table.getString(section, "path", "").expandTilde
+    sink.module = table.getString(section, "module", "")
+    sink.timer = table.getString(section, "timer", "")
+    if sink.kind == skFile and sink.path.len == 0:
+      raise newConfigError(section & "path", "is required for file sinks")
+    if sink.kind == skPolybarIpc and sink.module.len == 0:
+      raise newConfigError(section & "module",
+                           "is required for polybar_ipc sinks")
+    result.add(sink)
+
+proc parseProfile(toml: TomlValueRef, section: string,
+                  base: Profile): Profile =
+  ## Reads a profile from the table. Keys that are not present are taken from
+  ## the base profile.
+  result.pomodoroTime = toml.getMinutes(section, "pomodoro", base.pomodoroTime)
+  result.shortBreakTime =
+    toml.getMinutes(section, "short_break", base.shortBreakTime)
+  result.longBreakTime =
+    toml.getMinutes(section, "long_break", base.longBreakTime)
+  result.breakCycle = toml.getPositiveInt(section, "break_cycle", base.breakCycle)
+  result.schedule = toml.getSchedule(section, base.schedule)
+  result.flowtime = toml.getBool(section, "flowtime", base.flowtime)
+  result.flowtimeRatio = base.flowtimeRatio
+  if toml.hasKey("flowtime_ratio"):
+    result.flowtimeRatio = toml["flowtime_ratio"].getFloat(-1.0)
+    if result.flowtimeRatio <= 0:
+      raise newConfigError(section & "flowtime_ratio",
+                           "must be a positive number")
+
+proc loadConfig*(path = configFile()): Config =
+  ## Loads the configuration from the given file. If the file doesn't exist,
+  ## the default configuration is returned. Raises a ``ConfigError`` if any of
+  ## the values are invalid.
+  result = defaultConfig()
+  if not fileExists(path): return
+
+  let toml = parsetoml.parseFile(path)
+  # the top-level keys make up the default profile, which named profiles
+  # inherit from
+  let base = toml.parseProfile("", defaultProfile())
+  result.profiles[DefaultProfileName] = base
+  if toml.hasKey("profiles"):
+    let profiles = toml["profiles"]
+    if profiles.kind != TomlValueKind.Table:
+      raise newConfigError("profiles", "must be a table")
+    for name, table in profiles.getTable:
+      if table.kind != TomlValueKind.Table:
+        raise newConfigError("profiles." & name, "must be a table")
+      result.profiles[name] = table.parseProfile("profiles." & name & ".", base)
+  let tickMilliseconds = toml.getPositiveInt("", "tick_interval",
+                                             DefaultTickMilliseconds)
+  result.tickInterval = initDuration(milliseconds = tickMilliseconds)
+  result.fifoPath = toml.getString("", "fifo", "").expandTilde
+  result.httpAddress = toml.getString("", "http", "")
+  result.outputFormat = toml.getEnum("", "format", result.outputFormat)
+  if toml.getBool("", "show_cycle", false):
+    result.statusTemplate = CycleTemplate
+  result.statusTemplate = toml.getString("", "template", result.statusTemplate)
+  result.precision = toml.getEnum("", "precision", result.precision)
+  result.colorMode = toml.getEnum("", "color", result.colorMode)
+  result.terminalTitle =
+    toml.getBool("", "terminal_title", result.terminalTitle)
+  result.strict = toml.getBool("", "strict", result.strict)
+  result.overtime = toml.getBool("", "overtime", result.overtime)
+  result.autoStartBreaks =
+    toml.getBool("", "auto_start_breaks", result.autoStartBreaks)
+  result.autoStartPomodoros =
+    toml.getBool("", "auto_start_pomodoros", result.autoStartPomodoros)
+  if toml.hasKey("day_start"):
+    # either an hour, or a time of day such as 04:30
+    if toml["day_start"].kind == TomlValueKind.String:
+      let (hour, minute) = toml.getTimeOfDay("", "day_start")
+      result.dayStart = initDuration(hours = hour, minutes = minute)
+    else:
+      let hour = toml["day_start"].getInt(-1)
+      if hour notin 0..23:
+        raise newConfigError("day_start", "must be an hour from 0 to 23, " &
+                             "or a time of day, eg. 04:00")
+      result.dayStart = initDuration(hours = hour)
+  if toml.hasKey("daily_goal"):
+    result.dailyGoal = toml.getPositiveInt("", "daily_goal", 1)
+  result.persistState =
+    toml.getBool("", "persist_state", result.persistState)
+  result.crashRecovery =
+    toml.getEnum("", "crash_recovery", result.crashRecovery)
+  if toml.hasKey("daily_limit"):
+    result.dailyLimit = toml.getPositiveInt("", "daily_limit", 1)
+  if toml.hasKey("streak_warning"):
+    let (hour, minute) = toml.getTimeOfDay("", "streak_warning")
+    result.streakWarning = some(hour * 60 + minute)
+  result.onChange = toml.getBool("", "on_change", result.onChange)
+  if toml.hasKey("prepare"):
+    result.prepareTime =
+      initDuration(seconds = toml.getPositiveInt("", "prepare", 1))
+  result.outputPath = toml.getString("", "output", "").expandTilde
+  result.sessionLog = toml.getString("", "session_log", "").expandTilde
+  result.syncDir = toml.getString("", "sync_dir", "").expandTilde
+  if toml.hasKey("heartbeat"):
+    result.heartbeat =
+      initDuration(seconds = toml.getPositiveInt("", "heartbeat", 1))
+  try: checkTemplate(result.statusTemplate)
+  except ValueError as e:
+    raise newConfigError("template", "is invalid: " & e.msg)
+  for key in ["idle_template", "paused_template", "ready_template"]:
+    if not toml.hasKey(key): continue
+    let text = toml.getString("", key, "")
+    try: checkTemplate(text)
+    except ValueError as e:
+      raise newConfigError(key, "is invalid: " & e.msg)
+    case key
+    of "idle_template": result.idleTemplate = some(text)
+    of "paused_template": result.pausedTemplate = some(text)
+    else: result.readyTemplate = text
+  result.controlAddress = toml.getString("", "control_tcp", "")
+  result.controlToken = toml.getString("", "control_token", "")
+  if result.controlAddress.len > 0 and result.controlToken.len == 0:
+    raise newConfigError("control_tcp", "requires control_token to be set")
+  result.icons = toml.getEnum("", "icon_set", isNerdFont).icons
+  if toml.hasKey("icons"):
+    if toml["icons"].kind != TomlValueKind.Table:
+      raise newConfigError("icons", "must be a table")
+    result.icons = toml["icons"].parseIcons(result.icons)
+  if (let colors = toml.getTable("colors"); colors != nil):
+    result.colors = colors.parseColors()
+  if (let mqtt = toml.getTable("mqtt"); mqtt != nil):
+    result.mqtt = mqtt.parseMqtt(result.mqtt)
+  if (let polybar = toml.getTable("polybar"); polybar != nil):
+    result.polybar = polybar.parsePolybar()
+  if (let bar = toml.getTable("progress_bar"); bar != nil):
+    result.progressBar = bar.parseProgressBar(result.progressBar)
+  if (let xroot = toml.getTable("xroot"); xroot != nil):
+    result.xroot = xroot.parseXroot(result.xroot)
+  if (let microBreaks = toml.getTable("micro_breaks"); microBreaks != nil):
+    result.microBreaks = microBreaks.parseMicroBreaks(result.microBreaks)
+  result.reminders = toml.getReminders()
+  result.autoStarts = toml.getAutoStarts()
+  result.quietHours = toml.getQuietHours()
+  result.users = toml.getUsers()
+  if (let summary = toml.getTable("daily_summary"); summary != nil):
+    result.dailySummary = summary.parseDailySummary()
+  if (let adaptive = toml.getTable("adaptive"); adaptive != nil):
+    result.adaptive = adaptive.parseAdaptive(result.adaptive)
+  result.signals = toml.getSignals(result.signals)
+  result.sinks = toml.getSinks(result.outputFormat)
+  if toml.hasKey("profile"):
+    result.selectProfile(toml["profile"].getStr())
+  else:
+    result.selectProfile(DefaultProfileName)
+  if toml.hasKey("weekdays"):
+    let weekdays = toml["weekdays"]
+    if weekdays.kind != TomlValueKind.Table:
+      raise newConfigError("weekdays", "must be a table")
+    for day in WeekDay:
+      let
+        key = "weekdays." & day.weekdayKey
+        name = weekdays.getString("weekdays.", day.weekdayKey, "")
+      if name.len > 0 and name != OffDay and not result.hasProfile(name):
+        raise newConfigError(key, "refers to a nonexistent profile: " & name)
+      result.weekdays[day] = name
+
+proc loadConfigOrDefault*(path = configFile()): Config =
+  ## Loads the configuration like ``loadConfig``, falling back to the defaults
+  ## if the file is broken. For the commands that only need a setting or two,
+  ## which leave a broken config file for the daemon to complain about.
+  result =
+    try: loadConfig(path)
+    except CatchableError: defaultConfig()
+
+proc addUnknownKeys(table: TomlValueRef, section: string,
+                    known: openArray[string], unknown: var seq[string]) =
+  ## Adds the keys of the table that are not known to the list of unknown
+  ## keys.
+  if table.kind != TomlValueKind.Table: return
+  for key in table.getTable.keys:
+    if key notin known:
+      unknown.add(section & "." & key)
+
+proc unknownKeys(toml: TomlValueRef): seq[string] =
+  ## Returns the dotted paths of all keys pomod doesn't know about.
+  for key, value in toml.getTable:
+    case key
+    of "icons": value.addUnknownKeys(key, IconKeys, result)
+    of "colors": value.addUnknownKeys(key, ColorKeys, result)
+    of "weekdays": value.addUnknownKeys(key, WeekdayKeys, result)
+    of "mqtt": value.addUnknownKeys(key, MqttKeys, result)
+    of "polybar": value.addUnknownKeys(key, PolybarKeys, result)
+    of "xroot": value.addUnknownKeys(key, XrootKeys, result)
+    of "progress_bar": value.addUnknownKeys(key, ProgressBarKeys, result)
+    of "micro_breaks": value.addUnknownKeys(key, MicroBreakKeys, result)
+    of "adaptive": value.addUnknownKeys(key, AdaptiveKeys, result)
+    of "daily_summary": value.addUnknownKeys(key, DailySummaryKeys, result)
+    of "auto_start":
+      if value.kind != TomlValueKind.Array: continue
+      for i, start in value.getElems:
+        start.addUnknownKeys("auto_start." & $i, AutoStartKeys, result)
+    of "quiet_hours":
+      if value.kind != TomlValueKind.Array: continue
+      for i, period in value.getElems:
+        period.addUnknownKeys("quiet_hours." & $i, QuietHoursKeys, result)
+    of "reminders":
+      if value.kind != TomlValueKind.Array: continue
+      for i, reminder in value.getElems:
+        reminder.addUnknownKeys("reminders." & $i, ReminderKeys, result)
+    of "sinks":
+      if value.kind != TomlValueKind.Array: continue
+      for i, sink in value.getElems:
+        sink.addUnknownKeys("sinks." & $i, SinkKeys, result)
+    of "profiles":
+      if value.kind != TomlValueKind.Table: continue
+      for name, profile in value.getTable:
+        profile.addUnknownKeys("profiles." & name, ProfileKeys, result)
+    elif key notin TopLevelKeys:
+      result.add(key)
+
+proc keyLine(path, key: string): int =
+  ## Finds the line the dotted key is defined on, or returns 0 if it can't be
+  ## found. This doesn't understand every TOML construct, but it's good enough
+  ## for pointing the user at the right place.
+  var
+    section = ""
+    lineNumber = 0
+  for rawLine in lines(path):
+    inc lineNumber
+    let line = rawLine.strip
+    if line.startsWith('['):
+      section = line.strip(chars = {'[', ']', ' '})
+      if section == key: return lineNumber
+    elif '=' in line:
+      let
+        name = line.split('=', 1)[0].strip
+        fullKey = if section.len == 0: name else: section & "." & name
+      if fullKey == key: return lineNumber
+
+proc checkConfig*(path = configFile()): seq[ConfigIssue] =
+  ## Checks the config file for problems. Unknown keys are reported as
+  ## non-fatal issues.
+  if not fileExists(path): return
+  try:
+    let toml = parsetoml.parseFile(path)
+    for key in toml.unknownKeys:
+      result.add(ConfigIssue(line: path.keyLine(key),
+                             message: "unknown key " & key))
+    discard loadConfig(path)
+  except ConfigError as e:
+    result.add(ConfigIssue(line: path.keyLine(e.key), message: e.msg,
+                           fatal: true))
+  except TomlError as e:
+    # the parser's messages already carry the position of the error
+    result.add(ConfigIssue(message: e.msg, fatal: true))
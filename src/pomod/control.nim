@@ -0,0 +1,191 @@
+## The control socket, a Unix domain socket accepting line-based commands.
+## Every command line is answered with a single reply line.
+##
+## The same protocol can also be served over TCP, in which case clients must
+## authenticate by sending ``auth <token>`` before any commands.
+
+import std/nativesockets
+import std/net
+import std/os
+import std/posix
+import std/strutils
+
+const
+  MaxBacklog* = 64 * 1024
+    ## how much may be waiting to be sent to a subscriber before it's dropped
+  MaxLineLength = 16 * 1024
+    ## how long a command line may get before its client is dropped
+
+type
+  ControlClient = object
+    socket: Socket
+    buffer: string                ## received data without a full line yet
+    authenticated: bool
+  ControlServer* = object ## a listening control socket
+    socket: Socket
+    path: string                  ## empty for TCP servers
+    token: string                 ## required from clients if not empty
+    clients: seq[ControlClient]
+  LineHandler* = proc (line: string): string ## returns the reply to a line
+  Subscriber* = object ## a client that events are streamed to
+    socket*: Socket
+    backlog: string               ## data that's yet to be sent
+
+proc readable*(socket: Socket): bool =
+  ## Returns whether the socket can be read from (or accepted from) without
+  ## blocking.
+  var fds = @[socket.getFd]
+  result = selectRead(fds, timeout = 0) > 0
+
+proc initSubscriber*(socket: Socket): Subscriber =
+  ## Streams to the socket, which is made non-blocking.
+  socket.getFd.setBlocking(false)
+  result = Subscriber(socket: socket)
+
+proc flush*(subscriber: var Subscriber): bool =
+  ## Sends as much of the backlog as the socket takes without blocking. Returns
+  ## false if the subscriber has gone away, or has fallen so far behind that
+  ## the backlog outgrew ``MaxBacklog``.
+  while subscriber.backlog.len > 0:
+    let sent = send(subscriber.socket.getFd, subscriber.backlog[0].addr,
+                    subscriber.backlog.len, MSG_NOSIGNAL)
+    if sent < 0:
+      if errno == EAGAIN or errno == EWOULDBLOCK: break
+      return false
+    subscriber.backlog = subscriber.backlog[sent .. ^1]
+  result = subscriber.backlog.len <= MaxBacklog
+
+proc stream*(subscriber: var Subscriber, data: string): bool =
+  ## Queues the data to be sent to the subscriber and sends what it can.
+  ## Returns false if the subscriber should be dropped, as with ``flush``.
+  subscriber.backlog.add(data)
+  result = subscriber.flush()
+
+proc secureEquals*(a, b: string): bool =
+  ## Compares the strings in a time that only depends on their length, so
+  ## that a token can't be guessed a character at a time.
+  if a.len != b.len: return false
+  var difference = 0
+  for i in 0 ..< a.len:
+    difference = difference or (a[i].int xor b[i].int)
+  result = difference == 0
+
+proc parseAddress*(text: string): (string, Port) =
+  ## Parses an address of the form ``host:port``. Raises a ``ValueError`` if
+  ## it's malformed.
+  let colon = text.rfind(':')
+  if colon < 0:
+    raise newException(ValueError, "expected host:port, got " & text)
+  let port = parseInt(text[colon + 1 .. ^1])
+  if port notin 1..65535:
+    raise newException(ValueError, "invalid port: " & $port)
+  result = (text[0 ..< colon], Port(port))
+
+proc openControlServer*(path: string): ControlServer =
+  ## Opens a control socket at the given path, removing any stale socket left
+  ## behind at that path.
+  removeFile(path)
+  result.path = path
+  result.socket = newSocket(AF_UNIX, SOCK_STREAM, IPPROTO_IP, buffered = false)
+  result.socket.bindUnix(path)
+  result.socket.listen()
+
+proc activatedSockets*(): seq[SocketHandle] =
+  ## Returns the listening sockets passed in by systemd socket activation, if
+  ## any. The environment variables describing them are cleared, so that they
+  ## don't leak to child processes.
+  const FirstFd = 3  # SD_LISTEN_FDS_START
+  if getEnv("LISTEN_PID") != $getCurrentProcessId(): return
+  let count =
+    try: parseInt(getEnv("LISTEN_FDS"))
+    except ValueError: 0
+  for fd in FirstFd ..< FirstFd + count:
+    result.add(SocketHandle(fd))
+  delEnv("LISTEN_PID")
+  delEnv("LISTEN_FDS")
+  delEnv("LISTEN_FDNAMES")
+
+proc adoptControlServer*(handle: SocketHandle): ControlServer =
+  ## Serves the control socket on an already listening Unix socket, such as one
+  ## passed in by systemd. The socket file is left alone on closing, as it's
+  ## owned by whoever created it.
+  result.socket = newSocket(handle, AF_UNIX, SOCK_STREAM, IPPROTO_IP,
+                            buffered = false)
+
+proc openTcpControlServer*(address, token: string): ControlServer =
+  ## Opens a control server on the given ``host:port`` address. Clients have
+  ## to present the token before sending commands. Raises a ``ValueError`` if
+  ## the address is malformed, or an ``OSError`` if it can't be bound to.
+  let (host, port) = parseAddress(address)
+  result.token = token
+  result.socket = newSocket(buffered = false)
+  result.socket.setSockOpt(OptReuseAddr, true)
+  result.socket.bindAddr(port, host)
+  result.socket.listen()
+
+proc isOpen*(server: ControlServer): bool =
+  result = server.socket != nil
+
+proc close*(server: var ControlServer) =
+  ## Disconnects all clients and removes the socket.
+  if not server.isOpen: return
+  for client in server.clients:
+    client.socket.close()
+  server.clients.setLen(0)
+  server.socket.close()
+  server.socket = nil
+  if server.path.len > 0:
+    removeFile(server.path)
+
+proc acceptClients(server: var ControlServer) =
+  ## Accepts all pending connections.
+  while server.socket.readable:
+    var client: Socket
+    new(client)
+    server.socket.accept(client)
+    server.clients.add(ControlClient(socket: client))
+
+proc handleData(client: var ControlClient, token: string,
+                handler: LineHandler): bool =
+  ## Reads whatever data is available and replies to the complete lines in it.
+  ## Returns false if the client has disconnected, has failed to authenticate,
+  ## or has sent a line longer than ``MaxLineLength``.
+  var data: string
+  let count =
+    try: client.socket.recv(data, 4096)
+    except OSError: 0
+  if count <= 0: return false
+  client.buffer.add(data)
+  while true:
+    let newline = client.buffer.find('\n')
+    if newline < 0: break
+    let line = client.buffer[0 ..< newline].strip
+    client.buffer = client.buffer[newline + 1 .. ^1]
+    if line.len == 0: continue
+    if token.len > 0 and not client.authenticated:
+      # the very first line has to be the token
+      client.authenticated = secureEquals(line, "auth " & token)
+      let reply = if client.authenticated: "ok" else: "error: invalid token"
+      try: client.socket.send(reply & "\n")
+      except OSError: return false
+      if not client.authenticated: return false
+      continue
+    try:
+      client.socket.send(handler(line) & "\n")
+    except OSError:
+      return false
+  result = client.buffer.len <= MaxLineLength
+
+proc poll*(server: var ControlServer, handler: LineHandler) =
+  ## Accepts new connections and replies to the commands that have arrived,
+  ## without blocking.
+  if not server.isOpen: return
+  server.acceptClients()
+  var i = 0
+  while i < server.clients.len:
+    if server.clients[i].socket.readable and
+       not server.clients[i].handleData(server.token, handler):
+      server.clients[i].socket.close()
+      server.clients.delete(i)
+    else:
+      inc i
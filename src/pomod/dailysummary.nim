@@ -0,0 +1,147 @@
+## The daily summary, an email sent at the end of the day with what the day's
+## pomodoros came to. It's sent over SMTP, or piped to a sendmail command.
+##
+## Sending can take a while, or hang on an unresponsive server, so it's left to
+## a child process (``pomod send-summary``) which the daemon checks on every
+## tick, and kills once it's taken too long.
+
+import std/algorithm
+import std/json
+import std/monotimes
+import std/os
+import std/osproc
+import std/streams
+import std/strutils
+import std/tables
+import std/times
+
+import smtp
+
+import config
+import control
+import output
+import stats
+
+const SendTimeout = initDuration(minutes = 1)
+
+type
+  Delivery* = object ## a summary being sent in the background
+    process: Process
+    deadline: MonoTime
+
+proc topTags(summary: Summary, count = 3): seq[string] =
+  ## Returns the tags the most pomodoros were completed with, eg.
+  ## ``#client (4)``.
+  var tags: seq[string]
+  for tag in summary.byTag.keys:
+    tags.add(tag)
+  tags.sort do (a, b: string) -> int:
+    cmp(summary.byTag[b].completed, summary.byTag[a].completed)
+  for tag in tags[0 ..< min(count, tags.len)]:
+    result.add("#" & tag & " (" & $summary.byTag[tag].completed & ")")
+
+proc composeSummary*(summary: Summary, day: string,
+                     goal: int): tuple[subject, body: string] =
+  ## Writes the summary of the day's sessions, for sending.
+  let tags = summary.topTags
+  result.subject = "pomod: " & $summary.completed & " pomodoros on " & day
+  result.body = "pomodoros completed  " & $summary.completed &
+                (if goal > 0: " of " & $goal else: "") & "\n" &
+                "focus time           " & summary.focused.hoursAndMinutes &
+                "\n" &
+                "top tags             " &
+                (if tags.len == 0: "-" else: tags.join(", ")) & "\n"
+
+proc rfc5322Date(time: DateTime): string =
+  ## Formats the time for the Date header, eg.
+  ## ``Thu, 02 May 2024 18:00:00 +0200``.
+  result = time.format("ddd, dd MMM yyyy HH:mm:ss ") &
+           time.format("zzz").replace(":", "")
+
+proc deliver(config: DailySummaryConfig, message: string) =
+  ## Sends the message as configured, waiting until it's been sent. Raises an
+  ## ``IOError`` or ``OSError`` if it can't be.
+  if config.smtp.len > 0:
+    let (host, port) =
+      try: parseAddress(config.smtp)
+      except ValueError as e: raise newException(IOError, e.msg)
+    let client = newSmtp()
+    defer: client.close()
+    client.connect(host, port)
+    if config.starttls:
+      when defined(ssl): client.startTls()
+      else:
+        raise newException(IOError, "STARTTLS needs pomod to be built " &
+                           "with -d:ssl")
+    if config.username.len > 0:
+      client.auth(config.username, config.password)
+    client.sendMail(config.sender, @[config.to], message)
+  else:
+    let process = startProcess(config.sendmail,
+                               options = {poEvalCommand, poStdErrToStdOut})
+    process.inputStream.write(message)
+    process.inputStream.close()
+    let output = process.outputStream.readAll
+    let code = process.waitForExit
+    process.close()
+    if code != 0:
+      raise newException(IOError, config.sendmail & " failed: " &
+                         output.strip)
+
+proc sendSummary*(config: DailySummaryConfig,
+                  subject, body: string): Delivery =
+  ## Starts sending the summary as configured, without waiting for it to be
+  ## sent. Raises an ``OSError`` if the sending process can't be started.
+  let message = $createMessage(subject, body, @[config.to], @[], [
+    ("From", config.sender),
+    ("Date", now().rfc5322Date),
+    ("Content-Type", "text/plain; charset=utf-8"),
+  ])
+  # passed through stdin, so that the password doesn't show up in ps
+  let job = %*{
+    "sender": config.sender, "to": config.to,
+    "smtp": config.smtp, "starttls": config.starttls,
+    "username": config.username, "password": config.password,
+    "sendmail": config.sendmail, "message": message,
+  }
+  result = Delivery(
+    process: startProcess(getAppFilename(), args = ["send-summary"],
+                          options = {poStdErrToStdOut}),
+    deadline: getMonoTime() + SendTimeout)
+  result.process.inputStream.write($job)
+  result.process.inputStream.close()
+
+proc sending*(delivery: Delivery): bool =
+  ## Returns whether a summary is still being sent.
+  result = not delivery.process.isNil
+
+proc check*(delivery: var Delivery): string =
+  ## Checks on the summary being sent. Returns why it couldn't be sent once it
+  ## has failed or taken too long, and "" otherwise.
+  if not delivery.sending: return
+  let code = delivery.process.peekExitCode
+  if code == -1:
+    if getMonoTime() < delivery.deadline: return
+    delivery.process.kill()
+    discard delivery.process.waitForExit
+    result = "timed out after " & $SendTimeout.inSeconds & " seconds"
+  elif code != 0:
+    result = delivery.process.outputStream.readAll.strip
+    if result.len == 0: result = "exited with code " & $code
+  delivery.process.close()
+  delivery.process = nil
+
+proc runDelivery*(): int =
+  ## Sends the summary the daemon passed through stdin. Returns the exit code.
+  try:
+    let job = parseJson(stdin.readAll)
+    let config = DailySummaryConfig(
+      sender: job["sender"].getStr, to: job["to"].getStr,
+      smtp: job["smtp"].getStr, starttls: job["starttls"].getBool,
+      username: job["username"].getStr, password: job["password"].getStr,
+      sendmail: job["sendmail"].getStr)
+    config.deliver(job["message"].getStr)
+  except CatchableError as e:
+    stderr.writeLine(e.msg)
+    return QuitFailure
+  result = QuitSuccess
@@ -0,0 +1,280 @@
+## A session D-Bus service exposing the timer as ``org.pomod.Timer`` at
+## ``/org/pomod/Timer``.
+##
+## The dbus package only covers the client side of D-Bus, so the handful of
+## libdbus functions needed for serving are bound here directly.
+
+import commands
+import timer
+
+const
+  ServiceName = "org.pomod.Timer"
+  ObjectPath = "/org/pomod/Timer"
+  Interface = "org.pomod.Timer"
+  PropertiesInterface = "org.freedesktop.DBus.Properties"
+  IntrospectableInterface = "org.freedesktop.DBus.Introspectable"
+  IntrospectionXml = """
+<!DOCTYPE node PUBLIC "-//freedesktop//DTD D-BUS Object Introspection 1.0//EN"
+ "http://www.freedesktop.org/standards/dbus/1.0/introspect.dtd">
+<node>
+  <interface name="org.pomod.Timer">
+    <method name="Start"/>
+    <method name="Pause"/>
+    <method name="Resume"/>
+    <method name="Skip"/>
+    <method name="Reset"/>
+    <property name="State" type="s" access="read"/>
+    <property name="Remaining" type="x" access="read"/>
+    <property name="Running" type="b" access="read"/>
+    <property name="Profile" type="s" access="read"/>
+    <signal name="StateChanged">
+      <arg name="state" type="s"/>
+      <arg name="running" type="b"/>
+    </signal>
+  </interface>
+  <interface name="org.freedesktop.DBus.Properties">
+    <method name="Get">
+      <arg name="interface" direction="in" type="s"/>
+      <arg name="name" direction="in" type="s"/>
+      <arg name="value" direction="out" type="v"/>
+    </method>
+    <method name="GetAll">
+      <arg name="interface" direction="in" type="s"/>
+      <arg name="properties" direction="out" type="a{sv}"/>
+    </method>
+  </interface>
+  <interface name="org.freedesktop.DBus.Introspectable">
+    <method name="Introspect">
+      <arg name="xml" direction="out" type="s"/>
+    </method>
+  </interface>
+</node>
+"""
+
+# libdbus bindings
+
+const libdbus = "libdbus-1.so(|.3)"
+
+type
+  DBusConnection = pointer
+  DBusMessage = pointer
+  DBusBool = uint32
+  DBusError {.bycopy.} = object
+    name, message: cstring
+    dummy: cuint
+    padding: pointer
+  DBusMessageIter {.bycopy.} = object
+    # opaque; larger than and aligned like the real struct
+    storage: array[16, pointer]
+
+const
+  BusSession = 0.cint
+  NameFlagDoNotQueue = 4.cuint
+  NameReplyPrimaryOwner = 1.cint
+  MessageTypeMethodCall = 1.cint
+  TypeString = cint('s')
+  TypeInt64 = cint('x')
+  TypeBoolean = cint('b')
+  TypeVariant = cint('v')
+  TypeArray = cint('a')
+  TypeDictEntry = cint('e')
+
+{.push importc, dynlib: libdbus, cdecl.}
+proc dbus_error_init(error: ptr DBusError)
+proc dbus_error_free(error: ptr DBusError)
+proc dbus_error_is_set(error: ptr DBusError): DBusBool
+proc dbus_bus_get(kind: cint, error: ptr DBusError): DBusConnection
+proc dbus_bus_request_name(connection: DBusConnection, name: cstring,
+                           flags: cuint, error: ptr DBusError): cint
+proc dbus_connection_read_write(connection: DBusConnection,
+                                timeout: cint): DBusBool
+proc dbus_connection_pop_message(connection: DBusConnection): DBusMessage
+proc dbus_connection_send(connection: DBusConnection, message: DBusMessage,
+                          serial: ptr uint32): DBusBool
+proc dbus_connection_flush(connection: DBusConnection)
+proc dbus_message_get_type(message: DBusMessage): cint
+proc dbus_message_get_path(message: DBusMessage): cstring
+proc dbus_message_get_interface(message: DBusMessage): cstring
+proc dbus_message_get_member(message: DBusMessage): cstring
+proc dbus_message_new_method_return(message: DBusMessage): DBusMessage
+proc dbus_message_new_error(message: DBusMessage,
+                            name, text: cstring): DBusMessage
+proc dbus_message_new_signal(path, iface, name: cstring): DBusMessage
+proc dbus_message_unref(message: DBusMessage)
+proc dbus_message_iter_init(message: DBusMessage,
+                            iter: ptr DBusMessageIter): DBusBool
+proc dbus_message_iter_next(iter: ptr DBusMessageIter): DBusBool
+proc dbus_message_iter_get_arg_type(iter: ptr DBusMessageIter): cint
+proc dbus_message_iter_get_basic(iter: ptr DBusMessageIter, value: pointer)
+proc dbus_message_iter_init_append(message: DBusMessage,
+                                   iter: ptr DBusMessageIter)
+proc dbus_message_iter_append_basic(iter: ptr DBusMessageIter, kind: cint,
+                                    value: pointer): DBusBool
+proc dbus_message_iter_open_container(iter: ptr DBusMessageIter, kind: cint,
+                                      signature: cstring,
+                                      sub: ptr DBusMessageIter): DBusBool
+proc dbus_message_iter_close_container(iter,
+                                       sub: ptr DBusMessageIter): DBusBool
+{.pop.}
+
+# message building
+
+proc appendString(iter: ptr DBusMessageIter, value: string) =
+  var cstr = value.cstring
+  discard dbus_message_iter_append_basic(iter, TypeString, addr cstr)
+
+proc appendBool(iter: ptr DBusMessageIter, value: bool) =
+  var dbool = DBusBool(value)
+  discard dbus_message_iter_append_basic(iter, TypeBoolean, addr dbool)
+
+proc appendVariant(iter: ptr DBusMessageIter, timer: Timer,
+                   property: string): bool =
+  ## Appends the value of the given property wrapped in a variant. Returns
+  ## false if there's no such property.
+  var variant: DBusMessageIter
+  template openVariant(signature: string) =
+    discard dbus_message_iter_open_container(iter, TypeVariant, signature,
+                                             addr variant)
+  case property
+  of "State":
+    openVariant("s")
+    appendString(addr variant, timer.state.name)
+  of "Remaining":
+    openVariant("x")
+    var seconds = timer.remainingTime.inSeconds
+    discard dbus_message_iter_append_basic(addr variant, TypeInt64,
+                                           addr seconds)
+  of "Running":
+    openVariant("b")
+    appendBool(addr variant, timer.running)
+  of "Profile":
+    openVariant("s")
+    appendString(addr variant, timer.config.profileName)
+  else:
+    return false
+  discard dbus_message_iter_close_container(iter, addr variant)
+  result = true
+
+proc appendAllProperties(iter: ptr DBusMessageIter, timer: Timer) =
+  var dict: DBusMessageIter
+  discard dbus_message_iter_open_container(iter, TypeArray, "{sv}", addr dict)
+  for property in ["State", "Remaining", "Running", "Profile"]:
+    var entry: DBusMessageIter
+    discard dbus_message_iter_open_container(addr dict, TypeDictEntry, nil,
+                                             addr entry)
+    appendString(addr entry, property)
+    discard appendVariant(addr entry, timer, property)
+    discard dbus_message_iter_close_container(addr dict, addr entry)
+  discard dbus_message_iter_close_container(iter, addr dict)
+
+proc stringArgument(message: DBusMessage, index: int): string =
+  ## Returns the string argument at the given index, or "" if there's none.
+  var iter: DBusMessageIter
+  if dbus_message_iter_init(message, addr iter) == 0: return
+  for _ in 0 ..< index:
+    if dbus_message_iter_next(addr iter) == 0: return
+  if dbus_message_iter_get_arg_type(addr iter) == TypeString:
+    var cstr: cstring
+    dbus_message_iter_get_basic(addr iter, addr cstr)
+    result = $cstr
+
+# the service
+
+type
+  DbusService* = object ## a connection to the session bus owning our name
+    connection: DBusConnection
+  MethodHandler* = proc (command: Command)
+    ## executes the called method, raising a ``ValueError`` if it can't be
+
+proc openDbusService*(): DbusService =
+  ## Connects to the session bus and requests the service name. Raises an
+  ## ``IOError`` if the bus is unavailable or the name is taken.
+  var error: DBusError
+  dbus_error_init(addr error)
+  defer: dbus_error_free(addr error)
+  result.connection = dbus_bus_get(BusSession, addr error)
+  if dbus_error_is_set(addr error) != 0 or result.connection == nil:
+    raise newException(IOError, "cannot connect to the session bus: " &
+                       $error.message)
+  let reply = dbus_bus_request_name(result.connection, ServiceName,
+                                    NameFlagDoNotQueue, addr error)
+  if dbus_error_is_set(addr error) != 0:
+    raise newException(IOError, "cannot request " & ServiceName & ": " &
+                       $error.message)
+  if reply != NameReplyPrimaryOwner:
+    raise newException(IOError, ServiceName & " is already owned by someone " &
+                       "else")
+
+proc isOpen*(service: DbusService): bool =
+  result = service.connection != nil
+
+proc send(service: DbusService, message: DBusMessage) =
+  discard dbus_connection_send(service.connection, message, nil)
+  dbus_message_unref(message)
+
+proc reply(service: DbusService, call: DBusMessage, timer: Timer,
+           handler: MethodHandler) =
+  ## Replies to a method call. Calls that fail are answered with an error
+  ## carrying the reason.
+  let
+    path = $dbus_message_get_path(call)
+    iface = $dbus_message_get_interface(call)
+    member = $dbus_message_get_member(call)
+  var
+    response = dbus_message_new_method_return(call)
+    iter: DBusMessageIter
+  dbus_message_iter_init_append(response, addr iter)
+  template fail(name, text: string) =
+    dbus_message_unref(response)
+    response = dbus_message_new_error(call, name, text)
+  template run(kind: CommandKind) =
+    try: handler(Command(kind: kind))
+    except ValueError as e: fail("org.freedesktop.DBus.Error.Failed", e.msg)
+  if path != ObjectPath:
+    fail("org.freedesktop.DBus.Error.UnknownObject", path)
+    service.send(response)
+    return
+  case iface
+  of Interface:
+    case member
+    of "Start": run(ckStart)
+    of "Pause": run(ckPause)
+    of "Resume": run(ckResume)
+    of "Skip": run(ckSkip)
+    of "Reset": run(ckReset)
+    else: fail("org.freedesktop.DBus.Error.UnknownMethod", member)
+  of PropertiesInterface:
+    case member
+    of "Get":
+      if not appendVariant(addr iter, timer, call.stringArgument(1)):
+        fail("org.freedesktop.DBus.Error.UnknownProperty",
+              call.stringArgument(1))
+    of "GetAll": appendAllProperties(addr iter, timer)
+    else: fail("org.freedesktop.DBus.Error.UnknownMethod", member)
+  of IntrospectableInterface:
+    appendString(addr iter, IntrospectionXml)
+  else:
+    fail("org.freedesktop.DBus.Error.UnknownInterface", iface)
+  service.send(response)
+
+proc poll*(service: DbusService, timer: var Timer, handler: MethodHandler) =
+  ## Handles all pending method calls without blocking.
+  if not service.isOpen: return
+  discard dbus_connection_read_write(service.connection, 0)
+  while true:
+    let message = dbus_connection_pop_message(service.connection)
+    if message == nil: break
+    if dbus_message_get_type(message) == MessageTypeMethodCall:
+      service.reply(message, timer, handler)
+    dbus_message_unref(message)
+
+proc emitStateChanged*(service: DbusService, timer: Timer) =
+  ## Emits the StateChanged signal with the timer's current state.
+  if not service.isOpen: return
+  let signal = dbus_message_new_signal(ObjectPath, Interface, "StateChanged")
+  var iter: DBusMessageIter
+  dbus_message_iter_init_append(signal, addr iter)
+  appendString(addr iter, timer.state.name)
+  appendBool(addr iter, timer.running)
+  service.send(signal)
+  dbus_connection_flush(service.connection)
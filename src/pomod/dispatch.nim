@@ -0,0 +1,158 @@
+## Running commands on a timer, shared by the daemon and the server mode.
+## Only the timer itself is acted on; anything else a command does, such as
+## notifying the user, is left to the caller.
+
+import std/json
+import std/options
+import std/strutils
+import std/times
+
+import commands
+import config
+import httpapi
+import timer
+
+proc statusJson*(timer: Timer): JsonNode =
+  ## Describes the timer's status in a machine-readable way.
+  let untilLongBreak =
+    if timer.untilLongBreak.isSome: %timer.untilLongBreak.get.inSeconds
+    else: newJNull()
+  result = %*{
+    "state": timer.state.name,
+    "remaining": timer.remainingTime.inSeconds,
+    "running": timer.running,
+    "profile": timer.config.profileName,
+    "break_counter": timer.breakCounter,
+    "break_cycle": timer.config.profile.breakCycle,
+    "until_long_break": untilLongBreak,
+    "overtime": timer.overtime,
+    "preparing": timer.preparing,
+    "interruptions": timer.pomodoroInterruptions,
+    "task": timer.label,
+    "tags": timer.tags,
+    "completed_today": timer.completedToday,
+    "daily_goal": timer.config.dailyGoal,
+    "streak": timer.stats.streak,
+  }
+
+proc checkLimit*(timer: Timer, command: Command) =
+  ## Rejects commands that would begin a pomodoro past the daily limit.
+  if command.kind in {ckStart, ckResume, ckToggle} and timer.limitReached and
+     timer.startsPomodoro:
+    raise newException(LimitError, "that's " &
+                       $timer.config.dailyLimit & " pomodoros today " &
+                       "already, use override to keep going")
+
+proc checkStrict*(timer: Timer, command: Command) =
+  ## Rejects commands that would break up a pomodoro in strict mode.
+  if not timer.config.strict or timer.state != tsPomodoro or
+     timer.overtime: return
+  # flowtime pomodoros end by skipping them
+  if timer.countsUp and command.kind == ckSkip: return
+  let interrupts =
+    case command.kind
+    of ckPause: timer.running
+    of ckToggle: timer.running
+    of ckSkip, ckSetState: true
+    else: false
+  if interrupts:
+    raise newException(StrictModeError, "strict mode: a pomodoro can't be " &
+                       "paused or skipped, only abandoned")
+
+proc minutesArgument*(command: Command, default = 5.0): Duration =
+  ## Returns the amount of minutes given as the command's argument.
+  let minutes =
+    if command.arguments.len == 0: default
+    else:
+      try: parseFloat(command.arguments[0])
+      except ValueError: -1.0
+  if minutes <= 0:
+    raise newException(CommandError, $command.kind &
+                       " expects a positive number of minutes")
+  result = initDuration(milliseconds = int64(minutes * 60_000))
+
+proc control*(timer: var Timer, command: Command): bool =
+  ## Runs a command that acts on nothing but the timer. Returns false if the
+  ## command is up to the caller instead, or raises a ``CommandError`` if it
+  ## can't be run in the timer's state.
+  result = true
+  case command.kind
+  of ckStart: timer.start()
+  of ckPause: timer.pause()
+  of ckResume:
+    if timer.stateStartTime.isNone:
+      raise newException(CommandError, "the timer hasn't been started yet")
+    timer.start()
+  of ckToggle: timer.toggle()
+  of ckResetInterval, ckRestart:
+    if timer.state == tsNone:
+      raise newException(CommandError, "there's nothing to restart yet")
+    timer.cutShort()
+    timer.resetInterval()
+  of ckSetState:
+    let state =
+      case command.arguments[0]
+      of "pomodoro": tsPomodoro
+      of "short-break": tsShortBreak
+      of "long-break": tsLongBreak
+      else:
+        raise newException(CommandError, "set-state expects pomodoro, " &
+                           "short-break or long-break")
+    try: timer.jumpTo(state)
+    except ValueError as e: raise newException(CommandError, e.msg)
+  of ckAcknowledge:
+    if not timer.overtime:
+      raise newException(CommandError, "the timer isn't in overtime")
+    timer.acknowledge()
+  of ckAbandon:
+    if timer.state != tsPomodoro:
+      raise newException(CommandError, "only a pomodoro can be abandoned")
+    timer.abandon()
+  of ckSkip:
+    if timer.config.stopwatch:
+      raise newException(CommandError, "the stopwatch can't be skipped, " &
+                         "use lap or reset")
+    timer.skip()
+  of ckOverride:
+    if timer.config.dailyLimit == 0:
+      raise newException(CommandError, "there's no daily limit to lift")
+    timer.stats.overriddenOn = now().dayOf(timer.config.dayStart)
+  of ckExtend:
+    let duration = command.minutesArgument()
+    if timer.state == tsNone:
+      raise newException(CommandError, "there's nothing to extend yet")
+    if timer.overtime:
+      raise newException(CommandError, "the time is up already, use ack " &
+                         "to move on")
+    if timer.countsUp:
+      raise newException(CommandError, "flowtime pomodoros go on until " &
+                         "they're skipped")
+    timer.extend(duration)
+  of ckSetRemaining:
+    let duration =
+      try: parseClockTime(command.arguments[0])
+      except ValueError as e: raise newException(CommandError, e.msg)
+    if timer.state == tsNone:
+      raise newException(CommandError, "the timer hasn't been started yet")
+    if timer.overtime or timer.countsUp:
+      raise newException(CommandError, "the timer is counting up")
+    timer.setRemaining(duration)
+  of ckSnooze:
+    let duration = command.minutesArgument()
+    if not timer.canSnooze:
+      raise newException(CommandError, "only a break that has just begun " &
+                         "can be snoozed")
+    timer.snooze(duration)
+  else: result = false
+
+proc errorResponse*(timer: Timer, error: ref CommandError): HttpResponse =
+  ## Answers a REST request for a command that couldn't be run: strict mode
+  ## forbids with a 403, and anything else is a conflict with the timer's
+  ## state.
+  if error of StrictModeError:
+    result = errorResponse(403, error.msg)
+  elif error of LimitError:
+    result = jsonResponse(409, %*{"error": error.msg,
+                                  "daily_limit": timer.config.dailyLimit})
+  else:
+    result = errorResponse(409, error.msg)
@@ -0,0 +1,50 @@
+## Events describing what the timer is doing, for streaming to clients.
+
+import std/json
+import std/times
+
+import timer
+
+type
+  EventKind* = enum ## the kind of an event
+    ekTick = "tick"                    ## the remaining time has changed
+    ekStateChanged = "state_changed"   ## the timer moved on to another state,
+                                       ## or was started or paused
+    ekPomodoroStarted = "pomodoro_started"
+    ekBreakStarted = "break_started"   ## a short or long break has started
+    ekPaused = "paused"
+    ekResumed = "resumed"
+    ekReset = "reset"                  ## the timer went back to planned
+
+proc transitionEvents*(lastState: TimerState, lastRunning: bool,
+                       timer: Timer): seq[EventKind] =
+  ## Returns the specific events describing how the timer got from the last
+  ## state to its current one.
+  if timer.state != lastState:
+    case timer.state
+    of tsNone: result.add(ekReset)
+    of tsPomodoro: result.add(ekPomodoroStarted)
+    of tsShortBreak, tsLongBreak: result.add(ekBreakStarted)
+  elif timer.running != lastRunning:
+    result.add(if timer.running: ekResumed else: ekPaused)
+
+proc toJson*(kind: EventKind, timer: Timer): JsonNode =
+  ## Describes an event along with the timer's status at the time. Timestamps
+  ## are in seconds since the Unix epoch; ``ends_at`` is null while the timer
+  ## is paused.
+  let now = getTime()
+  result = %*{
+    "event": $kind,
+    "state": timer.state.name,
+    "remaining": timer.remainingTime.inSeconds,
+    "running": timer.running,
+    "profile": timer.config.profileName,
+    "task": timer.label,
+    "tags": timer.tags,
+    "break_counter": timer.breakCounter,
+    "break_cycle": timer.config.profile.breakCycle,
+    "timestamp": now.toUnixFloat,
+    "ends_at": nil,
+  }
+  if timer.running:
+    result["ends_at"] = %(now + timer.remainingTime).toUnixFloat
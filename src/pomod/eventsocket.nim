@@ -0,0 +1,73 @@
+## The event socket, a Unix domain socket that streams events to any number of
+## clients as JSON lines. Anything the clients send is ignored. Clients that
+## don't keep up with the events are dropped, rather than holding up the timer.
+
+import std/nativesockets
+import std/net
+import std/os
+
+import control
+
+type
+  EventServer* = object ## a listening event socket
+    socket: Socket
+    path: string
+    clients: seq[Subscriber]
+
+proc openEventServer*(path: string): EventServer =
+  ## Opens an event socket at the given path, removing any stale socket left
+  ## behind at that path.
+  removeFile(path)
+  result.path = path
+  result.socket = newSocket(AF_UNIX, SOCK_STREAM, IPPROTO_IP, buffered = false)
+  result.socket.bindUnix(path)
+  result.socket.listen()
+
+proc isOpen*(server: EventServer): bool =
+  result = server.socket != nil
+
+proc close*(server: var EventServer) =
+  ## Disconnects all clients and removes the socket.
+  if not server.isOpen: return
+  for client in server.clients:
+    client.socket.close()
+  server.clients.setLen(0)
+  server.socket.close()
+  server.socket = nil
+  removeFile(server.path)
+
+proc hasClosed(client: Socket): bool =
+  ## Reads and discards whatever the client has sent, returning whether it has
+  ## gone away.
+  if not client.readable: return false
+  var data: string
+  result =
+    try: client.recv(data, 4096) <= 0
+    except OSError: true
+
+proc poll*(server: var EventServer) =
+  ## Accepts new clients and drops the ones that have disconnected, without
+  ## blocking.
+  if not server.isOpen: return
+  while server.socket.readable:
+    var client: Socket
+    new(client)
+    server.socket.accept(client)
+    server.clients.add(initSubscriber(client))
+  var i = 0
+  while i < server.clients.len:
+    if server.clients[i].socket.hasClosed or not server.clients[i].flush():
+      server.clients[i].socket.close()
+      server.clients.delete(i)
+    else:
+      inc i
+
+proc broadcast*(server: var EventServer, event: string) =
+  ## Queues the event for all connected clients, as a single line.
+  var i = 0
+  while i < server.clients.len:
+    if server.clients[i].stream(event & "\n"):
+      inc i
+    else:
+      server.clients[i].socket.close()
+      server.clients.delete(i)
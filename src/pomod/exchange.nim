@@ -0,0 +1,162 @@
+## Exchanging the history with other programs, through CSV and JSON files in a
+## stable schema. CSV files made by other pomodoro apps can be imported too, as
+## long as they have columns for when each pomodoro began and ended.
+
+import std/json
+import std/parsecsv
+import std/streams
+import std/strutils
+import std/times
+
+import history
+import timer
+
+const ExchangeSchema* = 1 ## the version of the exported schema
+
+type
+  ExchangeFormat* = enum ## what the history is exported as
+    efCsv = "csv"
+    efJson = "json"
+
+const CsvColumns* = ["started_at", "ended_at", "state", "outcome", "task",
+                     "interruptions", "tags", "id"]
+
+proc timestamp(time: Time): string =
+  ## Formats the time in ISO 8601, in the local time zone.
+  result = time.local.format("yyyy-MM-dd'T'HH:mm:sszzz")
+
+proc csvField(text: string): string =
+  ## Quotes the field if it needs to be.
+  if text.contains({',', '"', '\n', '\r'}):
+    result = '"' & text.replace("\"", "\"\"") & '"'
+  else:
+    result = text
+
+proc toCsv*(sessions: openArray[Session]): string =
+  ## Renders the sessions as CSV, with a header naming the columns.
+  result = CsvColumns.join(",") & "\n"
+  for session in sessions:
+    let fields = [session.start.timestamp, session.stop.timestamp,
+                  session.state.name, $session.outcome, session.task,
+                  $session.interruptions, session.tags.join(","),
+                  session.id]
+    var quoted: seq[string]
+    for field in fields:
+      quoted.add(field.csvField)
+    result.add(quoted.join(",") & "\n")
+
+proc toJson*(sessions: openArray[Session]): JsonNode =
+  ## Describes the sessions, along with the version of the schema.
+  var list = newJArray()
+  for session in sessions:
+    let node = session.toJson
+    node["started_at"] = %session.start.timestamp
+    node["ended_at"] = %session.stop.timestamp
+    list.add(node)
+  result = %*{"schema": ExchangeSchema, "sessions": list}
+
+proc exportHistory*(sessions: openArray[Session],
+                    format: ExchangeFormat): string =
+  ## Renders the sessions in the given format.
+  result =
+    case format
+    of efCsv: sessions.toCsv
+    of efJson: sessions.toJson.pretty & "\n"
+
+proc parseTimestamp(text: string): Time =
+  ## Parses a time in ISO 8601, as exported, or a date and time of day in the
+  ## local time zone, or seconds since the Unix epoch. Raises a ``ValueError``
+  ## if it's none of those.
+  const LocalFormats = ["yyyy-MM-dd'T'HH:mm:ss", "yyyy-MM-dd HH:mm:ss",
+                        "yyyy-MM-dd'T'HH:mm", "yyyy-MM-dd HH:mm"]
+  let text = text.strip
+  try: return parse(text, "yyyy-MM-dd'T'HH:mm:sszzz").toTime
+  except TimeParseError: discard
+  try: return parse(text, "yyyy-MM-dd'T'HH:mm:ss'Z'", utc()).toTime
+  except TimeParseError: discard
+  for format in LocalFormats:
+    try: return parse(text, format).toTime
+    except TimeParseError: discard
+  try: result = fromUnixFloat(parseFloat(text))
+  except ValueError:
+    raise newException(ValueError, "not a time: " & text)
+
+proc findColumn(header: seq[string], names: openArray[string]): int =
+  ## Returns the index of the first column going by one of the names, or -1 if
+  ## there's none.
+  for name in names:
+    let index = header.find(name)
+    if index >= 0: return index
+  result = -1
+
+proc parseCsv(text: string): seq[Session] =
+  ## Reads sessions from CSV. Files exported by pomod have the state and
+  ## outcome of each session, while all other files are taken as listing
+  ## completed pomodoros.
+  const
+    StartColumns = ["started_at", "start", "start_time", "begin", "from"]
+    EndColumns = ["ended_at", "end", "end_time", "stop", "to"]
+    TaskColumns = ["task", "label", "description", "name", "title"]
+  var parser: CsvParser
+  parser.open(newStringStream(text), "history")
+  defer: parser.close()
+  parser.readHeaderRow()
+  var header: seq[string]
+  for column in parser.headers:
+    header.add(column.strip.toLowerAscii)
+  let
+    start = header.findColumn(StartColumns)
+    stop = header.findColumn(EndColumns)
+    task = header.findColumn(TaskColumns)
+    state = header.find("state")
+    outcome = header.find("outcome")
+    interruptions = header.find("interruptions")
+    tags = header.findColumn(["tags", "tag"])
+    # other apps' IDs are only unique among their own sessions
+    id = if state >= 0: header.find("id") else: header.find("uuid")
+  if start < 0 or stop < 0:
+    raise newException(ValueError, "the CSV needs columns for when each " &
+                       "pomodoro began and ended, eg. start and end")
+  while parser.readRow():
+    let row = parser.row
+    template field(index: int): string =
+      (if index in 0 ..< row.len: row[index] else: "")
+    var session = Session(state: tsPomodoro, outcome: soCompleted,
+                          start: parseTimestamp(field(start)),
+                          stop: parseTimestamp(field(stop)),
+                          task: field(task))
+    if field(state).len > 0: session.state = parseState(field(state))
+    if field(outcome).len > 0:
+      session.outcome = parseEnum[SessionOutcome](field(outcome))
+    if field(interruptions).len > 0:
+      session.interruptions = parseInt(field(interruptions))
+    session.tags = parseTags(field(tags))
+    session.id = field(id)
+    result.add(session)
+
+proc parseJsonExport(node: JsonNode): seq[Session] =
+  ## Reads sessions from pomod's JSON export.
+  let schema = node{"schema"}.getInt(0)
+  if schema != ExchangeSchema:
+    raise newException(ValueError, "unsupported schema: " & $schema)
+  for entry in node["sessions"].getElems:
+    result.add(Session(state: parseState(entry["state"].getStr),
+                       outcome: parseEnum[SessionOutcome](
+                         entry["outcome"].getStr),
+                       start: parseTimestamp(entry["started_at"].getStr),
+                       stop: parseTimestamp(entry["ended_at"].getStr),
+                       id: entry["id"].getStr,
+                       task: entry["task"].getStr,
+                       interruptions: entry["interruptions"].getInt))
+    for tag in entry["tags"].getElems:
+      result[^1].tags.add(tag.getStr)
+
+proc importHistory*(text: string): seq[Session] =
+  ## Reads the sessions of an exported history, telling JSON from CSV by its
+  ## first character. Raises a ``ValueError`` if they can't be read.
+  if text.strip.startsWith("{"):
+    try: result = parseJsonExport(parseJson(text))
+    except KeyError as e:
+      raise newException(ValueError, "missing field: " & e.msg)
+  else:
+    result = parseCsv(text)
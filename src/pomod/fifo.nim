@@ -0,0 +1,52 @@
+## A named pipe that newline-delimited commands can be written into, for setups
+## where talking to a socket is too much hassle.
+
+import std/os
+import std/posix
+import std/strutils
+
+import control
+
+type
+  CommandFifo* = object ## an open command FIFO
+    fd: cint
+    buffer: string                ## received data without a full line yet
+
+proc openCommandFifo*(path: string): CommandFifo =
+  ## Opens the FIFO at the given path, creating it if it doesn't exist. Raises
+  ## an ``OSError`` on failure.
+  if not fileExists(path) and mkfifo(path.cstring, Mode(0o600)) != 0:
+    raiseOSError(osLastError(), path)
+  # opening the FIFO for writing too keeps it from reaching end of file
+  # whenever a writer closes it
+  result.fd = posix.open(path.cstring, O_RDWR or O_NONBLOCK)
+  if result.fd < 0:
+    raiseOSError(osLastError(), path)
+
+proc isOpen*(fifo: CommandFifo): bool =
+  result = fifo.fd > 0
+
+proc close*(fifo: var CommandFifo) =
+  if fifo.isOpen:
+    discard posix.close(fifo.fd)
+    fifo.fd = 0
+
+proc poll*(fifo: var CommandFifo, handler: LineHandler) =
+  ## Handles the commands written into the FIFO since the last poll, without
+  ## blocking. Replies are discarded, except for errors, which are logged.
+  if not fifo.isOpen: return
+  var chunk: array[4096, char]
+  while true:
+    let count = posix.read(fifo.fd, addr chunk[0], chunk.len)
+    if count <= 0: break
+    for i in 0 ..< count:
+      fifo.buffer.add(chunk[i])
+  while true:
+    let newline = fifo.buffer.find('\n')
+    if newline < 0: break
+    let line = fifo.buffer[0 ..< newline].strip
+    fifo.buffer = fifo.buffer[newline + 1 .. ^1]
+    if line.len > 0:
+      let reply = handler(line)
+      if reply.startsWith("error: "):
+        stderr.writeLine("fifo: " & reply)
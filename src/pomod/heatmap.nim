@@ -0,0 +1,73 @@
+## A calendar heatmap of the pomodoros completed each day, for the terminal,
+## with a column for each week of the last year.
+
+import std/strutils
+import std/tables
+import std/times
+
+import output
+import timer
+
+const
+  Weeks = 53
+  # from no pomodoros to the most, as on a certain code hosting site
+  Colors = ["#161b22", "#0e4429", "#006d32", "#26a641", "#39d353"]
+  Shades = ["·", "░", "▒", "▓", "█"] ## without colors
+  DayLabels = ["Mon", "", "Wed", "", "Fri", "", ""]
+
+proc level(count, most: int): int =
+  ## Returns how dark a day's cell is, from 0 to 4.
+  result = if count == 0: 0 else: min(4, 1 + (count * 4 - 1) div most)
+
+proc cell(level: int, color: bool): string =
+  result = if color: Colors[level].ansiColor & "■\e[0m" else: Shades[level]
+
+proc heatmapStart*(today: DateTime, dayStart = DurationZero): DateTime =
+  ## Returns when the first day the heatmap shows began: the Monday of the
+  ## week a year before this one.
+  let
+    day = today - dayStart
+    midnight = dateTime(day.year, day.month, day.monthday,
+                        zone = today.timezone)
+  result = midnight - days(ord(day.weekday) + (Weeks - 1) * 7) + dayStart
+
+proc completedByDay*(sessions: openArray[Session],
+                     dayStart = DurationZero): CountTable[string] =
+  ## Counts the pomodoros completed on each day.
+  for session in sessions:
+    if session.state == tsPomodoro and session.outcome == soCompleted:
+      result.inc(session.start.local.dayOf(dayStart))
+
+proc renderHeatmap*(counts: CountTable[string], today: DateTime,
+                    color: bool, dayStart = DurationZero): string =
+  ## Renders the heatmap, with the months along the top, and the days of the
+  ## week down the side.
+  let
+    start = heatmapStart(today, dayStart)
+    last = today.dayOf(dayStart)
+  var most, total = 0
+  for day, count in counts:
+    if day >= start.dayOf(dayStart) and day <= last:
+      most = max(most, count)
+      total += count
+  # the months, above the weeks they begin in
+  var months = "    "
+  for week in 0 ..< Weeks:
+    let
+      monday = start + days(week * 7)
+      column = 4 + week * 2
+    if (week == 0 or monday.month != (monday - days(7)).month) and
+       months.len <= column:
+      months.add(repeat(' ', column - months.len) & ($monday.month)[0 ..< 3])
+  result = months & "\n"
+  for weekday in 0 ..< 7:
+    var row = alignLeft(DayLabels[weekday], 4)
+    for week in 0 ..< Weeks:
+      let day = (start + days(week * 7 + weekday)).dayOf(dayStart)
+      if day > last: break
+      row.add(cell(level(counts[day], most), color) & " ")
+    result.add(row.strip(leading = false) & "\n")
+  var legend = "    less "
+  for level in 0 .. 4:
+    legend.add(cell(level, color) & " ")
+  result.add(legend & "more, " & $total & " pomodoros in the last year\n")
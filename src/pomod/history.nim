@@ -0,0 +1,132 @@
+## The history of pomodoros and breaks, kept in an SQLite database in the data
+## directory. Statistics and integrations are built on top of it.
+
+import std/json
+import std/strutils
+import std/times
+
+import db_connector/db_sqlite
+
+import paths
+import timer
+
+export DbError
+
+const HistorySchema* = 1 ## the version of the database's schema
+
+type
+  History* = object ## an open history database
+    db: DbConn
+    isOpen: bool
+
+proc openHistory*(path = historyFile()): History =
+  ## Opens the history database, creating it if it doesn't exist yet. Raises a
+  ## ``DbError`` if it can't be opened, or if it was made by a newer pomod.
+  result.db = open(path, "", "", "")
+  result.isOpen = true
+  let version = parseInt(result.db.getValue(sql"pragma user_version"))
+  if version > HistorySchema:
+    result.db.close()
+    raise newException(DbError, "the history was made by a newer pomod " &
+                       "(schema " & $version & ")")
+  result.db.exec(sql"""
+    create table if not exists sessions (
+      id integer primary key,
+      uuid text not null,
+      state text not null,
+      outcome text not null,
+      started_at real not null,
+      ended_at real not null,
+      task text not null,
+      interruptions integer not null,
+      tags text not null default ''
+    )
+  """)
+  result.db.exec(sql"""
+    create unique index if not exists sessions_uuid on sessions (uuid)
+  """)
+  result.db.exec(sql("pragma user_version = " & $HistorySchema))
+
+proc schemaOf*(path: string): int =
+  ## Returns the version of a history database's schema, without migrating
+  ## it. Raises a ``DbError`` if it isn't a database.
+  let db = open(path, "", "", "")
+  defer: db.close()
+  result = parseInt(db.getValue(sql"pragma user_version"))
+
+proc isOpen*(history: History): bool =
+  ## Returns whether the history is open.
+  result = history.isOpen
+
+proc record*(history: History, session: Session) =
+  ## Adds an ended pomodoro or break to the history. Raises a ``DbError`` if
+  ## it can't be written.
+  if not history.isOpen: return
+  let id = if session.id.len == 0: newSessionId() else: session.id
+  history.db.exec(sql"""
+    insert into sessions
+      (uuid, state, outcome, started_at, ended_at, task, interruptions, tags)
+    values (?, ?, ?, ?, ?, ?, ?, ?)
+  """, id, $session.state, $session.outcome, session.start.toUnixFloat,
+       session.stop.toUnixFloat, session.task, session.interruptions,
+       session.tags.join(","))
+
+proc contains*(history: History, session: Session): bool =
+  ## Returns whether the history has the session: one with the same UUID, or
+  ## for sessions without one, of the same state beginning within a second of
+  ## the given one's start.
+  if not history.isOpen: return
+  if session.id.len > 0:
+    return history.db.getValue(sql"""
+      select count(*) from sessions where uuid = ?
+    """, session.id) != "0"
+  let start = session.start.toUnixFloat
+  result = history.db.getValue(sql"""
+    select count(*) from sessions
+    where state = ? and started_at > ? and started_at < ?
+  """, $session.state, start - 1, start + 1) != "0"
+
+proc sessions*(history: History, since = fromUnix(0),
+               until = getTime()): seq[Session] =
+  ## Returns the pomodoros and breaks that began within the given time, oldest
+  ## first.
+  if not history.isOpen: return
+  for row in history.db.fastRows(sql"""
+    select uuid, state, outcome, started_at, ended_at, task, interruptions,
+           tags
+    from sessions where started_at >= ? and started_at < ?
+    order by started_at
+  """, since.toUnixFloat, until.toUnixFloat):
+    result.add(Session(id: row[0], state: parseState(row[1]),
+                       outcome: parseEnum[SessionOutcome](row[2]),
+                       start: fromUnixFloat(parseFloat(row[3])),
+                       stop: fromUnixFloat(parseFloat(row[4])),
+                       task: row[5], interruptions: parseInt(row[6]),
+                       tags: parseTags(row[7])))
+
+proc copyTo*(history: History, path: string) =
+  ## Writes a consistent copy of the database to the file, even while it's
+  ## being written to. Raises a ``DbError`` if it can't be written.
+  if history.isOpen: history.db.exec(sql"vacuum into ?", path)
+
+proc tagged*(sessions: openArray[Session], tag: string): seq[Session] =
+  ## Returns the sessions that have the tag.
+  for session in sessions:
+    if tag in session.tags: result.add(session)
+
+proc close*(history: History) =
+  ## Closes the history database.
+  if history.isOpen: history.db.close()
+
+proc toJson*(session: Session): JsonNode =
+  ## Describes a session. Timestamps are in seconds since the Unix epoch.
+  result = %*{
+    "id": session.id,
+    "state": session.state.name,
+    "outcome": $session.outcome,
+    "started_at": session.start.toUnixFloat,
+    "ended_at": session.stop.toUnixFloat,
+    "task": session.task,
+    "tags": session.tags,
+    "interruptions": session.interruptions,
+  }
@@ -0,0 +1,55 @@
+## Syncing the history between machines through a directory that something
+## else keeps in sync, such as Syncthing or a cloud drive. Every machine
+## exports its history to a file of its own in the directory, and merges in
+## the sessions from the other machines' files, telling them apart by their
+## UUIDs.
+
+import std/nativesockets
+import std/os
+
+import exchange
+import history
+import persistence
+
+type
+  SyncResult* = object ## what a sync has done
+    machines*: int            ## the other machines whose files were read
+    merged*: int              ## the sessions added from them
+    errors*: seq[string]      ## the files that couldn't be read, and why
+
+proc machineName*(): string =
+  ## Returns the name this machine's file in the sync directory goes by.
+  result = getHostname()
+
+proc merge*(history: History, dir: string,
+            name = machineName()): SyncResult =
+  ## Adds the sessions the history doesn't have yet from the other machines'
+  ## files. Raises a ``DbError`` if they can't be recorded.
+  if not dirExists(dir): return
+  for kind, path in walkDir(dir):
+    let (_, file, extension) = path.splitFile
+    if kind != pcFile or extension != ".json" or file == name: continue
+    let sessions =
+      try: importHistory(readFile(path))
+      except IOError, ValueError:
+        result.errors.add(path & ": " & getCurrentExceptionMsg())
+        continue
+    inc result.machines
+    for session in sessions:
+      if session in history: continue
+      history.record(session)
+      inc result.merged
+
+proc publish*(history: History, dir: string, name = machineName()) =
+  ## Writes the history to this machine's file in the directory. Raises an
+  ## ``IOError`` or ``OSError`` if it can't be written.
+  createDir(dir)
+  writeAtomically(dir/(name & ".json"),
+                  history.sessions.exportHistory(efJson))
+
+proc sync*(history: History, dir: string,
+           name = machineName()): SyncResult =
+  ## Merges in the other machines' sessions, then publishes the history,
+  ## along with them, for the other machines to merge in.
+  result = history.merge(dir, name)
+  history.publish(dir, name)
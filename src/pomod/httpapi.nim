@@ -0,0 +1,240 @@
+## A tiny HTTP server for the REST API. Only as much of HTTP as the API needs
+## is implemented: requests are answered as soon as their headers arrive, and
+## the connection is closed after the response, or after ``RequestTimeout`` if
+## the request doesn't arrive in full by then. Past ``MaxConnections``, new
+## connections are turned away.
+##
+## Requests to ``/events`` may be upgraded to a WebSocket, over which events
+## are broadcast as text messages. Messages sent by clients are ignored, and
+## clients that don't keep up with the events are dropped.
+
+import std/base64
+import std/json
+import std/monotimes
+import std/nativesockets
+import std/net
+import std/strutils
+import std/times
+
+import checksums/sha1
+
+import control
+
+type
+  HttpRequest* = object
+    verb*, path*: string
+    headers*: seq[(string, string)]   ## header names are lowercase
+  HttpResponse* = object
+    code*: int
+    body*: string
+    contentType*: string            ## JSON if empty
+  HttpHandler* = proc (request: HttpRequest): HttpResponse
+  Connection = object
+    socket: Socket
+    buffer: string
+    deadline: MonoTime              ## when it's closed if the request
+                                    ## hasn't arrived yet
+  ConnectionStatus = enum
+    csOpen
+    csClosed
+    csUpgraded
+  HttpServer* = object ## a listening HTTP server
+    socket: Socket
+    connections: seq[Connection]
+    websockets: seq[Subscriber]
+
+const
+  MaxRequestSize = 16 * 1024
+  MaxConnections = 64  # waiting for their request, WebSockets aside
+  RequestTimeout = initDuration(seconds = 5)
+  EventsPath = "/events"
+  WebSocketGuid = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11"
+
+proc openHttpServer*(address: string): HttpServer =
+  ## Starts listening on the given ``host:port`` address. Raises a
+  ## ``ValueError`` if the address is malformed, or an ``OSError`` if it can't
+  ## be bound to.
+  let (host, port) = parseAddress(address)
+  result.socket = newSocket(buffered = false)
+  result.socket.setSockOpt(OptReuseAddr, true)
+  result.socket.bindAddr(port, host)
+  result.socket.listen()
+
+proc isOpen*(server: HttpServer): bool =
+  result = server.socket != nil
+
+proc close*(server: var HttpServer) =
+  if not server.isOpen: return
+  for connection in server.connections:
+    connection.socket.close()
+  for websocket in server.websockets:
+    websocket.socket.close()
+  server.connections.setLen(0)
+  server.websockets.setLen(0)
+  server.socket.close()
+  server.socket = nil
+
+proc header*(request: HttpRequest, name: string): string =
+  ## Returns the value of the header with the given (lowercase) name, or "" if
+  ## it's not present.
+  for (key, value) in request.headers:
+    if key == name: return value
+
+proc parseRequest(head: string): HttpRequest =
+  ## Parses the request line and headers. The request line's version is
+  ## ignored.
+  let lines = head.split("\r\n")
+  let requestLine = lines[0].splitWhitespace
+  if requestLine.len >= 2:
+    result.verb = requestLine[0]
+    result.path = requestLine[1]
+  for line in lines[1..^1]:
+    let colon = line.find(':')
+    if colon > 0:
+      result.headers.add((line[0 ..< colon].strip.toLowerAscii,
+                          line[colon + 1 .. ^1].strip))
+
+proc jsonResponse*(code: int, node: JsonNode): HttpResponse =
+  result = HttpResponse(code: code, body: $node)
+
+proc errorResponse*(code: int, message: string): HttpResponse =
+  ## Describes what went wrong as ``{"error": message}``.
+  result = jsonResponse(code, %*{"error": message})
+
+proc reason(code: int): string =
+  result =
+    case code
+    of 200: "OK"
+    of 400: "Bad Request"
+    of 401: "Unauthorized"
+    of 403: "Forbidden"
+    of 404: "Not Found"
+    of 405: "Method Not Allowed"
+    of 409: "Conflict"
+    of 501: "Not Implemented"
+    else: "Unknown"
+
+proc send*(socket: Socket, response: HttpResponse) =
+  ## Sends a response over the socket.
+  let contentType =
+    if response.contentType.len > 0: response.contentType
+    else: "application/json"
+  socket.send("HTTP/1.1 " & $response.code & " " & response.code.reason &
+              "\r\n" &
+              "Content-Type: " & contentType & "\r\n" &
+              "Content-Length: " & $response.body.len & "\r\n" &
+              "Connection: close\r\n" &
+              "\r\n" & response.body)
+
+proc acceptWebSocket(socket: Socket, request: HttpRequest) =
+  ## Completes the WebSocket opening handshake.
+  let
+    key = request.header("sec-websocket-key")
+    digest = Sha1Digest(secureHash(key & WebSocketGuid))
+  var hash = newString(digest.len)
+  for i, value in digest:
+    hash[i] = char(value)
+  socket.send("HTTP/1.1 101 Switching Protocols\r\n" &
+              "Upgrade: websocket\r\n" &
+              "Connection: Upgrade\r\n" &
+              "Sec-WebSocket-Accept: " & encode(hash) & "\r\n" &
+              "\r\n")
+
+proc textFrame(message: string): string =
+  ## Wraps the message in an unmasked WebSocket text frame.
+  result.add(char(0x81))  # FIN + text opcode
+  if message.len < 126:
+    result.add(char(message.len))
+  elif message.len < 65536:
+    result.add(char(126))
+    result.add(char(message.len shr 8))
+    result.add(char(message.len and 0xff))
+  else:
+    result.add(char(127))
+    for shift in countdown(56, 0, 8):
+      result.add(char((message.len shr shift) and 0xff))
+  result.add(message)
+
+proc handleData(connection: var Connection,
+                handler: HttpHandler): ConnectionStatus =
+  ## Reads whatever data is available and responds once the request is
+  ## complete.
+  var data: string
+  let count =
+    try: connection.socket.recv(data, 4096)
+    except OSError: 0
+  if count <= 0: return csClosed
+  connection.buffer.add(data)
+  let headEnd = connection.buffer.find("\r\n\r\n")
+  if headEnd < 0:
+    return (if connection.buffer.len < MaxRequestSize: csOpen else: csClosed)
+  let request = parseRequest(connection.buffer[0 ..< headEnd])
+  result = csClosed
+  try:
+    if request.verb.len == 0:
+      connection.socket.send(HttpResponse(code: 400))
+    elif request.path == EventsPath and
+         request.header("upgrade").toLowerAscii == "websocket":
+      connection.socket.acceptWebSocket(request)
+      result = csUpgraded
+    else:
+      connection.socket.send(handler(request))
+  except OSError:
+    result = csClosed
+
+proc hasClosed(websocket: Socket): bool =
+  ## Reads and discards whatever the client has sent, returning whether it has
+  ## gone away.
+  if not websocket.readable: return false
+  var data: string
+  result =
+    try: websocket.recv(data, 4096) <= 0
+    except OSError: true
+
+proc broadcast*(server: var HttpServer, message: string) =
+  ## Queues the message for all connected WebSockets.
+  let frame = textFrame(message)
+  var i = 0
+  while i < server.websockets.len:
+    if server.websockets[i].stream(frame):
+      inc i
+    else:
+      server.websockets[i].socket.close()
+      server.websockets.delete(i)
+
+proc poll*(server: var HttpServer, handler: HttpHandler) =
+  ## Accepts new connections and responds to the requests that have arrived,
+  ## without blocking.
+  if not server.isOpen: return
+  while server.socket.readable:
+    var client: Socket
+    new(client)
+    server.socket.accept(client)
+    if server.connections.len >= MaxConnections:
+      client.close()
+      continue
+    server.connections.add(Connection(socket: client,
+                                      deadline: getMonoTime() + RequestTimeout))
+  var i = 0
+  while i < server.connections.len:
+    let status =
+      if getMonoTime() > server.connections[i].deadline: csClosed
+      elif server.connections[i].socket.readable:
+        server.connections[i].handleData(handler)
+      else: csOpen
+    case status
+    of csOpen: inc i
+    of csClosed:
+      server.connections[i].socket.close()
+      server.connections.delete(i)
+    of csUpgraded:
+      server.websockets.add(initSubscriber(server.connections[i].socket))
+      server.connections.delete(i)
+  i = 0
+  while i < server.websockets.len:
+    if server.websockets[i].socket.hasClosed or
+       not server.websockets[i].flush():
+      server.websockets[i].socket.close()
+      server.websockets.delete(i)
+    else:
+      inc i
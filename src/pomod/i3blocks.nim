@@ -0,0 +1,37 @@
+## Integration with i3blocks, which runs a command every interval and reads the
+## block's full text, short text, and color from its output. Clicks on the
+## block are passed in through ``BLOCK_BUTTON``.
+
+import std/os
+
+import client
+import config
+import output
+
+proc clickCommand(button: string): string =
+  ## Returns the command a mouse button runs: left click toggles the timer,
+  ## middle click resets it, and right click skips to the next state.
+  result =
+    case button
+    of "1": "toggle"
+    of "2": "reset"
+    of "3": "skip"
+    else: ""
+
+proc runI3blocks*(): int =
+  ## Handles a click, if there was one, then prints the block. Returns the exit
+  ## code.
+  let config = loadConfigOrDefault()
+  try:
+    let command = clickCommand(getEnv("BLOCK_BUTTON"))
+    if command.len > 0:
+      discard sendCommand(command)
+    let status = queryStatus()
+    echo status.state.stateIcon(config.icons) & " " & status.remaining.clockTime
+    echo status.remaining.clockTime
+    echo status.state.stateColor(status.running, config.colors)
+    result = QuitSuccess
+  except OSError, ValueError:
+    stderr.writeLine("pomod: cannot get the status from pomod: " &
+                     getCurrentExceptionMsg())
+    result = QuitFailure
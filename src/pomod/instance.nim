@@ -0,0 +1,82 @@
+## Making sure only one pomod runs at a time, using a locked pidfile.
+
+import std/os
+import std/posix
+import std/strutils
+
+import paths
+
+type
+  InstanceLock* = object ## the lock held by the running instance
+    fd: cint
+    tookOver*: bool           ## whether another instance was replaced
+  AlreadyRunningError* = object of OSError
+    pid*: int                 ## the running instance's process ID
+
+var
+  LOCK_EX {.importc, header: "<sys/file.h>".}: cint
+  LOCK_NB {.importc, header: "<sys/file.h>".}: cint
+  LOCK_UN {.importc, header: "<sys/file.h>".}: cint
+
+proc flock(fd, operation: cint): cint {.importc, header: "<sys/file.h>".}
+
+const TakeoverTimeout = 5000  # milliseconds
+
+proc initInstanceLock*(): InstanceLock =
+  ## Returns a lock that isn't held, for instances that run alongside another.
+  result = InstanceLock(fd: -1)
+
+proc readPid(fd: cint): int =
+  ## Reads the process ID stored in the pidfile, or returns 0 if it's empty.
+  var buffer: array[32, char]
+  let count = pread(fd, addr buffer[0], buffer.len, 0)
+  if count <= 0: return 0
+  var text = newString(count)
+  copyMem(addr text[0], addr buffer[0], count)
+  result =
+    try: parseInt(text.strip)
+    except ValueError: 0
+
+proc lockInstance*(path = pidFile(), takeover = false,
+                   writePid = true): InstanceLock =
+  ## Locks the pidfile and writes the current process ID into it, unless
+  ## ``writePid`` is false. If another instance holds the lock, an
+  ## ``AlreadyRunningError`` is raised, unless ``takeover`` is true: then the
+  ## other instance is asked to save its state and exit, and the lock is taken
+  ## over once it has.
+  result.fd = posix.open(path.cstring, O_RDWR or O_CREAT, Mode(0o600))
+  if result.fd < 0:
+    raiseOSError(osLastError(), path)
+  if flock(result.fd, LOCK_EX or LOCK_NB) != 0:
+    let pid = readPid(result.fd)
+    if not takeover or pid <= 0:
+      discard posix.close(result.fd)
+      let error = newException(AlreadyRunningError,
+                               "pomod is already running (pid " & $pid & ")")
+      error.pid = pid
+      raise error
+    # SIGTERM makes the other instance save its state and exit gracefully
+    discard kill(Pid(pid), SIGTERM)
+    var waited = 0
+    while flock(result.fd, LOCK_EX or LOCK_NB) != 0:
+      if waited >= TakeoverTimeout:
+        discard posix.close(result.fd)
+        let error = newException(AlreadyRunningError,
+                                 "pomod (pid " & $pid & ") didn't exit")
+        error.pid = pid
+        raise error
+      sleep(100)
+      waited += 100
+    result.tookOver = true
+  if not writePid: return
+  let pid = $getpid() & "\n"
+  discard ftruncate(result.fd, 0)
+  discard pwrite(result.fd, pid.cstring, pid.len, 0)
+
+proc release*(lock: var InstanceLock) =
+  ## Lets go of the lock before the process exits, eg. so that pomod can be
+  ## started while a command that held the lock is finishing up.
+  if lock.fd < 0: return
+  discard flock(lock.fd, LOCK_UN)
+  discard posix.close(lock.fd)
+  lock.fd = -1
@@ -0,0 +1,60 @@
+## Interactive mode, in which pomod is controlled with keys pressed in the
+## terminal it runs in.
+
+import std/posix
+import std/termios
+
+const
+  KeyHelp* = "p/space: pause or resume, s: skip, a: abandon, " &
+            "+: extend by 5 minutes, i/e: internal/external interruption, " &
+            "r: reset, q: quit"
+
+type
+  Terminal* = object ## the terminal's settings from before entering raw mode
+    raw: bool
+    original: Termios
+  KeyAction* = enum ## what a pressed key does
+    kaNone
+    kaCommand   ## sends a command to the timer
+    kaQuit      ## quits pomod
+
+proc enterRawMode*(): Terminal =
+  ## Makes keys pressed in the terminal available immediately, without
+  ## echoing them. Nothing is done if stdin isn't a terminal.
+  if isatty(STDIN_FILENO) == 0: return
+  if tcGetAttr(STDIN_FILENO, addr result.original) != 0: return
+  var raw = result.original
+  # ISIG is disabled too, so that ^C arrives as a key and the terminal can be
+  # restored before quitting
+  raw.c_lflag = raw.c_lflag and not Cflag(ICANON or ECHO or ISIG)
+  if tcSetAttr(STDIN_FILENO, TCSANOW, addr raw) == 0:
+    result.raw = true
+
+proc restore*(terminal: Terminal) =
+  ## Restores the terminal's original settings.
+  if terminal.raw:
+    var original = terminal.original
+    discard tcSetAttr(STDIN_FILENO, TCSANOW, addr original)
+
+proc readKeys*(): string =
+  ## Returns the keys pressed since the last call, without blocking.
+  var pollFd = TPollfd(fd: STDIN_FILENO, events: POLLIN)
+  while posix.poll(addr pollFd, Tnfds(1), 0) > 0:
+    var key: char
+    if posix.read(STDIN_FILENO, addr key, 1) <= 0: break
+    result.add(key)
+
+proc keyAction*(key: char): (KeyAction, string) =
+  ## Returns what the given key does. For commands, the command line to
+  ## execute is returned alongside.
+  result =
+    case key
+    of 'p', ' ': (kaCommand, "toggle")
+    of 's': (kaCommand, "skip")
+    of 'a': (kaCommand, "abandon")
+    of '+': (kaCommand, "extend 5")
+    of 'i': (kaCommand, "interrupt internal")
+    of 'e': (kaCommand, "interrupt external")
+    of 'r': (kaCommand, "reset")
+    of 'q', '\x03', '\x04': (kaQuit, "")
+    else: (kaNone, "")
@@ -0,0 +1,48 @@
+## The journal of the timer's state changes. Every change is appended to it
+## and flushed right away, so that even when pomod is killed without a chance
+## to save its state, what it was doing can be pieced together.
+
+import std/json
+import std/os
+
+import paths
+import persistence
+import timer
+
+type
+  JournalEvent* = enum ## why an entry was written
+    jeStarted = "started"     ## pomod was started
+    jeChanged = "changed"     ## the timer's state changed
+    jeShutdown = "shutdown"   ## pomod shut down cleanly
+  Journal* = object ## a journal being written
+    file: File
+
+proc lastEntry*(path = journalFile()): JsonNode =
+  ## Returns the last entry of the journal, or nil if there are none. An entry
+  ## cut off halfway by a crash is ignored.
+  if not fileExists(path): return
+  for line in lines(path):
+    try: result = parseJson(line)
+    except JsonParsingError: discard
+
+proc crashed*(entry: JsonNode): bool =
+  ## Returns whether the journal ended with the given entry without pomod
+  ## shutting down cleanly.
+  result = entry != nil and entry{"event"}.getStr != $jeShutdown
+
+proc openJournal*(path = journalFile()): Journal =
+  ## Starts a new journal, in place of the last one. Raises an ``IOError`` if
+  ## the file can't be opened.
+  result.file = open(path, fmWrite)
+
+proc write*(journal: Journal, event: JournalEvent, timer: Timer) =
+  ## Appends the timer's state to the journal.
+  if journal.file == nil: return
+  let entry = timer.toJson
+  entry["event"] = %($event)
+  journal.file.writeLine($entry)
+  journal.file.flushFile()
+
+proc close*(journal: Journal) =
+  ## Closes the journal.
+  if journal.file != nil: journal.file.close()
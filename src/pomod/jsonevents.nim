@@ -0,0 +1,39 @@
+## The JSON Lines event stream, written to file descriptor 3 so that wrappers
+## can follow the timer without parsing the status on stdout.
+
+import std/posix
+
+type
+  JsonEventStream* = object ## where events are written
+    fd: cint                  ## -1 if closed
+
+const EventFd* = 3
+
+proc initJsonEventStream*(): JsonEventStream =
+  ## Returns a closed stream, which discards events.
+  result.fd = -1
+
+proc openJsonEvents*(fd = EventFd): JsonEventStream =
+  ## Starts writing events to the given file descriptor, which has to be open
+  ## already. Raises an ``IOError`` if it isn't.
+  if fcntl(fd, F_GETFD) < 0:
+    raise newException(IOError, "file descriptor " & $fd & " is not open")
+  # a reader that isn't keeping up must not hold the timer back
+  discard fcntl(fd, F_SETFL, fcntl(fd, F_GETFL) or O_NONBLOCK)
+  result.fd = fd
+
+proc isOpen*(stream: JsonEventStream): bool =
+  result = stream.fd >= 0
+
+proc close*(stream: var JsonEventStream) =
+  if not stream.isOpen: return
+  discard posix.close(stream.fd)
+  stream.fd = -1
+
+proc write*(stream: var JsonEventStream, event: string) =
+  ## Writes a line with the event. If the reader has gone away, the stream is
+  ## closed; if it's not keeping up, the event is dropped.
+  if not stream.isOpen: return
+  let line = event & "\n"
+  if posix.write(stream.fd, line.cstring, line.len) < 0 and errno == EPIPE:
+    stream.close()
@@ -0,0 +1,66 @@
+## Metrics in the Prometheus text exposition format.
+
+import std/strutils
+import std/times
+
+import timer
+
+const MetricsContentType* = "text/plain; version=0.0.4"
+
+proc metric(output: var string, name, kind, help: string, value: int64,
+            labels = "") =
+  output.add("# HELP " & name & " " & help & "\n")
+  output.add("# TYPE " & name & " " & kind & "\n")
+  output.add(name & labels & " " & $value & "\n")
+
+proc metrics*(timer: Timer): string =
+  ## Renders the timer's counters and status as Prometheus metrics.
+  let stats = timer.stats
+  result.metric("pomod_pomodoros_completed_total", "counter",
+                "Pomodoros that ran to completion.", stats.pomodorosCompleted)
+  result.metric("pomod_pomodoros_skipped_total", "counter",
+                "Pomodoros that were skipped.", stats.pomodorosSkipped)
+  result.metric("pomod_pomodoros_cut_short_total", "counter",
+                "Pomodoros that were reset or restarted midway.",
+                stats.pomodorosCutShort)
+  result.metric("pomod_pomodoros_abandoned_total", "counter",
+                "Pomodoros that were voided.", stats.pomodorosAbandoned)
+  result.metric("pomod_breaks_completed_total", "counter",
+                "Breaks that ran to completion.", stats.breaksCompleted)
+  result.metric("pomod_breaks_skipped_total", "counter",
+                "Breaks that were skipped.", stats.breaksSkipped)
+  result.metric("pomod_breaks_overrun_total", "counter",
+                "Breaks that went into overtime.", stats.breaksOverrun)
+  result.metric("pomod_breaks_snoozed_total", "counter",
+                "Breaks that were postponed.", stats.breaksSnoozed)
+  var interruptions: array[InterruptionKind, int]
+  for interruption in stats.interruptions:
+    inc interruptions[interruption.kind]
+  result.add("# HELP pomod_interruptions_total Interruptions recorded " &
+             "against pomodoros.\n")
+  result.add("# TYPE pomod_interruptions_total counter\n")
+  for kind in InterruptionKind:
+    result.add("pomod_interruptions_total{kind=\"" & $kind & "\"} " &
+               $interruptions[kind] & "\n")
+  result.add("# HELP pomod_overtime_seconds_total Time spent past the end " &
+             "of pomodoros and breaks.\n")
+  result.add("# TYPE pomod_overtime_seconds_total counter\n")
+  result.add("pomod_overtime_seconds_total{state=\"pomodoro\"} " &
+             $stats.pomodoroOvertime.inSeconds & "\n")
+  result.add("pomod_overtime_seconds_total{state=\"break\"} " &
+             $stats.breakOvertime.inSeconds & "\n")
+  result.metric("pomod_streak_days", "gauge",
+                "Consecutive days on which enough pomodoros were completed.",
+                stats.streak)
+  result.metric("pomod_remaining_seconds", "gauge",
+                "Time left in the current state.",
+                timer.remainingTime.inSeconds)
+  result.metric("pomod_running", "gauge",
+                "Whether the timer is running.", int64(timer.running))
+  result.add("# HELP pomod_state The state the timer is in.\n")
+  result.add("# TYPE pomod_state gauge\n")
+  for state in TimerState:
+    let
+      label = state.name.replace(' ', '_')
+      value = if state == timer.state: 1 else: 0
+    result.add("pomod_state{state=\"" & label & "\"} " & $value & "\n")
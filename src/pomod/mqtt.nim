@@ -0,0 +1,166 @@
+## A minimal MQTT 3.1.1 client, publishing the timer's state and optionally
+## receiving commands. Only QoS 0 is supported, which is plenty for status
+## updates.
+
+import std/monotimes
+import std/net
+import std/strutils
+import std/times
+
+import control
+
+type
+  MqttClient* = object ## a connection to an MQTT broker
+    socket: Socket
+    buffer: string
+    topicPrefix: string
+    lastSent: MonoTime            ## for keeping the connection alive
+  MqttError* = object of IOError
+
+const
+  KeepAlive = 60                  # seconds
+  ConnectTimeout = 5000           # milliseconds
+  # packet types
+  Connect = 0x10
+  Connack = 0x20
+  Publish = 0x30
+  Subscribe = 0x82
+  PingReq = 0xc0
+  Disconnect = 0xe0
+
+proc encodeLength(length: int): string =
+  ## Encodes the remaining length of a packet as a variable-length integer.
+  var length = length
+  while true:
+    var digit = length mod 128
+    length = length div 128
+    if length > 0: digit = digit or 0x80
+    result.add(char(digit))
+    if length == 0: break
+
+proc encodeString(text: string): string =
+  result = char(text.len shr 8) & char(text.len and 0xff) & text
+
+proc packet(kind: int, body: string): string =
+  result = char(kind) & encodeLength(body.len) & body
+
+proc send(client: var MqttClient, data: string) =
+  client.socket.send(data)
+  client.lastSent = getMonoTime()
+
+proc connectMqtt*(broker, clientId, topicPrefix: string,
+                  username, password = "", subscribe = false): MqttClient =
+  ## Connects to the broker at the given ``host:port`` address. If
+  ## ``subscribe`` is true, commands published to ``<prefix>/command`` are
+  ## received. Raises an ``MqttError`` or ``OSError`` if connecting fails.
+  let (host, port) = parseAddress(broker)
+  result.topicPrefix = topicPrefix
+  result.socket = newSocket(buffered = false)
+  result.socket.connect(host, port, timeout = ConnectTimeout)
+
+  var flags = 0x02  # clean session
+  var payload = encodeString(clientId)
+  if username.len > 0:
+    flags = flags or 0x80
+    payload.add(encodeString(username))
+  if password.len > 0:
+    flags = flags or 0x40
+    payload.add(encodeString(password))
+  result.send(packet(Connect, encodeString("MQTT") & char(4) & char(flags) &
+                              char(KeepAlive shr 8) & char(KeepAlive and 0xff) &
+                              payload))
+  var connack: string
+  if result.socket.recv(connack, 4, ConnectTimeout) != 4 or
+     connack[0].int != Connack:
+    raise newException(MqttError,
+                       "the broker didn't acknowledge the connection")
+  if connack[3].int != 0:
+    raise newException(MqttError, "the broker refused the connection (code " &
+                       $connack[3].int & ")")
+  if subscribe:
+    result.send(packet(Subscribe, "\x00\x01" &
+                                  encodeString(topicPrefix & "/command") &
+                                  "\x00"))
+
+proc isOpen*(client: MqttClient): bool =
+  result = client.socket != nil
+
+proc close*(client: var MqttClient) =
+  if not client.isOpen: return
+  try: client.send(packet(Disconnect, ""))
+  except OSError: discard
+  client.socket.close()
+  client.socket = nil
+
+proc publish*(client: var MqttClient, topic, message: string, retain = false) =
+  ## Publishes a message to ``<prefix>/<topic>``. The connection is closed if
+  ## sending fails.
+  if not client.isOpen: return
+  var kind = Publish
+  if retain: kind = kind or 0x01
+  try:
+    client.send(packet(kind, encodeString(client.topicPrefix & "/" & topic) &
+                             message))
+  except OSError:
+    client.socket.close()
+    client.socket = nil
+
+proc decodePacket(buffer: string): (int, string, int) =
+  ## Tries to decode a packet at the start of the buffer. Returns its type, its
+  ## body, and its total length, or a length of 0 if the packet is incomplete.
+  ## Raises an ``MqttError`` if the packet's length is malformed.
+  if buffer.len < 2: return
+  var
+    length = 0
+    multiplier = 1
+    i = 1
+  while true:
+    if i >= buffer.len: return
+    let digit = buffer[i].int
+    length += (digit and 0x7f) * multiplier
+    multiplier *= 128
+    inc i
+    if (digit and 0x80) == 0: break
+    if i > 4:
+      # MQTT allows four bytes at most, past which the length would overflow
+      raise newException(MqttError, "malformed packet length")
+  if buffer.len < i + length: return
+  result = (buffer[0].int and 0xf0, buffer[i ..< i + length], i + length)
+
+proc poll*(client: var MqttClient, handler: LineHandler) =
+  ## Receives commands and keeps the connection alive, without blocking.
+  ## Replies to commands are published to ``<prefix>/reply``.
+  if not client.isOpen: return
+  while client.socket.readable:
+    var data: string
+    let count =
+      try: client.socket.recv(data, 4096)
+      except OSError: 0
+    if count <= 0:
+      client.socket.close()
+      client.socket = nil
+      return
+    client.buffer.add(data)
+  while true:
+    let (kind, body, length) =
+      try: decodePacket(client.buffer)
+      except MqttError:
+        # nothing past a malformed packet can be made sense of
+        client.socket.close()
+        client.socket = nil
+        return
+    if length == 0: break
+    client.buffer = client.buffer[length .. ^1]
+    if kind == Publish and body.len >= 2:
+      let topicLength = (body[0].int shl 8) or body[1].int
+      # a topic running past the packet is the broker's mistake, so the
+      # packet is dropped
+      if 2 + topicLength > body.len: continue
+      let line = body[2 + topicLength .. ^1].strip
+      if line.len > 0:
+        client.publish("reply", handler(line))
+  if getMonoTime() - client.lastSent > initDuration(seconds = KeepAlive div 2):
+    try: client.send(packet(PingReq, ""))
+    except OSError:
+      client.socket.close()
+      client.socket = nil
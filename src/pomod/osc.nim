@@ -0,0 +1,28 @@
+## Terminal escape sequences for showing the status outside of the terminal's
+## contents: in its title, and as progress in the taskbar (OSC 9;4, supported
+## by WezTerm, ConEmu, and Windows Terminal among others).
+
+import output
+import timer
+
+const
+  ProgressHidden = 0
+  ProgressNormal = 1
+  ProgressPaused = 4
+
+proc titleSequence*(title: string): string =
+  ## Sets the terminal's title.
+  result = "\e]0;" & title & "\a"
+
+proc progressSequence*(timer: Timer): string =
+  ## Reports the progress of the current state. Nothing is shown before the
+  ## timer is started.
+  let state =
+    if timer.state == tsNone: ProgressHidden
+    elif timer.running: ProgressNormal
+    else: ProgressPaused
+  result = "\e]9;4;" & $state & ";" & $int(timer.progress * 100) & "\a"
+
+proc clearProgressSequence*(): string =
+  ## Removes the progress report.
+  result = "\e]9;4;" & $ProgressHidden & ";0\a"
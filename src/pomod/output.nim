@@ -0,0 +1,267 @@
+## Rendering of the timer's status for bars and terminals.
+
+import std/json
+import std/options
+import std/strformat
+import std/strutils
+import std/times
+
+import config
+import timer
+
+proc minutes*(duration: Duration): int64 =
+  ## Get the amount of minutes in the duration.
+  duration.inSeconds div 60
+
+proc seconds*(duration: Duration): int64 =
+  ## Get the amount of seconds in the current minute.
+  duration.inSeconds mod 60
+
+proc clockTime*(duration: Duration, precision = pSeconds): string =
+  ## Formats the duration with the given precision.
+  case precision
+  of pSeconds:
+    result = fmt"{duration.minutes:02}:{duration.seconds:02}"
+  of pMinutes:
+    # rounded up, so that "0m" only shows up once the time is over
+    let minutes = (max(duration.inMilliseconds, 0) + 59_999) div 60_000
+    result = $minutes & "m"
+  of pTenths:
+    if duration < initDuration(minutes = 1):
+      let tenths = max(duration.inMilliseconds, 0) div 100
+      result = fmt"{tenths div 10:02}.{tenths mod 10}"
+    else:
+      result = duration.clockTime(pSeconds)
+
+proc hoursAndMinutes*(duration: Duration): string =
+  ## Formats the duration as eg. ``2h05m``, or ``45m`` when under an hour.
+  let
+    hours = duration.inMinutes div 60
+    minutes = duration.inMinutes mod 60
+  result = if hours > 0: fmt"{hours}h{minutes:02}m" else: fmt"{minutes}m"
+
+proc stateIcon*(state: TimerState, icons: Icons): string =
+  ## Returns the configured icon associated with the given state.
+  result =
+    case state
+    of tsNone: icons.planned
+    of tsPomodoro: icons.pomodoro
+    of tsShortBreak: icons.shortBreak
+    of tsLongBreak: icons.longBreak
+
+proc stateColor*(state: TimerState, running: bool, colors: Colors): string =
+  ## Returns the configured color the state is shown in. Paused states have a
+  ## color of their own.
+  result =
+    if state != tsNone and not running: colors.paused
+    else:
+      case state
+      of tsNone: colors.planned
+      of tsPomodoro: colors.pomodoro
+      of tsShortBreak: colors.shortBreak
+      of tsLongBreak: colors.longBreak
+
+proc color(timer: Timer): string =
+  result = timer.state.stateColor(timer.running, timer.config.colors)
+
+proc progress*(timer: Timer): float =
+  ## Returns how much of the current state has elapsed, from 0 to 1.
+  let total = timer.intervalTime.inMilliseconds
+  if total <= 0: return 0
+  result = 1 - timer.remainingTime.inMilliseconds / total
+  result = result.clamp(0.0, 1.0)
+
+proc cyclePosition*(timer: Timer): int =
+  ## Returns which pomodoro of the cycle the timer is at, counting from 1.
+  ## Breaks count as part of the pomodoro before them.
+  result =
+    case timer.state
+    of tsNone, tsPomodoro: timer.breakCounter + 1
+    of tsShortBreak: timer.breakCounter
+    of tsLongBreak: timer.config.profile.breakCycle
+
+proc progressBar*(timer: Timer): string =
+  ## Renders the elapsed part of the current state as a bar.
+  let
+    bar = timer.config.progressBar
+    filled = int(timer.progress * bar.width.float)
+  for i in 0 ..< bar.width:
+    result.add(if i < filled: bar.filled else: bar.empty)
+
+proc progressGlyph*(timer: Timer): string =
+  ## Renders the elapsed part of the current state as a single character.
+  const
+    Blocks = ["▁", "▂", "▃", "▄", "▅", "▆", "▇", "█"]
+    Braille = ["⠀", "⡀", "⡄", "⡆", "⡇", "⣇", "⣧", "⣷", "⣿"]
+  template pick(glyphs: openArray[string]): string =
+    glyphs[min(int(timer.progress * glyphs.len.float), glyphs.high)]
+  result =
+    case timer.config.progressBar.glyph
+    of gsBlocks: pick(Blocks)
+    of gsBraille: pick(Braille)
+
+proc placeholder(timer: Timer, name, timers: string): string =
+  ## Returns the value of a status template placeholder. ``timers`` describes
+  ## the named timers running alongside.
+  let
+    elapsed = max(timer.intervalTime - timer.remainingTime, DurationZero)
+    # in overtime, the time past the end is shown instead, and flowtime
+    # pomodoros show how long they've gone on for
+    remaining =
+      if timer.overtime or timer.countsUp: -timer.remainingTime
+      else: timer.remainingTime
+    sign = if timer.overtime: "+" else: ""
+  result =
+    case name
+    of "icon": timer.state.stateIcon(timer.config.icons)
+    of "state": timer.state.name
+    of "mm": fmt"{remaining.minutes:02}"
+    of "ss": fmt"{remaining.seconds:02}"
+    of "remaining": sign & remaining.clockTime(timer.config.precision)
+    of "elapsed": elapsed.clockTime(timer.config.precision)
+    of "cycle": $timer.cyclePosition
+    of "position":
+      $timer.cyclePosition & "/" & $timer.config.profile.breakCycle
+    of "total": $timer.config.profile.breakCycle
+    of "profile": timer.config.profileName
+    of "profile_suffix":
+      if timer.config.profileName == DefaultProfileName: ""
+      else: " " & timer.config.profileName
+    of "completed": $timer.stats.pomodorosCompleted
+    of "bar": timer.progressBar
+    of "glyph": timer.progressGlyph
+    of "label": timer.label
+    of "label_suffix":
+      if timer.label.len == 0: "" else: " " & timer.label
+    of "lap": timer.currentLap.clockTime(timer.config.precision)
+    of "laps": $timer.laps.len
+    of "timers": timers
+    of "interruptions": $timer.pomodoroInterruptions
+    of "ready": $((timer.getReady.inMilliseconds + 999) div 1000) & "s"
+    of "today": timer.focusedToday.hoursAndMinutes
+    of "goal":
+      if timer.config.dailyGoal == 0: ""
+      else: $timer.completedToday & "/" & $timer.config.dailyGoal
+    of "streak": $timer.stats.streak
+    of "long_break":
+      if timer.untilLongBreak.isSome:
+        timer.untilLongBreak.get.hoursAndMinutes
+      else: "-"
+    of "percent": $int(timer.progress * 100) & "%"
+    else: "{" & name & "}"
+
+proc renderTemplate*(timer: Timer, text: string, timers = ""): string =
+  ## Fills in the placeholders of a status template. ``{{`` stands for a
+  ## literal brace.
+  var i = 0
+  while i < text.len:
+    if text[i] == '{':
+      if i + 1 < text.len and text[i + 1] == '{':
+        result.add('{')
+        i += 2
+        continue
+      let close = text.find('}', i)
+      if close > i:
+        result.add(timer.placeholder(text[i + 1 ..< close], timers))
+        i = close + 1
+        continue
+    result.add(text[i])
+    inc i
+
+proc statusLine*(timer: Timer, timers = ""): string =
+  ## Renders the timer's status for the bar, following the status template.
+  ## Before starting and while paused, the idle and paused templates are used
+  ## instead, if they're set, and the ready template while getting ready.
+  let config = timer.config
+  let text =
+    if timer.preparing: config.readyTemplate
+    elif timer.state == tsNone and config.idleTemplate.isSome:
+      config.idleTemplate.get
+    elif timer.state != tsNone and not timer.running and
+         config.pausedTemplate.isSome:
+      config.pausedTemplate.get
+    else: config.statusTemplate
+  result = timer.renderTemplate(text, timers)
+
+proc cycleInfo(timer: Timer): string =
+  ## Describes where the timer is in the cycle of breaks.
+  let cycle = timer.config.profile.breakCycle
+  result = fmt"{timer.state.name}, pomodoro {timer.cyclePosition} of {cycle}" &
+           ", profile " & timer.config.profileName
+
+proc waybar*(timer: Timer, timers = ""): string =
+  ## Renders the timer's status as a line of Waybar's custom module JSON. The
+  ## class is one of ``planned``, ``pomodoro``, ``break``, or ``paused``.
+  let class =
+    if timer.state == tsNone: "planned"
+    elif not timer.running: "paused"
+    elif timer.state == tsPomodoro: "pomodoro"
+    else: "break"
+  result = $ %*{
+    "text": timer.statusLine(timers),
+    "class": class,
+    "tooltip": timer.cycleInfo,
+    "percentage": int(timer.progress * 100),
+  }
+
+proc polybar*(timer: Timer, timers = ""): string =
+  ## Renders the timer's status wrapped in the state's Polybar format tags.
+  ## All colors and decorations are reset afterwards.
+  let tags = timer.config.polybar
+  let tag =
+    if timer.state != tsNone and not timer.running: tags.paused
+    else:
+      case timer.state
+      of tsNone: tags.planned
+      of tsPomodoro: tags.pomodoro
+      of tsShortBreak: tags.shortBreak
+      of tsLongBreak: tags.longBreak
+  result = timer.statusLine(timers)
+  if tag.len > 0:
+    result = tag & result & "%{F- B- -u -o}"
+
+proc xmlEscape*(text: string): string =
+  ## Escapes the characters that have a meaning in markup.
+  for c in text:
+    case c
+    of '&': result.add("&amp;")
+    of '<': result.add("&lt;")
+    of '>': result.add("&gt;")
+    else: result.add(c)
+
+proc ansiColor*(hex: string): string =
+  ## Returns the escape code for a 24-bit ``#rrggbb`` foreground color, or ""
+  ## if the color is malformed.
+  if hex.len != 7 or hex[0] != '#': return
+  try:
+    let rgb = parseHexInt(hex[1..^1])
+    result = "\e[38;2;" & $(rgb shr 16) & ";" & $((rgb shr 8) and 0xff) & ";" &
+             $(rgb and 0xff) & "m"
+  except ValueError:
+    discard
+
+proc ansi*(timer: Timer, text: string): string =
+  ## Colors the text for the terminal by the timer's state. Paused timers are
+  ## dimmed as well.
+  var codes = ansiColor(timer.color)
+  if timer.state != tsNone and not timer.running:
+    codes.add("\e[2m")
+  result = if codes.len > 0: codes & text & "\e[0m" else: text
+
+proc render*(timer: Timer, format: OutputFormat, timers = ""): string =
+  ## Renders the timer's status in the given format.
+  let line = timer.statusLine(timers)
+  result =
+    case format
+    of ofPlain: line
+    of ofWaybar: timer.waybar(timers)
+    of ofPolybar: timer.polybar(timers)
+    of ofLemonbar: "%{F" & timer.color & "}" & line & "%{F-}"
+    of ofXmobar:
+      "<fc=" & timer.color & ">" & line.xmlEscape & "</fc>"
+    of ofPango:
+      # a running pomodoro stands out in bold
+      let weight =
+        if timer.running and timer.state == tsPomodoro: "bold" else: "normal"
+      "<span foreground=\"" & timer.color & "\" weight=\"" & weight & "\">" &
+      line.xmlEscape & "</span>"
@@ -0,0 +1,56 @@
+## Writing the status to a file or FIFO rather than stdout, so that bar scripts
+## can read it from a stable path.
+##
+## Regular files always hold the latest status only, replaced atomically.
+## FIFOs get a stream of status lines; when no one is reading, lines are
+## dropped, and the FIFO is reopened once a reader comes back.
+
+import std/os
+import std/posix
+
+import persistence
+
+type
+  OutputFile* = object ## where the status is written
+    path: string
+    fifo: bool
+    fd: cint                  ## the FIFO's fd, or -1 if it's not open yet
+
+proc openOutputFile*(path: string): OutputFile =
+  ## Prepares for writing to the file at the given path.
+  result = OutputFile(path: path, fd: -1)
+  var info: Stat
+  if stat(path.cstring, info) == 0:
+    result.fifo = S_ISFIFO(info.st_mode)
+
+proc isOpen*(output: OutputFile): bool =
+  result = output.path.len > 0
+
+proc closeFifo(output: var OutputFile) =
+  if output.fd >= 0:
+    discard posix.close(output.fd)
+    output.fd = -1
+
+proc close*(output: var OutputFile) =
+  output.closeFifo()
+  output.path = ""
+
+proc write*(output: var OutputFile, line: string) =
+  ## Writes a status line. Errors are logged, but never fatal.
+  if not output.isOpen: return
+  if not output.fifo:
+    try: writeAtomically(output.path, line & "\n")
+    except IOError, OSError:
+      stderr.writeLine("cannot write the status to " & output.path & ": " &
+                       getCurrentExceptionMsg())
+    return
+  if output.fd < 0:
+    # this fails with ENXIO until someone opens the FIFO for reading
+    output.fd = posix.open(output.path.cstring, O_WRONLY or O_NONBLOCK)
+    if output.fd < 0: return
+  let data = line & "\n"
+  if posix.write(output.fd, data.cstring, data.len) < 0:
+    # EPIPE when the reader has gone away, EAGAIN when it's not keeping up;
+    # either way, the line is dropped
+    if errno == EPIPE:
+      output.closeFifo()
@@ -0,0 +1,83 @@
+## Locations of pomod's files, following the XDG base directory specification.
+
+import std/os
+import std/posix
+
+proc xdgDir(variable, fallback: string): string =
+  ## Returns the directory stored in the environment variable, or the fallback
+  ## if it's unset. Relative paths are ignored, as mandated by the spec.
+  result = getEnv(variable)
+  if not result.isAbsolute:
+    result = fallback
+
+proc configDir*(): string =
+  ## Returns the directory the config file and sound live in.
+  result = xdgDir("XDG_CONFIG_HOME", getHomeDir()/".config")/"pomod"
+
+proc dataDir*(): string =
+  ## Returns the directory persistent data (such as history) is stored in.
+  result = xdgDir("XDG_DATA_HOME", getHomeDir()/".local"/"share")/"pomod"
+
+proc runtimeDir*(): string =
+  ## Returns the directory sockets and status files are placed in. If
+  ## ``XDG_RUNTIME_DIR`` is not set, a per-user directory in /tmp is used.
+  let base = getEnv("XDG_RUNTIME_DIR")
+  if base.isAbsolute:
+    result = base/"pomod"
+  else:
+    result = getTempDir()/("pomod-" & $int(getuid()))
+
+proc configFile*(): string =
+  result = configDir()/"config.toml"
+
+proc controlSocket*(): string =
+  result = runtimeDir()/"control.sock"
+
+proc eventSocket*(): string =
+  result = runtimeDir()/"events.sock"
+
+proc pidFile*(): string =
+  result = runtimeDir()/"pomod.pid"
+
+proc statusFile*(): string =
+  result = runtimeDir()/"status.json"
+
+proc stateFile*(): string =
+  ## Returns the file the timer's state is saved to on shutdown.
+  result = dataDir()/"state.json"
+
+proc tasksFile*(): string =
+  ## Returns the file the task list is kept in.
+  result = dataDir()/"tasks.json"
+
+proc daysFile*(): string =
+  ## Returns the file the pomodoros completed each day are kept in.
+  result = dataDir()/"days.json"
+
+proc journalFile*(): string =
+  ## Returns the file state changes are journaled to.
+  result = dataDir()/"journal.jsonl"
+
+proc historyFile*(): string =
+  ## Returns the database the history of pomodoros and breaks is kept in.
+  result = dataDir()/"history.db"
+
+proc usersDir*(): string =
+  ## Returns the directory the server mode keeps its users' data in, a
+  ## directory for each.
+  result = dataDir()/"users"
+
+proc ensureDir*(dir: string, private = false) =
+  ## Creates the directory if it doesn't exist yet. Private directories are
+  ## only accessible by the current user.
+  if not dirExists(dir):
+    stderr.writeLine("creating directory " & dir)
+    createDir(dir)
+    if private:
+      setFilePermissions(dir, {fpUserRead, fpUserWrite, fpUserExec})
+
+proc ensureDirs*() =
+  ## Creates all of pomod's directories.
+  ensureDir(configDir())
+  ensureDir(dataDir())
+  ensureDir(runtimeDir(), private = true)
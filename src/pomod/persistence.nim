@@ -0,0 +1,111 @@
+## Saving the timer's state, so that it survives pomod being stopped.
+
+import std/json
+import std/monotimes
+import std/os
+import std/options
+import std/times
+
+import config
+import paths
+import timer
+
+proc toJson*(timer: Timer): JsonNode =
+  ## Describes everything needed to pick the timer up where it was left.
+  result = %*{
+    "state": timer.state.name,
+    "remaining_ms": timer.remainingTime.inMilliseconds,
+    "extension_ms": timer.extension.inMilliseconds,
+    "running": timer.running,
+    "overtime": timer.overtime,
+    "snoozing": timer.snoozing,
+    "earned_break_ms": timer.earnedBreak.inMilliseconds,
+    "get_ready_ms": timer.getReady.inMilliseconds,
+    "interruptions": timer.pomodoroInterruptions,
+    "started": timer.stateStartTime.isSome,
+    "session_start": nil,
+    "break_counter": timer.breakCounter,
+    "schedule_index": timer.scheduleIndex,
+    "profile": timer.config.profileName,
+    "task": timer.label,
+    "tags": timer.tags,
+    "day": timer.stats.day,
+    "completed_today": timer.stats.completedToday,
+    "focused_today_ms": timer.stats.focusedToday.inMilliseconds,
+    "overridden_on": timer.stats.overriddenOn,
+    "saved_at_ms": getTime().toUnixFloat * 1000,
+  }
+  if timer.sessionStart.isSome:
+    result["session_start"] = %timer.sessionStart.get.toUnixFloat
+
+proc restore*(timer: var Timer, state: JsonNode) =
+  ## Picks up the state described by ``toJson``. If the timer was running, the
+  ## time that has passed since is taken into account. Raises a ``ValueError``
+  ## or a ``KeyError`` if the state is malformed.
+  let profile = state["profile"].getStr
+  if profile != timer.config.profileName and timer.config.hasProfile(profile):
+    var config = timer.config
+    config.selectProfile(profile)
+    timer.setConfig(config)
+  timer.state = parseState(state["state"].getStr)
+  timer.remainingTime =
+    initDuration(milliseconds = state["remaining_ms"].getBiggestInt)
+  timer.extension =
+    initDuration(milliseconds = state["extension_ms"].getBiggestInt)
+  timer.running = state["running"].getBool
+  timer.overtime = state["overtime"].getBool
+  timer.snoozing = state["snoozing"].getBool
+  timer.earnedBreak =
+    initDuration(milliseconds = state["earned_break_ms"].getBiggestInt)
+  timer.getReady =
+    initDuration(milliseconds = state["get_ready_ms"].getBiggestInt)
+  timer.pomodoroInterruptions = state["interruptions"].getInt
+  timer.label = state["task"].getStr
+  timer.tags.setLen(0)
+  for tag in state["tags"].getElems:
+    timer.tags.add(tag.getStr)
+  timer.stateStartTime =
+    if state["started"].getBool: some(getMonoTime())
+    else: none(MonoTime)
+  let sessionStart = state["session_start"]
+  timer.sessionStart =
+    if sessionStart.kind in {JInt, JFloat}:
+      some(fromUnixFloat(sessionStart.getFloat))
+    else: none(Time)
+  # the config may have changed since, leaving these past the cycle's or the
+  # schedule's end
+  let
+    index = state["schedule_index"].getInt
+    breaks = state["break_counter"].getInt
+  timer.scheduleIndex =
+    if index in 0 ..< timer.config.profile.schedule.len: index
+    else: 0
+  timer.breakCounter = breaks.clamp(0, timer.config.profile.breakCycle - 1)
+  # so that the daily goal and limit carry on
+  timer.stats.day = state["day"].getStr
+  timer.stats.completedToday = state["completed_today"].getInt
+  timer.stats.focusedToday =
+    initDuration(milliseconds = state["focused_today_ms"].getBiggestInt)
+  timer.stats.overriddenOn = state["overridden_on"].getStr
+  if timer.running:
+    # the monotonic clock doesn't survive a reboot, so the wall clock tells
+    # how much time has passed
+    let elapsed =
+      getTime().toUnixFloat * 1000 - state["saved_at_ms"].getFloat
+    timer.remainingTime -= initDuration(milliseconds = int64(max(elapsed, 0)))
+
+proc restoreState*(timer: var Timer, path = stateFile()) =
+  ## Picks up the state saved to the file. Raises an ``IOError`` if the file
+  ## can't be read, or a ``ValueError`` or ``KeyError`` if it's malformed.
+  timer.restore(parseFile(path))
+
+proc writeAtomically*(path, content: string) =
+  ## Writes the file by replacing it with a fully written temporary one, so
+  ## that readers never see it half-written.
+  let temporary = path & ".tmp"
+  writeFile(temporary, content)
+  moveFile(temporary, path)
+
+proc saveState*(timer: Timer, path = stateFile()) =
+  ## Writes the timer's state to the file.
+  writeAtomically(path, $timer.toJson)
@@ -0,0 +1,22 @@
+## Pushing the status to Polybar through ``polybar-msg``, for custom/ipc
+## modules.
+
+import std/osproc
+import std/streams
+
+const PushTimeout = 1000  # milliseconds
+
+proc pushToPolybar*(module, text: string) =
+  ## Replaces the text of the custom/ipc module with the given name. Failures
+  ## are logged.
+  try:
+    let
+      action = "#" & module & ".send." & text
+      process = startProcess("polybar-msg", args = ["action", action],
+                             options = {poUsePath, poStdErrToStdOut})
+    defer: process.close()
+    if process.waitForExit(PushTimeout) != 0:
+      stderr.writeLine("polybar-msg failed: " &
+                       process.outputStream.readAll)
+  except OSError as e:
+    stderr.writeLine("cannot run polybar-msg: " & e.msg)
@@ -0,0 +1,31 @@
+## A status segment for shell prompts, read from the status file so that it's
+## fast and doesn't bother the daemon.
+
+import std/json
+import std/times
+
+import config
+import output
+import paths
+import timer
+
+proc runPrompt*(): int =
+  ## Prints the status segment, or nothing if pomod isn't running or the timer
+  ## hasn't been started. Always succeeds, so that prompts don't show errors.
+  result = QuitSuccess
+  let status =
+    try: parseFile(statusFile())
+    except IOError, OSError, JsonParsingError: return
+  let config = loadConfigOrDefault()
+  try:
+    let
+      state = parseState(status["state"].getStr)
+      remaining = initDuration(seconds = status["remaining"].getBiggestInt)
+    if state == tsNone: return
+    var segment = state.stateIcon(config.icons) & " " &
+                  remaining.clockTime(config.precision)
+    if not status["running"].getBool:
+      segment.add(" paused")
+    echo segment
+  except KeyError, ValueError:
+    discard
@@ -0,0 +1,28 @@
+## Quiet hours, during which scheduled starts are suppressed and notifications
+## are hushed, eg. so that a one-off timer in the evening doesn't chime like a
+## pomodoro.
+
+import std/options
+import std/times
+
+import config
+
+proc contains*(period: QuietHours, time: DateTime): bool =
+  ## Returns whether the time falls within the period. Periods going past
+  ## midnight belong to the day they begin on.
+  let minute = time.hour * 60 + time.minute
+  if period.start <= period.stop:
+    result = time.weekday in period.days and
+             minute >= period.start and minute < period.stop
+  elif minute >= period.start:
+    result = time.weekday in period.days
+  elif minute < period.stop:
+    result = (time - 1.days).weekday in period.days
+
+proc quietMode*(periods: seq[QuietHours], time: DateTime): Option[QuietMode] =
+  ## Returns what happens to notifications at the given time, or none if it's
+  ## not within quiet hours. Where periods overlap, the quietest one wins.
+  for period in periods:
+    if time in period:
+      if result.isNone or period.notifications > result.get:
+        result = some(period.notifications)
@@ -0,0 +1,41 @@
+## Reminders, which run on tracks of their own alongside the pomodoro cycle
+## without affecting it, such as micro-breaks for resting the eyes.
+
+import std/times
+
+import config
+
+type
+  ReminderTrack* = object ## the progress towards a reminder
+    elapsed: Duration         ## counted towards the reminder, or the time it
+                              ## has been going on for
+    active: bool              ## whether the reminder is going on
+    notification*: uint32     ## the notification shown for it, if any
+  ReminderEvent* = enum ## what happened on a track
+    reNone
+    reDue                     ## the reminder should be shown
+    reOver                    ## the reminder's length has passed
+
+proc advance*(track: var ReminderTrack, reminder: ReminderConfig,
+              delta: Duration, counting: bool): ReminderEvent =
+  ## Moves the track forward by the given amount of time. Time only counts
+  ## towards the reminder while ``counting`` is true, but once the reminder is
+  ## going on, it always lasts its full length.
+  if track.active:
+    track.elapsed += delta
+    if track.elapsed >= reminder.length:
+      track.active = false
+      track.elapsed = DurationZero
+      result = reOver
+  elif counting:
+    track.elapsed += delta
+    if track.elapsed >= reminder.period:
+      track.active = reminder.length > DurationZero
+      track.elapsed = DurationZero
+      result = reDue
+
+proc restart*(track: var ReminderTrack) =
+  ## Counts towards the reminder from scratch. A reminder that's going on is
+  ## left alone.
+  if not track.active:
+    track.elapsed = DurationZero
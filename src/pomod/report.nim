@@ -0,0 +1,146 @@
+## Weekly reports of the history, in Markdown or HTML, for archiving or
+## sharing.
+
+import std/algorithm
+import std/strutils
+import std/tables
+import std/times
+
+import output
+import stats
+import timer
+
+type
+  ReportFormat* = enum ## what a report is written in
+    rfMarkdown = "markdown"
+    rfHtml = "html"
+  TaskTotal = object ## what was spent on a task
+    task: string
+    completed: int
+    focused: Duration
+
+const
+  DayNames = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"]
+  ChartBarWidth = 40
+  ChartHeight = 120
+
+proc reportFormat*(path: string): ReportFormat =
+  ## Returns the format a report written to the file should be in, going by
+  ## its extension.
+  result =
+    if path.toLowerAscii.endsWith(".html") or
+       path.toLowerAscii.endsWith(".htm"): rfHtml
+    else: rfMarkdown
+
+proc byDay(sessions: openArray[Session], start: DateTime): array[7, int] =
+  ## Counts the pomodoros completed on each day of the week.
+  for session in sessions:
+    if session.state != tsPomodoro or session.outcome != soCompleted: continue
+    let day = (session.start - start.toTime).inDays
+    if day in 0 .. 6: inc result[day]
+
+proc byTask(sessions: openArray[Session]): seq[TaskTotal] =
+  ## Sums up the completed pomodoros by their task, the most worked on first.
+  var totals: OrderedTable[string, TaskTotal]
+  for session in sessions:
+    if session.state != tsPomodoro or session.outcome != soCompleted: continue
+    let task = if session.task.len == 0: "(no task)" else: session.task
+    var total = totals.getOrDefault(task, TaskTotal(task: task))
+    inc total.completed
+    total.focused += session.stop - session.start
+    totals[task] = total
+  for total in totals.values:
+    result.add(total)
+  result.sort do (a, b: TaskTotal) -> int:
+    cmp(b.focused, a.focused)
+
+proc chart(days: array[7, int]): string =
+  ## Draws the pomodoros completed each day as an SVG bar chart.
+  let
+    most = max(max(days), 1)
+    width = ChartBarWidth * 7
+    bars = ChartHeight - 30
+  result = "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"" & $width &
+           "\" height=\"" & $ChartHeight & "\">\n"
+  for i, count in days:
+    let
+      height = count * bars div most
+      x = i * ChartBarWidth + 5
+      y = bars - height + 15
+    result.add("  <rect x=\"" & $x & "\" y=\"" & $y & "\" width=\"" &
+               $(ChartBarWidth - 10) & "\" height=\"" & $height &
+               "\" fill=\"#26a641\"/>\n")
+    result.add("  <text x=\"" & $(x + (ChartBarWidth - 10) div 2) &
+               "\" y=\"" & $(y - 3) & "\" font-size=\"11\" " &
+               "text-anchor=\"middle\">" & $count & "</text>\n")
+    result.add("  <text x=\"" & $(x + (ChartBarWidth - 10) div 2) &
+               "\" y=\"" & $(ChartHeight - 2) & "\" font-size=\"11\" " &
+               "text-anchor=\"middle\">" & DayNames[i] & "</text>\n")
+  result.add("</svg>\n")
+
+proc textChart(days: array[7, int]): string =
+  ## Draws the pomodoros completed each day as bars of text.
+  for i, count in days:
+    result.add(DayNames[i] & " " & repeat("█", count) & " " & $count & "\n")
+
+proc weeklyReport*(sessions: openArray[Session], start: DateTime,
+                   format: ReportFormat): string =
+  ## Writes the report of the week beginning at the given time.
+  let
+    summary = sessions.summarize
+    title = "pomod weekly report, " & start.format("yyyy-MM-dd") & " to " &
+            (start + days(6)).format("yyyy-MM-dd")
+    days = sessions.byDay(start)
+    tasks = sessions.byTask
+    totals = [
+      ("pomodoros completed", $summary.completed),
+      ("focus time", summary.focused.hoursAndMinutes),
+      ("break adherence", $int(summary.breakAdherence * 100 + 0.5) & "%"),
+      ("abandonment rate", $int(summary.abandonmentRate * 100 + 0.5) & "%"),
+    ]
+  case format
+  of rfMarkdown:
+    result = "# " & title & "\n\n## Totals\n\n"
+    for total in totals:
+      result.add("- " & total[0] & ": " & total[1] & "\n")
+    result.add("\n## Pomodoros by day\n\n```\n" & days.textChart & "```\n")
+    result.add("\n## By task\n\n| task | pomodoros | focus time |\n" &
+               "|------|-----------|------------|\n")
+    for total in tasks:
+      result.add("| " & total.task.replace("|", "\\|") & " | " &
+                 $total.completed & " | " & total.focused.hoursAndMinutes &
+                 " |\n")
+    if summary.byTag.len > 0:
+      result.add("\n## By tag\n\n| tag | pomodoros | focus time |\n" &
+                 "|-----|-----------|------------|\n")
+      for tag, total in summary.byTag:
+        result.add("| " & tag.replace("|", "\\|") & " | " &
+                   $total.completed & " | " &
+                   total.focused.hoursAndMinutes & " |\n")
+  of rfHtml:
+    result = "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n" &
+             "<title>" & title & "</title>\n" &
+             "<style>body { font-family: sans-serif; max-width: 40em; " &
+             "margin: auto; } td, th { padding: 0.2em 1em; text-align: " &
+             "left; }</style>\n</head>\n<body>\n<h1>" & title & "</h1>\n" &
+             "<h2>Totals</h2>\n<table>\n"
+    for total in totals:
+      result.add("<tr><th>" & total[0] & "</th><td>" & total[1] &
+                 "</td></tr>\n")
+    result.add("</table>\n<h2>Pomodoros by day</h2>\n" & days.chart)
+    result.add("<h2>By task</h2>\n<table>\n<tr><th>task</th>" &
+               "<th>pomodoros</th><th>focus time</th></tr>\n")
+    for total in tasks:
+      result.add("<tr><td>" & total.task.xmlEscape & "</td><td>" &
+                 $total.completed & "</td><td>" &
+                 total.focused.hoursAndMinutes & "</td></tr>\n")
+    result.add("</table>\n")
+    if summary.byTag.len > 0:
+      result.add("<h2>By tag</h2>\n<table>\n<tr><th>tag</th>" &
+                 "<th>pomodoros</th><th>focus time</th></tr>\n")
+      for tag, total in summary.byTag:
+        result.add("<tr><td>" & tag.xmlEscape & "</td><td>" &
+                   $total.completed & "</td><td>" &
+                   total.focused.hoursAndMinutes & "</td></tr>\n")
+      result.add("</table>\n")
+    result.add("</body>\n</html>\n")
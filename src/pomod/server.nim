@@ -0,0 +1,187 @@
+## The server mode, in which a single pomod hosts timers for several users,
+## such as a small team, over the REST API. Every user authenticates with a
+## token of their own, and has a timer and history of their own, kept in a
+## directory of their own. Notifications, sounds and status lines are left to
+## the clients.
+##
+## Tokens are sent in the clear, as the server speaks plain HTTP only. Anywhere
+## but a trusted network it's meant to sit behind a reverse proxy that
+## terminates TLS.
+
+import std/json
+import std/os
+import std/posix
+import std/strutils
+import std/times
+import std/uri
+
+import commands
+import config
+import control
+import dispatch
+import history
+import httpapi
+import paths
+import persistence
+import timer
+
+type
+  Account = object ## a user's timer, and where their data is kept
+    user: UserConfig
+    dir: string
+    timer: Timer
+    history: History
+    unsaved: bool             ## whether the timer's state has changed since
+                              ## it was last saved
+
+proc openAccount(user: UserConfig, config: Config): Account =
+  ## Sets up the user's timer, continuing where it left off.
+  result = Account(user: user, dir: usersDir()/user.name,
+                   timer: initTimer(config))
+  ensureDir(result.dir, private = true)
+  if fileExists(result.dir/"state.json"):
+    try: result.timer.restoreState(result.dir/"state.json")
+    except CatchableError as e:
+      stderr.writeLine("cannot restore " & user.name & "'s timer: " & e.msg)
+  result.history =
+    try: openHistory(result.dir/"history.db")
+    except DbError as e:
+      stderr.writeLine("cannot open " & user.name & "'s history: " & e.msg)
+      History()
+
+proc save(account: var Account) =
+  ## Saves the timer's state and records the sessions that have ended.
+  for session in account.timer.stats.endedSessions:
+    try: account.history.record(session)
+    except DbError as e:
+      stderr.writeLine("cannot record " & account.user.name & "'s history: " &
+                       e.msg)
+  account.timer.stats.endedSessions.setLen(0)
+  if account.unsaved:
+    try: account.timer.saveState(account.dir/"state.json")
+    except IOError, OSError:
+      stderr.writeLine("cannot save " & account.user.name & "'s timer: " &
+                       getCurrentExceptionMsg())
+    account.unsaved = false
+
+proc statusJson(account: Account): JsonNode =
+  ## Describes the user's timer.
+  result = account.timer.statusJson
+  result["user"] = %account.user.name
+
+proc authenticate(accounts: seq[Account], request: HttpRequest): int =
+  ## Returns the index of the account whose token the request presents as
+  ## ``Authorization: Bearer <token>``, or -1 if there's none.
+  const Scheme = "Bearer "
+  let authorization = request.header("authorization")
+  result = -1
+  if not authorization.startsWith(Scheme): return
+  let token = authorization[Scheme.len .. ^1].strip
+  for i, account in accounts:
+    if secureEquals(account.user.token, token): return i
+
+proc execute(account: var Account, command: Command) =
+  ## Runs a command on the user's timer. Raises a ``CommandError`` if it can't
+  ## be run.
+  account.timer.checkStrict(command)
+  account.timer.checkLimit(command)
+  if command.kind == ckReset: account.timer.reset(account.timer.config)
+  else: discard account.timer.control(command)
+  account.unsaved = true
+
+proc handle(accounts: var seq[Account], request: HttpRequest): HttpResponse =
+  ## Responds to a request on behalf of the user who made it.
+  let i = accounts.authenticate(request)
+  if i < 0: return errorResponse(401, "a valid bearer token is required")
+  let
+    parts = request.path.split('?', maxsplit = 1)
+    path = parts[0]
+  case path
+  of "/status":
+    if request.verb != "GET": return errorResponse(405, "use GET")
+    result = jsonResponse(200, accounts[i].statusJson)
+  of "/start", "/pause", "/resume", "/toggle", "/skip", "/abandon", "/reset":
+    if request.verb != "POST": return errorResponse(405, "use POST")
+    let command = Command(kind: parseEnum[CommandKind](path[1..^1]))
+    try: accounts[i].execute(command)
+    except CommandError as e: return accounts[i].timer.errorResponse(e)
+    result = jsonResponse(200, accounts[i].statusJson)
+  of "/task":
+    # given as ?text=...&tags=..., as request bodies aren't read
+    if request.verb != "POST": return errorResponse(405, "use POST")
+    accounts[i].timer.label = ""
+    accounts[i].timer.tags = @[]
+    if parts.len > 1:
+      for (key, value) in parts[1].decodeQuery:
+        case key
+        of "text": accounts[i].timer.label = value
+        of "tags": accounts[i].timer.tags = parseTags(value)
+        else: discard
+    accounts[i].unsaved = true
+    result = jsonResponse(200, accounts[i].statusJson)
+  of "/history":
+    if request.verb != "GET": return errorResponse(405, "use GET")
+    if not accounts[i].history.isOpen:
+      return errorResponse(501, "the history couldn't be opened")
+    var sessions = newJArray()
+    for session in accounts[i].history.sessions:
+      sessions.add(session.toJson)
+    result = jsonResponse(200, sessions)
+  else:
+    result = errorResponse(404, "no such endpoint: " & path)
+
+proc serve*(config: Config): int =
+  ## Hosts the configured users' timers on the REST API's address until
+  ## SIGTERM or SIGINT is received. Returns the exit code.
+  if config.httpAddress.len == 0:
+    stderr.writeLine("pomod: serve needs an address, set with http or --http")
+    return QuitFailure
+  if config.users.len == 0:
+    stderr.writeLine("pomod: serve needs users, see [[users]] in the readme")
+    return QuitFailure
+  var server =
+    try: openHttpServer(config.httpAddress)
+    except CatchableError as e:
+      stderr.writeLine("pomod: cannot serve on " & config.httpAddress & ": " &
+                       e.msg)
+      return QuitFailure
+  var accounts: seq[Account]
+  for user in config.users:
+    accounts.add(openAccount(user, config))
+  stderr.writeLine("serving " & $accounts.len & " users on " &
+                   config.httpAddress)
+  let (host, _) = parseAddress(config.httpAddress)
+  if host notin ["127.0.0.1", "localhost", "::1"]:
+    stderr.writeLine("warning: tokens are sent over plain HTTP, put the " &
+                     "server behind a TLS-terminating proxy")
+
+  # the signals are waited for between ticks, rather than handled
+  discard sighold(SIGTERM)
+  discard sighold(SIGINT)
+  var signals: SigSet
+  discard sigemptyset(signals)
+  discard sigaddset(signals, SIGTERM)
+  discard sigaddset(signals, SIGINT)
+  let
+    tick = config.tickInterval.inMilliseconds
+    timespec = Timespec(tv_sec: posix.Time(clong(tick div 1000)),
+                        tv_nsec: int(tick mod 1000) * 1_000_000)
+  while true:
+    var info: SigInfo
+    let signal = sigtimedwait(signals, info, timespec)
+    if signal == SIGTERM or signal == SIGINT: break
+    server.poll do (request: HttpRequest) -> HttpResponse:
+      accounts.handle(request)
+    for account in accounts.mitems:
+      let before = (account.timer.state, account.timer.running)
+      account.timer.poll()
+      if (account.timer.state, account.timer.running) != before:
+        account.unsaved = true
+      account.save()
+
+  for account in accounts.mitems:
+    account.unsaved = true
+    account.save()
+    account.history.close()
+  server.close()
+  result = QuitSuccess
@@ -0,0 +1,176 @@
+## Sinks, the places the status is sent to. The main sink is stdout, the output
+## file, or a Polybar IPC module, and any number of extra sinks can be
+## configured alongside it, each with its own format.
+##
+## Every sink is an ``OutputBackend``. Besides the built-in ones, custom
+## backends can be registered with ``registerBackend``; they're opened along
+## with the configured sinks.
+
+import std/monotimes
+import std/posix
+import std/times
+
+import config
+import output
+import outputfile
+import polybaripc
+import timer
+import timers
+import xroot
+
+type
+  TimerSnapshot* = object ## what's being output on a given tick
+    timer*: Timer
+    hidden*: bool             ## whether the status is hidden by the user
+    timers*: seq[NamedTimer]  ## the named timers running alongside
+  OutputBackend* = ref object of RootObj ## a place to send the status to
+    format*: OutputFormat
+    timerName*: string        ## the named timer shown, the main one if empty
+    lastLine*: string         ## the line last sent, for detecting changes
+  LineBackend = ref object of OutputBackend
+    ## a backend printing lines, to stdout or a file
+    color: bool               ## whether plain output is colored with ANSI codes
+    lastWrite: MonoTime
+  StdoutBackend* = ref object of LineBackend
+    overwrite: bool           ## keep the status on a single terminal line
+  FileBackend* = ref object of LineBackend
+    file: OutputFile
+  XrootBackend* = ref object of OutputBackend
+    root: RootWindow
+  PolybarIpcBackend* = ref object of OutputBackend
+    module: string
+
+var customBackends: seq[OutputBackend]
+
+method render*(backend: OutputBackend, snapshot: TimerSnapshot) {.base.} =
+  ## Sends the status to the backend.
+  discard
+
+method close*(backend: OutputBackend, snapshot: TimerSnapshot) {.base.} =
+  ## Sends a final status to the backend and releases what it holds.
+  discard
+
+proc registerBackend*(backend: OutputBackend) =
+  ## Adds a custom backend, which is sent the status along with the built-in
+  ## ones.
+  customBackends.add(backend)
+
+proc shownTimer(backend: OutputBackend, snapshot: TimerSnapshot): Timer =
+  result = snapshot.timer
+  let i = snapshot.timers.find(backend.timerName)
+  if i >= 0: result = snapshot.timers[i].timer
+
+proc line*(backend: OutputBackend, snapshot: TimerSnapshot): string =
+  ## Renders the status in the backend's format. A hidden status is rendered
+  ## as an empty line, so that whatever shows it clears it; so is the status of
+  ## a named timer that isn't running.
+  if snapshot.hidden: return ""
+  if backend.timerName.len == 0:
+    result = snapshot.timer.render(backend.format, snapshot.timers.combined)
+  elif snapshot.timers.find(backend.timerName) >= 0:
+    result = backend.shownTimer(snapshot).render(backend.format)
+
+proc line(backend: LineBackend, snapshot: TimerSnapshot): string =
+  result = OutputBackend(backend).line(snapshot)
+  if backend.color and backend.format == ofPlain and result.len > 0:
+    result = backend.shownTimer(snapshot).ansi(result)
+
+proc shouldWrite(backend: LineBackend, config: Config, line: string): bool =
+  ## Returns whether a line has to be written, given the on-change and
+  ## heartbeat settings.
+  let heartbeatDue = config.heartbeat > DurationZero and
+                     getMonoTime() - backend.lastWrite >= config.heartbeat
+  result = not config.onChange or line != backend.lastLine or heartbeatDue
+
+method render*(backend: StdoutBackend, snapshot: TimerSnapshot) =
+  let line = backend.line(snapshot)
+  if backend.overwrite:
+    # keep the status on a single line in the terminal
+    stdout.write("\r" & line & "\e[K")
+    stdout.flushFile()
+  elif backend.shouldWrite(snapshot.timer.config, line):
+    echo line
+    backend.lastWrite = getMonoTime()
+  backend.lastLine = line
+
+method close*(backend: StdoutBackend, snapshot: TimerSnapshot) =
+  let line = backend.line(TimerSnapshot(timer: snapshot.timer))
+  if backend.overwrite: stdout.write("\r" & line & "\e[K\n")
+  else: echo line
+
+method render*(backend: FileBackend, snapshot: TimerSnapshot) =
+  let line = backend.line(snapshot)
+  if backend.shouldWrite(snapshot.timer.config, line):
+    backend.file.write(line)
+    backend.lastWrite = getMonoTime()
+  backend.lastLine = line
+
+method close*(backend: FileBackend, snapshot: TimerSnapshot) =
+  backend.file.write(backend.line(TimerSnapshot(timer: snapshot.timer)))
+  backend.file.close()
+
+method render*(backend: XrootBackend, snapshot: TimerSnapshot) =
+  let line = backend.line(snapshot)
+  if line != backend.lastLine:
+    backend.root.setStatus(line)
+    backend.lastLine = line
+
+method close*(backend: XrootBackend, snapshot: TimerSnapshot) =
+  backend.root.close()
+
+method render*(backend: PolybarIpcBackend, snapshot: TimerSnapshot) =
+  # pushing is fairly expensive, so only do it when there's news
+  let line = backend.line(snapshot)
+  if line != backend.lastLine:
+    pushToPolybar(backend.module, line)
+    backend.lastLine = line
+
+proc openSink*(sink: SinkConfig, config: Config,
+               overwrite = false): OutputBackend =
+  ## Opens the backend for the sink. Raises an ``IOError`` if that's not
+  ## possible.
+  case sink.kind
+  of skStdout:
+    let color =
+      case config.colorMode
+      of cmAlways: true
+      of cmNever: false
+      of cmAuto: isatty(STDOUT_FILENO) != 0
+    result = StdoutBackend(overwrite: overwrite, color: color,
+                           lastWrite: getMonoTime())
+  of skFile:
+    result = FileBackend(file: openOutputFile(sink.path),
+                         color: config.colorMode == cmAlways,
+                         lastWrite: getMonoTime())
+  of skXroot:
+    result = XrootBackend(root: openRootWindow(config.xroot.append,
+                                               config.xroot.separator))
+  of skPolybarIpc:
+    result = PolybarIpcBackend(module: sink.module)
+  result.format = sink.format
+  result.timerName = sink.timer
+
+proc mainSink*(config: Config): SinkConfig =
+  ## Returns the sink the status goes to when no other sinks are configured.
+  result =
+    if config.polybar.ipcModule.len > 0:
+      SinkConfig(kind: skPolybarIpc, module: config.polybar.ipcModule)
+    elif config.outputPath.len > 0:
+      SinkConfig(kind: skFile, path: config.outputPath)
+    else:
+      SinkConfig(kind: skStdout)
+  result.format = config.outputFormat
+
+proc openSinks*(config: Config, overwrite = false): seq[OutputBackend] =
+  ## Opens the main sink, the root window if enabled, the configured extra
+  ## sinks, and the registered custom backends. Sinks that can't be opened are
+  ## logged and left out.
+  var sinks = @[config.mainSink]
+  if config.xroot.enabled:
+    sinks.add(SinkConfig(kind: skXroot, format: config.outputFormat))
+  sinks.add(config.sinks)
+  for sink in sinks:
+    try: result.add(openSink(sink, config, overwrite))
+    except IOError as e:
+      stderr.writeLine("cannot open the " & $sink.kind & " sink: " & e.msg)
+  result.add(customBackends)
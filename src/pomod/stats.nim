@@ -0,0 +1,139 @@
+## Statistics about the history, summed up over a period such as the current
+## week.
+
+import std/algorithm
+import std/json
+import std/strutils
+import std/tables
+import std/times
+
+import output
+import timer
+
+type
+  StatsPeriod* = enum ## the period statistics are summed up over
+    spToday = "today"
+    spWeek = "week"           ## since Monday
+    spMonth = "month"         ## since the first of the month
+  TagTotal* = object ## the completed pomodoros carrying a tag
+    completed*: int
+    focused*: Duration
+  Summary* = object ## the statistics of a period
+    completed*, skipped*, abandoned*, cutShort*: int ## pomodoros
+    focused*: Duration        ## the length of the completed pomodoros
+    breaksTaken*, breaksSkipped*: int
+    byHour*: array[24, int]   ## completed pomodoros by the hour they began in
+    byTag*: OrderedTable[string, TagTotal] ## completed pomodoros by tag, in
+                                           ## the order the tags came up in
+
+proc periodStart*(period: StatsPeriod, time: DateTime,
+                  dayStart = DurationZero): DateTime =
+  ## Returns when the period the time falls in began. Days begin at the given
+  ## time of day, so that late nights count towards the day before.
+  let
+    day = time - dayStart
+    midnight = dateTime(day.year, day.month, day.monthday,
+                        zone = time.timezone)
+  result =
+    case period
+    of spToday: midnight + dayStart
+    of spWeek: midnight - days(ord(day.weekday)) + dayStart
+    of spMonth:
+      dateTime(day.year, day.month, 1, zone = time.timezone) + dayStart
+
+proc summarize*(sessions: openArray[Session]): Summary =
+  ## Sums up the sessions.
+  for session in sessions:
+    if session.state == tsPomodoro:
+      case session.outcome
+      of soCompleted:
+        inc result.completed
+        result.focused += session.stop - session.start
+        inc result.byHour[session.start.local.hour]
+        for tag in session.tags:
+          var total = result.byTag.getOrDefault(tag)
+          inc total.completed
+          total.focused += session.stop - session.start
+          result.byTag[tag] = total
+      of soSkipped: inc result.skipped
+      of soAbandoned: inc result.abandoned
+      of soCutShort: inc result.cutShort
+    else:
+      if session.outcome == soCompleted: inc result.breaksTaken
+      else: inc result.breaksSkipped
+
+proc pomodoros*(summary: Summary): int =
+  ## Returns the amount of pomodoros that ended in any way.
+  result = summary.completed + summary.skipped + summary.abandoned +
+           summary.cutShort
+
+proc breakAdherence*(summary: Summary): float =
+  ## Returns the share of breaks that were taken rather than skipped.
+  let breaks = summary.breaksTaken + summary.breaksSkipped
+  result = if breaks == 0: 1.0 else: summary.breaksTaken / breaks
+
+proc abandonmentRate*(summary: Summary): float =
+  ## Returns the share of pomodoros that were abandoned.
+  result =
+    if summary.pomodoros == 0: 0.0
+    else: summary.abandoned / summary.pomodoros
+
+proc busiestHours*(summary: Summary, count = 3): seq[int] =
+  ## Returns the hours in which the most pomodoros began, busiest first.
+  var hours: seq[int]
+  for hour, completed in summary.byHour:
+    if completed > 0: hours.add(hour)
+  hours.sort do (a, b: int) -> int:
+    cmp(summary.byHour[b], summary.byHour[a])
+  result = hours[0 ..< min(count, hours.len)]
+
+proc percent(share: float): string =
+  result = $int(share * 100 + 0.5) & "%"
+
+proc render*(summary: Summary, period: StatsPeriod): string =
+  ## Describes the statistics for reading in the terminal.
+  let title =
+    case period
+    of spToday: "today"
+    of spWeek: "this week"
+    of spMonth: "this month"
+  var hours: seq[string]
+  for hour in summary.busiestHours:
+    hours.add(align($hour, 2, '0') & ":00 (" & $summary.byHour[hour] & ")")
+  let breaks = summary.breaksTaken + summary.breaksSkipped
+  result = title & "\n" &
+    "  pomodoros completed  " & $summary.completed & "\n" &
+    "  focus time           " & summary.focused.hoursAndMinutes & "\n" &
+    "  break adherence      " & summary.breakAdherence.percent & " (" &
+      $summary.breaksTaken & " of " & $breaks & " breaks taken)\n" &
+    "  abandonment rate     " & summary.abandonmentRate.percent & " (" &
+      $summary.abandoned & " of " & $summary.pomodoros & " pomodoros)\n" &
+    "  busiest hours        " &
+      (if hours.len == 0: "-" else: hours.join(", ")) & "\n"
+  if summary.byTag.len > 0:
+    result.add("  by tag\n")
+    for tag, total in summary.byTag:
+      result.add("    " & alignLeft("#" & tag, 19) & $total.completed &
+                 " (" & total.focused.hoursAndMinutes & ")\n")
+
+proc toJson*(summary: Summary, period: StatsPeriod): JsonNode =
+  ## Describes the statistics in a machine-readable way.
+  result = %*{
+    "period": $period,
+    "completed": summary.completed,
+    "skipped": summary.skipped,
+    "abandoned": summary.abandoned,
+    "cut_short": summary.cutShort,
+    "focused": summary.focused.inSeconds,
+    "breaks_taken": summary.breaksTaken,
+    "breaks_skipped": summary.breaksSkipped,
+    "break_adherence": summary.breakAdherence,
+    "abandonment_rate": summary.abandonmentRate,
+    "busiest_hours": summary.busiestHours,
+    "by_tag": {:},
+  }
+  for tag, total in summary.byTag:
+    result["by_tag"][tag] = %*{
+      "completed": total.completed,
+      "focused": total.focused.inSeconds,
+    }
@@ -0,0 +1,51 @@
+## Streaks of consecutive days on which enough pomodoros were completed. The
+## pomodoros completed each day are kept in the data directory, so that
+## streaks carry on across restarts.
+
+import std/json
+import std/os
+import std/tables
+import std/times
+
+import paths
+import persistence
+
+type
+  DayLog* = object ## the pomodoros completed on each day
+    days*: Table[string, int] ## by ``yyyy-MM-dd``
+
+proc loadDays*(path = daysFile()): DayLog =
+  ## Loads the day log from the file, or returns an empty one if the file
+  ## doesn't exist. Raises an ``IOError`` if it can't be read, or a
+  ## ``ValueError`` if it's malformed.
+  if not fileExists(path): return
+  for day, completed in parseFile(path).getFields:
+    result.days[day] = completed.getInt
+
+proc save*(log: DayLog, path = daysFile()) =
+  ## Writes the day log to the file.
+  var days = newJObject()
+  for day, completed in log.days:
+    days[day] = %completed
+  writeAtomically(path, $days)
+
+proc record*(log: var DayLog, day: string, completed: int) =
+  ## Adds completed pomodoros to the day.
+  log.days.mgetOrPut(day, 0) += completed
+
+proc completedOn*(log: DayLog, day: string): int =
+  ## Returns the amount of pomodoros completed on the day.
+  result = log.days.getOrDefault(day)
+
+proc dayBefore(day: string): string =
+  result = (parse(day, "yyyy-MM-dd") - 1.days).format("yyyy-MM-dd")
+
+proc streak*(log: DayLog, today: string, needed: int): int =
+  ## Returns the amount of consecutive days on which at least ``needed``
+  ## pomodoros were completed. Today only breaks the streak once it's over,
+  ## so until enough is done, the streak runs up to yesterday.
+  var day = today
+  if log.completedOn(day) < needed: day = day.dayBefore
+  while log.completedOn(day) >= needed:
+    inc result
+    day = day.dayBefore
@@ -0,0 +1,56 @@
+## The task list, with an estimate of how many pomodoros each task takes, and
+## the amount of pomodoros actually spent on it. The list is kept in the data
+## directory, so that it survives restarts.
+
+import std/json
+import std/os
+
+import paths
+import persistence
+
+type
+  Task* = object ## a task on the list
+    name*: string
+    estimate*: int            ## the pomodoros it's expected to take
+    actual*: int              ## the pomodoros completed on it
+  TaskList* = object
+    tasks*: seq[Task]
+
+proc toJson*(task: Task): JsonNode =
+  result = %*{
+    "name": task.name,
+    "estimate": task.estimate,
+    "actual": task.actual,
+    "delta": task.actual - task.estimate,
+  }
+
+proc loadTasks*(path = tasksFile()): TaskList =
+  ## Loads the task list from the file, or returns an empty one if the file
+  ## doesn't exist. Raises an ``IOError`` if it can't be read, or a
+  ## ``ValueError`` if it's malformed.
+  if not fileExists(path): return
+  for task in parseFile(path).getElems:
+    result.tasks.add(Task(name: task["name"].getStr,
+                          estimate: task["estimate"].getInt,
+                          actual: task["actual"].getInt))
+
+proc save*(list: TaskList, path = tasksFile()) =
+  ## Writes the task list to the file.
+  var tasks = newJArray()
+  for task in list.tasks:
+    tasks.add(task.toJson)
+  writeAtomically(path, $tasks)
+
+proc find*(list: TaskList, name: string): int =
+  ## Returns the index of the task with the given name, or -1 if it's not on
+  ## the list.
+  for i, task in list.tasks:
+    if task.name == name: return i
+  result = -1
+
+proc summary*(task: Task): string =
+  ## Describes how the task is going, eg. ``refactor parser 2/3 (-1)``.
+  let delta = task.actual - task.estimate
+  result = task.name & " " & $task.actual & "/" & $task.estimate
+  if delta != 0:
+    result.add(" (" & (if delta > 0: "+" else: "") & $delta & ")")
@@ -0,0 +1,615 @@
+## The pomodoro timer itself.
+
+import std/monotimes
+import std/options
+import std/random
+import std/strutils
+import std/sysrand
+import std/times
+
+import config
+
+type
+  TimerState* = enum ## the state of a pomodoro timer
+    tsNone        ## planned/not started yet
+    tsPomodoro = "pomodoro"
+    tsShortBreak = "short break"
+    tsLongBreak = "long break"
+  InterruptionKind* = enum ## where an interruption came from
+    irInternal = "internal"           ## from yourself, eg. a sudden urge to
+                                      ## check the mail
+    irExternal = "external"           ## from someone else, eg. a phone call
+  Interruption* = object ## an interruption recorded against a pomodoro
+    kind*: InterruptionKind
+    note*: string
+    time*: Time
+  SessionOutcome* = enum ## how a pomodoro or break ended
+    soCompleted = "completed"
+    soSkipped = "skipped"
+    soAbandoned = "abandoned"
+    soCutShort = "cut_short"          ## reset or restarted midway
+  Session* = object ## a pomodoro or break that has ended
+    id*: string                       ## a UUID telling it apart from
+                                      ## sessions on other machines
+    state*: TimerState
+    outcome*: SessionOutcome
+    start*, stop*: Time               ## when it began and ended
+    task*: string
+    tags*: seq[string]
+    interruptions*: int
+  PomodoroOutcome* = object ## how a pomodoro ended
+    focused*: Duration                ## how long it went on for
+    completed*: bool                  ## whether it ran its full length
+    task*: string                     ## what it was spent on, if known
+  TimerStats* = object ## counters of what the timer has done so far
+    pomodorosCompleted*, pomodorosSkipped*: int
+    pomodorosCutShort*: int           ## pomodoros reset or restarted midway
+    pomodorosAbandoned*: int          ## pomodoros voided with abandon
+    recentPomodoros*: seq[PomodoroOutcome] ## how the last pomodoros ended,
+                                           ## oldest first
+    breaksCompleted*, breaksSkipped*: int
+    focusedToday*: Duration           ## the length of today's completed
+                                      ## pomodoros
+    completedToday*: int              ## the pomodoros completed today
+    day*: string                      ## the day focusedToday and
+                                      ## completedToday count for
+    pomodoroOvertime*, breakOvertime*: Duration ## time spent past the end of
+                                                ## pomodoros and breaks
+    breaksOverrun*: int               ## the amount of breaks that went into
+                                      ## overtime
+    breaksSnoozed*: int
+    interruptions*: seq[Interruption] ## recorded since pomod was started
+    streak*: int                      ## consecutive days on which enough
+                                      ## pomodoros were completed
+    overriddenOn*: string             ## the day the daily limit was lifted on
+    endedSessions*: seq[Session]      ## ended since they were last taken
+                                      ## for the history
+  Timer* = object
+    config*: Config                   ## the configuration the timer runs with
+    running*: bool                    ## is the timer running or paused
+    state*: TimerState                ## the current state
+    stateStartTime*: Option[MonoTime] ## when the state was started
+    sessionStart*: Option[Time]       ## when the state first ran, by the wall
+                                      ## clock
+    remainingTime*: Duration
+    extension*: Duration              ## time added to the current state
+    overtime*: bool                   ## the state's time is up, but the user
+                                      ## hasn't moved on yet
+    snoozing*: bool                   ## the pomodoro goes on for a while
+                                      ## after its break was snoozed
+    earnedBreak*: Duration            ## in flowtime, the length of the break
+                                      ## the last pomodoro earned
+    label*: string                    ## what the timer is for, such as the
+                                      ## task at hand, if anything
+    tags*: seq[string]                ## the task's tags, eg. the client it's
+                                      ## for
+    getReady*: Duration               ## the time left to get ready before
+                                      ## the pomodoro begins, if any
+    pomodoroInterruptions*: int       ## recorded against the current
+                                      ## pomodoro
+    laps*: seq[Duration]              ## the stopwatch's time at each lap mark
+    lastPoll: MonoTime                ## last time when poll() was called
+    breakCounter*: int
+    scheduleIndex*: int               ## position in the profile's schedule
+    stats*: TimerStats
+    stateChangeProc: proc (newState: TimerState)
+
+const KeptOutcomes* = 32 ## the amount of recent pomodoros remembered
+
+proc name*(state: TimerState): string =
+  ## Returns the human-readable name of the state.
+  result = if state == tsNone: "planned" else: $state
+
+proc newSessionId*(): string =
+  ## Returns a random (version 4) UUID for a session.
+  var bytes: array[16, byte]
+  if not urandom(bytes):
+    # the system's source of randomness is unavailable, which is unlikely
+    var generator = initRand(getTime().toUnix xor getTime().nanosecond)
+    for b in bytes.mitems: b = byte(generator.rand(255))
+  bytes[6] = (bytes[6] and 0x0f) or 0x40
+  bytes[8] = (bytes[8] and 0x3f) or 0x80
+  for i, b in bytes:
+    if i in [4, 6, 8, 10]: result.add('-')
+    result.add(toHex(b).toLowerAscii)
+
+proc parseTags*(text: string): seq[string] =
+  ## Parses comma-separated tags, eg. ``backend,clientX``.
+  for tag in text.split(','):
+    let tag = tag.strip
+    if tag.len > 0 and tag notin result: result.add(tag)
+
+proc parseState*(name: string): TimerState =
+  ## Parses a state from its human-readable name. Raises a ``ValueError`` if
+  ## there's no such state.
+  result = if name == tsNone.name: tsNone else: parseEnum[TimerState](name)
+
+proc time*(state: TimerState, config: Config): Duration =
+  ## Returns the amount of time a given state should take.
+  result =
+    case state
+    of tsNone, tsPomodoro: config.profile.pomodoroTime
+    of tsShortBreak: config.profile.shortBreakTime
+    of tsLongBreak: config.profile.longBreakTime
+
+proc next*(state: var TimerState, breakCounter: var int, breakCycle: int) =
+  ## Sets the next state according to the break counter, then increments the
+  ## break counter.
+  case state
+  of tsNone, tsShortBreak, tsLongBreak: state = tsPomodoro
+  of tsPomodoro:
+    if breakCounter < breakCycle - 1:
+      state = tsShortBreak
+    else:
+      state = tsLongBreak
+    breakCounter = (breakCounter + 1) mod breakCycle
+
+proc state*(kind: IntervalKind): TimerState =
+  ## Returns the timer state a scheduled interval runs in.
+  result =
+    case kind
+    of ikFocus: tsPomodoro
+    of ikBreak: tsShortBreak
+    of ikLongBreak: tsLongBreak
+
+proc countsUp*(timer: Timer): bool =
+  ## Returns whether the timer is counting up, as in a flowtime pomodoro or a
+  ## stopwatch.
+  result = (timer.config.profile.flowtime or timer.config.stopwatch) and
+           timer.state == tsPomodoro
+
+proc stateTime*(timer: Timer): Duration =
+  ## Returns the full length of the timer's current state. Flowtime pomodoros
+  ## take no time of their own, as they're counted up from zero.
+  let schedule = timer.config.profile.schedule
+  if timer.config.stopwatch:
+    result = DurationZero
+  elif timer.config.profile.flowtime:
+    result =
+      if timer.state in {tsShortBreak, tsLongBreak}: timer.earnedBreak
+      else: DurationZero
+  elif schedule.len > 0:
+    result = schedule[timer.scheduleIndex].duration
+  else:
+    result = timer.state.time(timer.config)
+
+proc intervalTime*(timer: Timer): Duration =
+  ## Returns the length of the current state, including any time it has been
+  ## extended by.
+  result = timer.stateTime + timer.extension
+
+proc preparing*(timer: Timer): bool =
+  ## Returns whether the timer is counting down to the pomodoro's beginning.
+  result = timer.getReady > DurationZero
+
+proc dayOf*(time: DateTime, dayStart: Duration): string =
+  ## Returns the day the time belongs to, as ``yyyy-MM-dd``. Days begin at the
+  ## given time of day, so that late nights count towards the day before.
+  result = (time - dayStart).format("yyyy-MM-dd")
+
+proc focusedToday*(timer: Timer): Duration =
+  ## Returns the length of the pomodoros completed today.
+  if timer.stats.day == now().dayOf(timer.config.dayStart):
+    result = timer.stats.focusedToday
+
+proc completedToday*(timer: Timer): int =
+  ## Returns the amount of pomodoros completed today.
+  if timer.stats.day == now().dayOf(timer.config.dayStart):
+    result = timer.stats.completedToday
+
+proc untilLongBreak*(timer: Timer): Option[Duration] =
+  ## Returns the time left until the next long break begins, or none if there
+  ## are no long breaks. During a long break, that's zero.
+  if timer.config.profile.flowtime: return none(Duration)
+  if timer.state == tsLongBreak: return some(DurationZero)
+  # the planned state takes no time of its own, it only precedes the first
+  # interval
+  let planned = timer.state == tsNone
+  var total = if planned: DurationZero else: timer.remainingTime
+  let schedule = timer.config.profile.schedule
+  if schedule.len > 0:
+    let first = if planned: 0 else: 1
+    for offset in first .. schedule.len:
+      let interval = schedule[(timer.scheduleIndex + offset) mod schedule.len]
+      if interval.kind == ikLongBreak: return some(total)
+      total += interval.duration
+    return none(Duration)
+  var
+    state = timer.state
+    breakCounter = timer.breakCounter
+  while true:
+    state.next(breakCounter, timer.config.profile.breakCycle)
+    if state == tsLongBreak: return some(total)
+    total += state.time(timer.config)
+
+proc initTimer*(config: Config): Timer =
+  ## Initializes a new timer.
+  result = Timer(config: config)
+  result.remainingTime = result.stateTime
+  result.lastPoll = getMonoTime()
+
+proc onStateChange*(timer: var Timer, callback: proc (newState: TimerState)) =
+  ## Sets the timer's state change callback.
+  timer.stateChangeProc = callback
+
+proc setConfig*(timer: var Timer, config: Config) =
+  ## Changes the timer's configuration. The current state keeps running with
+  ## the time it has left, the new configuration applies to subsequent states.
+  timer.config = config
+  if config.profile.schedule.len > 0:
+    timer.scheduleIndex = timer.scheduleIndex mod config.profile.schedule.len
+  if timer.stateStartTime.isNone:
+    timer.remainingTime = timer.stateTime
+
+proc setBreakCycle*(timer: var Timer, breakCycle: int) =
+  ## Changes the amount of short breaks before a long break. The position in
+  ## the cycle is kept, unless it's past the new cycle's end, in which case the
+  ## long break comes next.
+  timer.config.profile.breakCycle = breakCycle
+  timer.breakCounter = min(timer.breakCounter, breakCycle - 1)
+
+proc disabledToday*(timer: Timer): bool =
+  ## Returns whether pomodoros are turned off for the current day of the week.
+  result = timer.config.weekdays[now().weekday] == OffDay
+
+proc selectWeekdayProfile(timer: var Timer) =
+  ## Switches to the profile configured for the current day of the week, if
+  ## there is one.
+  let name = timer.config.weekdays[now().weekday]
+  if name.len > 0 and name != OffDay and name != timer.config.profileName:
+    var config = timer.config
+    config.selectProfile(name)
+    timer.setConfig(config)
+
+proc nextState*(timer: var Timer) =
+  ## Skips to the next state and sets the timer's remaining time accordingly.
+  ## If the profile has a custom schedule, it is followed instead of the
+  ## regular pomodoro cycle. In flowtime, pomodoros and breaks simply
+  ## alternate.
+  if timer.state != tsPomodoro:
+    # a new pomodoro is coming up, so this is the time to pick up the profile
+    # for the day
+    timer.selectWeekdayProfile()
+  let schedule = timer.config.profile.schedule
+  if timer.config.stopwatch:
+    timer.state = tsPomodoro
+  elif timer.config.profile.flowtime:
+    timer.state = if timer.state == tsPomodoro: tsShortBreak else: tsPomodoro
+  elif schedule.len > 0:
+    # the planned state precedes the first interval of the schedule
+    if timer.state != tsNone:
+      timer.scheduleIndex = (timer.scheduleIndex + 1) mod schedule.len
+    timer.state = schedule[timer.scheduleIndex].kind.state
+  else:
+    timer.state.next(timer.breakCounter, timer.config.profile.breakCycle)
+  timer.remainingTime = timer.stateTime
+  timer.extension = DurationZero
+  timer.snoozing = false
+  timer.getReady = DurationZero
+  timer.sessionStart = if timer.running: some(getTime()) else: none(Time)
+  if timer.state == tsPomodoro: timer.pomodoroInterruptions = 0
+
+proc upcomingState*(timer: Timer): TimerState =
+  ## Returns the state the timer is going to move on to next.
+  let schedule = timer.config.profile.schedule
+  if timer.config.profile.flowtime:
+    return if timer.state == tsPomodoro: tsShortBreak else: tsPomodoro
+  if schedule.len > 0:
+    let index =
+      if timer.state == tsNone: timer.scheduleIndex
+      else: (timer.scheduleIndex + 1) mod schedule.len
+    return schedule[index].kind.state
+  var breakCounter = timer.breakCounter
+  result = timer.state
+  result.next(breakCounter, timer.config.profile.breakCycle)
+
+proc limitReached*(timer: Timer): bool =
+  ## Returns whether enough pomodoros have been completed today to reach the
+  ## daily limit, unless it has been lifted for today.
+  let limit = timer.config.dailyLimit
+  result = limit > 0 and timer.completedToday >= limit and
+           timer.stats.overriddenOn != now().dayOf(timer.config.dayStart)
+
+proc startsPomodoro*(timer: Timer): bool =
+  ## Returns whether starting the timer would begin a new pomodoro.
+  result = not timer.running and
+           (if timer.stateStartTime.isNone: timer.upcomingState == tsPomodoro
+            else: timer.state == tsPomodoro and
+                  timer.remainingTime == timer.intervalTime)
+
+proc start*(timer: var Timer) =
+  ## Starts the timer. A timer that hasn't been started yet won't start on days
+  ## that have pomodoros turned off, and new pomodoros don't begin once the
+  ## daily limit is reached. A pomodoro that's yet to begin is preceded by the
+  ## time to get ready, if there is any.
+  if timer.limitReached and timer.startsPomodoro: return
+  if not timer.running:
+    if timer.stateStartTime.isNone:
+      if timer.disabledToday: return
+      timer.stateStartTime = some(getMonoTime())
+      timer.nextState()
+    if timer.state == tsPomodoro and
+       timer.remainingTime == timer.intervalTime:
+      timer.getReady = timer.config.prepareTime
+    timer.running = true
+    if timer.sessionStart.isNone: timer.sessionStart = some(getTime())
+
+proc pause*(timer: var Timer) =
+  ## Pauses the timer. It keeps the time it has left, and can be resumed with
+  ## ``start``. Getting ready is called off altogether.
+  timer.running = false
+  timer.getReady = DurationZero
+
+proc resetInterval*(timer: var Timer) =
+  ## Puts the current state's full length back on the clock, without moving on
+  ## in the cycle. The timer keeps running or stays paused.
+  timer.remainingTime = timer.stateTime
+  timer.extension = DurationZero
+  timer.overtime = false
+  timer.sessionStart = if timer.running: some(getTime()) else: none(Time)
+
+proc endSession(timer: var Timer, outcome: SessionOutcome) =
+  ## Remembers the state going on as ended, for the history. The pomodoros
+  ## snoozed breaks go back to are only extensions of the ones before them,
+  ## so they're left out.
+  if timer.state == tsNone or timer.snoozing: return
+  let now = getTime()
+  timer.stats.endedSessions.add(Session(id: newSessionId(),
+                                        state: timer.state, outcome: outcome,
+                                        start: timer.sessionStart.get(now),
+                                        stop: now, task: timer.label,
+                                        tags: timer.tags,
+                                        interruptions:
+                                          timer.pomodoroInterruptions))
+
+proc recordPomodoro(timer: var Timer, completed: bool) =
+  ## Remembers how the pomodoro going on has ended. Open-ended and snoozed
+  ## pomodoros don't have a length to compare against, so they're left out.
+  if timer.countsUp or timer.snoozing: return
+  let focused = max(timer.intervalTime - timer.remainingTime, DurationZero)
+  timer.stats.recentPomodoros.add(PomodoroOutcome(focused: focused,
+                                                  completed: completed,
+                                                  task: timer.label))
+  if timer.stats.recentPomodoros.len > KeptOutcomes:
+    timer.stats.recentPomodoros.delete(0)
+
+proc cutShort*(timer: var Timer) =
+  ## Records the pomodoro going on as cut short, eg. before it's reset.
+  if timer.state != tsPomodoro or timer.stateStartTime.isNone or
+     timer.snoozing or timer.countsUp: return
+  inc timer.stats.pomodorosCutShort
+  timer.recordPomodoro(completed = false)
+  timer.endSession(soCutShort)
+
+proc abandon*(timer: var Timer) =
+  ## Voids the pomodoro going on. It's neither completed nor skipped, so the
+  ## cycle doesn't move on; instead, a pomodoro starts over from scratch, and
+  ## waits to be started.
+  if not timer.snoozing and not timer.countsUp:
+    inc timer.stats.pomodorosAbandoned
+  timer.recordPomodoro(completed = false)
+  timer.endSession(soAbandoned)
+  timer.snoozing = false
+  timer.running = false
+  timer.resetInterval()
+  timer.getReady = DurationZero
+  timer.pomodoroInterruptions = 0
+
+proc reset*(timer: var Timer, config: Config) =
+  ## Starts over from scratch with the given config. The pomodoro going on is
+  ## cut short, and the counters, the task and its tags are kept.
+  timer.cutShort()
+  let (stats, label, tags) = (timer.stats, timer.label, timer.tags)
+  timer = initTimer(config)
+  timer.stats = stats
+  timer.label = label
+  timer.tags = tags
+
+proc complete(timer: var Timer) =
+  ## Counts the current state as completed.
+  timer.endSession(soCompleted)
+  if timer.state == tsPomodoro:
+    timer.recordPomodoro(completed = true)
+    # a snoozed break only makes the pomodoro before it longer
+    if not timer.snoozing: inc timer.stats.pomodorosCompleted
+    let today = now().dayOf(timer.config.dayStart)
+    if timer.stats.day != today:
+      timer.stats.day = today
+      timer.stats.focusedToday = DurationZero
+      timer.stats.completedToday = 0
+    if not timer.snoozing: inc timer.stats.completedToday
+    timer.stats.focusedToday +=
+      if timer.countsUp: -timer.remainingTime
+      else: timer.intervalTime
+  else: inc timer.stats.breaksCompleted
+
+proc advance(timer: var Timer) =
+  ## Moves on to the next state once the current one is over, pausing if the
+  ## next one shouldn't start by itself.
+  timer.nextState()
+  let autoStart =
+    if timer.state == tsPomodoro:
+      timer.config.autoStartPomodoros and not timer.disabledToday and
+      not timer.limitReached
+    else: timer.config.autoStartBreaks
+  if not autoStart:
+    # wait for the user to start the next state
+    timer.running = false
+
+proc acknowledge*(timer: var Timer) =
+  ## Ends overtime, recording how long it went on for, and moves on to the next
+  ## state.
+  if not timer.overtime: return
+  let overtime = -timer.remainingTime
+  if timer.state == tsPomodoro:
+    timer.stats.pomodoroOvertime += overtime
+  else:
+    timer.stats.breakOvertime += overtime
+    inc timer.stats.breaksOverrun
+  timer.overtime = false
+  timer.advance()
+
+proc endFlow*(timer: var Timer) =
+  ## Ends a flowtime pomodoro, counting it as completed, and moves on to a
+  ## break proportional to its length.
+  if not timer.countsUp: return
+  let focused = -timer.remainingTime
+  timer.complete()
+  timer.earnedBreak = initDuration(milliseconds =
+    int64(focused.inMilliseconds.float / timer.config.profile.flowtimeRatio))
+  timer.advance()
+
+proc skip*(timer: var Timer) =
+  ## Ends the current state right away and moves on to the next one. In
+  ## overtime, this acknowledges that the state is over, and in flowtime it
+  ## ends the pomodoro with the break it has earned.
+  if timer.overtime:
+    timer.acknowledge()
+    return
+  if timer.countsUp and timer.stateStartTime.isSome:
+    timer.endFlow()
+    return
+  if timer.stateStartTime.isNone:
+    timer.stateStartTime = some(getMonoTime())
+  timer.endSession(soSkipped)
+  case timer.state
+  of tsNone: discard
+  of tsPomodoro:
+    timer.recordPomodoro(completed = false)
+    if not timer.snoozing: inc timer.stats.pomodorosSkipped
+  of tsShortBreak, tsLongBreak: inc timer.stats.breaksSkipped
+  timer.nextState()
+
+proc jumpTo*(timer: var Timer, state: TimerState) =
+  ## Moves to the given state right away, as a manual correction. Nothing is
+  ## counted as completed or skipped, and the position in the cycle follows
+  ## along: a long break completes the cycle, and a short break after a
+  ## pomodoro moves on to the next one in the cycle, but never past the last.
+  ## With a custom schedule, the next entry of the state is moved to. The
+  ## timer keeps running or stays paused. Raises a ``ValueError`` if the timer
+  ## can't be in that state.
+  if state == tsNone:
+    raise newException(ValueError, "use reset to go back to planned")
+  if timer.config.profile.flowtime or timer.config.stopwatch:
+    raise newException(ValueError, "flowtime and the stopwatch only move on " &
+                       "by skipping")
+  let schedule = timer.config.profile.schedule
+  if schedule.len > 0:
+    block found:
+      for offset in 0 ..< schedule.len:
+        let index = (timer.scheduleIndex + offset) mod schedule.len
+        if schedule[index].kind.state == state:
+          timer.scheduleIndex = index
+          break found
+      raise newException(ValueError, "the schedule has no " & state.name)
+  else:
+    let breakCycle = timer.config.profile.breakCycle
+    case state
+    of tsNone, tsPomodoro: discard
+    of tsShortBreak:
+      if timer.state == tsPomodoro:
+        timer.breakCounter = min(timer.breakCounter + 1, breakCycle - 1)
+    of tsLongBreak: timer.breakCounter = 0
+  if timer.stateStartTime.isNone:
+    timer.stateStartTime = some(getMonoTime())
+  timer.state = state
+  timer.remainingTime = timer.stateTime
+  timer.extension = DurationZero
+  timer.overtime = false
+  timer.snoozing = false
+  timer.getReady = DurationZero
+  timer.sessionStart = if timer.running: some(getTime()) else: none(Time)
+
+proc canSnooze*(timer: Timer): bool =
+  ## Returns whether the current state is a break that has only just begun.
+  const SnoozeWindow = initDuration(minutes = 1)
+  result = timer.state in {tsShortBreak, tsLongBreak} and
+           not timer.config.profile.flowtime and
+           timer.intervalTime - timer.remainingTime < SnoozeWindow
+
+proc snooze*(timer: var Timer, duration: Duration) =
+  ## Postpones the break that has just begun, going back to the pomodoro for
+  ## the given time. Once that's over, the same break begins again.
+  if not timer.canSnooze: return
+  # undo the step nextState took in the cycle
+  let schedule = timer.config.profile.schedule
+  if schedule.len > 0:
+    timer.scheduleIndex =
+      (timer.scheduleIndex - 1 + schedule.len) mod schedule.len
+  else:
+    let breakCycle = timer.config.profile.breakCycle
+    timer.breakCounter = (timer.breakCounter - 1 + breakCycle) mod breakCycle
+  timer.state = tsPomodoro
+  timer.remainingTime = duration
+  # the snoozed part of the pomodoro is as long as the snooze
+  timer.extension = duration - timer.stateTime
+  timer.snoozing = true
+  inc timer.stats.breaksSnoozed
+
+proc lap*(timer: var Timer): Duration =
+  ## Marks a lap of the stopwatch. Returns the time the lap took.
+  let
+    total = -timer.remainingTime
+    previous = if timer.laps.len > 0: timer.laps[^1] else: DurationZero
+  timer.laps.add(total)
+  result = total - previous
+
+proc currentLap*(timer: Timer): Duration =
+  ## Returns the time since the stopwatch's last lap mark.
+  let previous = if timer.laps.len > 0: timer.laps[^1] else: DurationZero
+  result = -timer.remainingTime - previous
+
+proc extend*(timer: var Timer, duration: Duration) =
+  ## Adds time to the current state.
+  timer.remainingTime += duration
+  timer.extension += duration
+
+proc recordInterruption*(timer: var Timer, kind: InterruptionKind,
+                         note = "") =
+  ## Records an interruption against the pomodoro going on, which keeps
+  ## running.
+  timer.stats.interruptions.add(Interruption(kind: kind, note: note,
+                                             time: getTime()))
+  inc timer.pomodoroInterruptions
+
+proc setRemaining*(timer: var Timer, duration: Duration) =
+  ## Puts the given time on the clock. The current state is made longer if it
+  ## wouldn't fit otherwise.
+  timer.remainingTime = duration
+  timer.extension = max(timer.extension, duration - timer.stateTime)
+
+proc toggle*(timer: var Timer) =
+  ## Toggles the timer. In overtime, this acknowledges that the state is over.
+  if timer.overtime: timer.acknowledge()
+  elif not timer.running: timer.start()
+  else: timer.pause()
+
+proc poll*(timer: var Timer) =
+  ## Polls the timer and updates its state. This must be called monotonically,
+  ## but the interval between calls doesn't matter.
+  let now = getMonoTime()
+  if timer.running and timer.preparing:
+    # the pomodoro begins once the countdown is over, and not before
+    timer.getReady = max(timer.getReady - (now - timer.lastPoll), DurationZero)
+  elif timer.running:
+    timer.remainingTime -= now - timer.lastPoll
+    if timer.remainingTime <= DurationZero and not timer.overtime and
+       not timer.countsUp:
+      timer.complete()
+      if timer.config.overtime:
+        # keep counting, into the negatives, until the user acknowledges it
+        timer.overtime = true
+        if timer.stateChangeProc != nil:
+          timer.stateChangeProc(timer.upcomingState)
+      else:
+        # time's up. carry the overshoot over to the next state so that long
+        # poll intervals don't make the timer drift, unless it's so large that
+        # the next state would be over already (eg. after suspending the
+        # computer)
+        let overshoot = -timer.remainingTime
+        timer.advance()
+        if timer.running and overshoot < timer.remainingTime:
+          timer.remainingTime -= overshoot
+        if timer.stateChangeProc != nil:
+          timer.stateChangeProc(timer.state)
+  timer.lastPoll = now
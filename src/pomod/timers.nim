@@ -0,0 +1,69 @@
+## Named timers, which run alongside the main one, eg. a one-shot countdown for
+## the laundry next to the pomodoro cycle.
+
+import std/options
+import std/strutils
+import std/times
+
+import config
+import output
+import timer
+
+type
+  NamedTimer* = object ## a timer addressed by its name
+    name*: string
+    timer*: Timer
+    oneShot*: bool            ## whether it's over after a single countdown
+
+proc countdownConfig*(config: Config, length: Duration): Config =
+  ## Returns the configuration for counting down once for the given length.
+  ## Anything that would keep the countdown from ending by itself is turned
+  ## off.
+  result = config
+  result.profile = defaultProfile()
+  result.profile.schedule = @[Interval(kind: ikFocus, duration: length)]
+  # neither days off nor the day's profile apply to a countdown
+  result.weekdays = default(array[WeekDay, string])
+  result.stopwatch = false
+  result.strict = false
+  result.overtime = false
+  result.prepareTime = DurationZero
+  result.dailyLimit = 0
+
+proc initNamedTimer*(name: string, config: Config,
+                     length = none(Duration)): NamedTimer =
+  ## Starts a named timer. With a length, it counts down once; otherwise it
+  ## runs a pomodoro cycle of its own.
+  result = NamedTimer(name: name, oneShot: length.isSome)
+  var config = config
+  config.stopwatch = false
+  config.dailyLimit = 0
+  if length.isSome:
+    config = config.countdownConfig(length.get)
+  result.timer = initTimer(config)
+  result.timer.label = name
+  result.timer.start()
+
+proc find*(timers: seq[NamedTimer], name: string): int =
+  ## Returns the index of the timer with the given name, or -1 if there's no
+  ## such timer.
+  for i, named in timers:
+    if named.name == name: return i
+  result = -1
+
+proc isOver*(named: NamedTimer): bool =
+  ## Returns whether a one-shot timer has counted down.
+  result = named.oneShot and named.timer.stats.pomodorosCompleted > 0
+
+proc summary*(named: NamedTimer): string =
+  ## Describes the timer briefly, eg. ``laundry 12:03``.
+  result = named.name & " " & named.timer.renderTemplate("{remaining}")
+  if named.timer.state != tsNone and not named.timer.running:
+    result.add(" (paused)")
+
+proc combined*(timers: seq[NamedTimer]): string =
+  ## Describes all the timers on a single line.
+  var summaries: seq[string]
+  for named in timers:
+    summaries.add(named.summary)
+  result = summaries.join(", ")
@@ -0,0 +1,39 @@
+## Integration with tmux: a status segment for polling with ``#(pomod tmux)``,
+## and refreshing the status line of every client when the timer's state
+## changes, so the segment doesn't lag behind until the next poll.
+
+import std/osproc
+import std/strutils
+
+import client
+import config
+import output
+
+proc runTmux*(): int =
+  ## Prints the status as a colored tmux segment. Prints nothing if pomod isn't
+  ## running, so the status line simply leaves the segment out. Returns the
+  ## exit code.
+  let config = loadConfigOrDefault()
+  try:
+    let
+      status = queryStatus()
+      color = status.state.stateColor(status.running, config.colors)
+      icon = status.state.stateIcon(config.icons)
+    echo "#[fg=" & color & "]" & icon & " " & status.remaining.clockTime &
+         "#[default]"
+  except OSError, ValueError:
+    discard
+  result = QuitSuccess
+
+proc refreshTmux*() =
+  ## Refreshes the status line of every attached tmux client.
+  try:
+    let (clients, code) =
+      execCmdEx("tmux list-clients -F '#{client_name}'", options = {poUsePath})
+    if code != 0: return
+    for name in clients.splitLines:
+      if name.len > 0:
+        discard execCmdEx("tmux refresh-client -S -t " & quoteShell(name),
+                          options = {poUsePath})
+  except OSError:
+    discard
@@ -0,0 +1,32 @@
+## A plain-text work log, which every completed session is appended to as a
+## line of its own, eg. ``2024-05-02 14:00–14:25 pomodoro "write report"
+## #client``.
+
+import std/strutils
+import std/times
+
+import timer
+
+proc isMarkdown(path: string): bool =
+  result = path.toLowerAscii.endsWith(".md") or
+           path.toLowerAscii.endsWith(".markdown")
+
+proc logLine*(session: Session, markdown = false): string =
+  ## Describes the session on a single line. In Markdown, it's a list item.
+  let start = session.start.local
+  result = start.format("yyyy-MM-dd HH:mm") & "–" &
+           session.stop.local.format("HH:mm") & " " & session.state.name
+  if session.task.len > 0:
+    result.add(" \"" & session.task.replace("\"", "\\\"") & "\"")
+  for tag in session.tags:
+    result.add(" #" & tag)
+  if markdown:
+    result = "- " & result
+
+proc appendToLog*(path: string, session: Session) =
+  ## Appends the session to the work log, if it was completed. Raises an
+  ## ``IOError`` if the log cannot be written to.
+  if session.outcome != soCompleted: return
+  let log = open(path, fmAppend)
+  defer: log.close()
+  log.writeLine(session.logLine(markdown = path.isMarkdown))
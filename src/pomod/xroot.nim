@@ -0,0 +1,82 @@
+## Setting the name of the X root window, which is where dwm and similar window
+## managers take their status text from.
+##
+## Only a few Xlib functions are needed, so they're bound here directly rather
+## than pulling in a whole binding package.
+
+import std/strutils
+
+# Xlib bindings
+
+const libx11 = "libX11.so(|.6)"
+
+type
+  Display = pointer
+  Window = culong
+
+{.push importc, dynlib: libx11, cdecl.}
+proc XOpenDisplay(name: cstring): Display
+proc XCloseDisplay(display: Display): cint
+proc XDefaultRootWindow(display: Display): Window
+proc XStoreName(display: Display, window: Window, name: cstring): cint
+proc XFetchName(display: Display, window: Window, name: ptr cstring): cint
+proc XFree(data: pointer): cint
+proc XFlush(display: Display): cint
+{.pop.}
+
+# the root window
+
+type
+  RootWindow* = object ## a connection to the X server for setting the name
+    display: Display
+    append: bool              ## whether to keep the name that was already set
+    separator: string
+    lastStatus: string        ## the status that was last appended
+
+proc openRootWindow*(append = false, separator = " | "): RootWindow =
+  ## Connects to the X server given by ``$DISPLAY``. Raises an ``IOError`` if
+  ## that's not possible.
+  result.display = XOpenDisplay(nil)
+  if result.display == nil:
+    raise newException(IOError, "cannot open the X display")
+  result.append = append
+  result.separator = separator
+
+proc isOpen*(root: RootWindow): bool =
+  result = root.display != nil
+
+proc currentName(root: RootWindow): string =
+  var name: cstring
+  if XFetchName(root.display, XDefaultRootWindow(root.display),
+                addr name) != 0 and name != nil:
+    result = $name
+    discard XFree(name)
+
+proc setStatus*(root: var RootWindow, status: string) =
+  ## Sets the root window's name to the status. When appending, the status is
+  ## added after whatever else has set the name, replacing the status that was
+  ## appended before.
+  if not root.isOpen: return
+  var name = status
+  if root.append:
+    var existing = root.currentName
+    let previous = root.separator & root.lastStatus
+    if root.lastStatus.len > 0 and existing.endsWith(previous):
+      existing.setLen(existing.len - previous.len)
+    elif existing == root.lastStatus:
+      existing = ""
+    if existing.len > 0 and status.len > 0:
+      name = existing & root.separator & status
+    elif existing.len > 0:
+      name = existing
+  discard XStoreName(root.display, XDefaultRootWindow(root.display),
+                     name.cstring)
+  discard XFlush(root.display)
+  root.lastStatus = status
+
+proc close*(root: var RootWindow) =
+  ## Takes the status back off the root window's name and disconnects.
+  if not root.isOpen: return
+  root.setStatus("")
+  discard XCloseDisplay(root.display)
+  root.display = nil
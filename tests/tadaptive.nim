@@ -0,0 +1,52 @@
+import std/times
+import std/unittest
+
+import pomod/adaptive
+import pomod/config
+import pomod/timer
+
+proc minutes(count: int): Duration = initDuration(minutes = count)
+
+proc completed(): PomodoroOutcome =
+  result = PomodoroOutcome(focused: minutes(25), completed: true)
+
+proc cutShort(after: int): PomodoroOutcome =
+  result = PomodoroOutcome(focused: minutes(after), completed: false)
+
+suite "adaptive nudging":
+  let adaptive = defaultConfig().adaptive
+
+  test "no history leaves the length alone":
+    let recent = newSeq[PomodoroOutcome]()
+    check nudgedLength(minutes(25), recent, adaptive) == minutes(25)
+
+  test "lengthens when every pomodoro is seen through":
+    let recent = [completed(), completed(), completed(), completed()]
+    check nudgedLength(minutes(25), recent, adaptive) == minutes(30)
+
+  test "shortens to where pomodoros were typically cut short":
+    let recent = [cutShort(16), cutShort(17), cutShort(18), completed()]
+    check nudgedLength(minutes(25), recent, adaptive) == minutes(17)
+
+  test "shortens by at least a step":
+    let recent = [cutShort(23), cutShort(24), cutShort(24), completed()]
+    check nudgedLength(minutes(25), recent, adaptive) == minutes(20)
+
+  test "a mixed record leaves the length alone":
+    let recent = [cutShort(10), completed(), completed(), completed()]
+    check nudgedLength(minutes(25), recent, adaptive) == minutes(25)
+
+  test "stays within bounds":
+    let longer = [completed(), completed()]
+    check nudgedLength(minutes(50), longer, adaptive) == adaptive.maxPomodoro
+    let shorter = [cutShort(3), cutShort(4)]
+    check nudgedLength(minutes(20), shorter, adaptive) == adaptive.minPomodoro
+
+  test "breaks keep their proportion to the pomodoro":
+    var timer = initTimer(defaultConfig())
+    for _ in 1..4:
+      timer.stats.recentPomodoros.add(completed())
+    timer.adapt(adaptive)
+    check timer.config.profile.pomodoroTime == minutes(30)
+    check timer.config.profile.shortBreakTime == minutes(6)
+    check timer.config.profile.longBreakTime == minutes(36)
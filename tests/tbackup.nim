@@ -0,0 +1,85 @@
+import std/json
+import std/os
+import std/osproc
+import std/times
+import std/unittest
+
+import pomod/backup
+import pomod/history
+import pomod/paths
+import pomod/timer
+
+let root = getTempDir()/"pomod-test-backup"
+putEnv("XDG_CONFIG_HOME", root/"config")
+putEnv("XDG_DATA_HOME", root/"data")
+putEnv("XDG_RUNTIME_DIR", root/"runtime")
+
+proc archive(files: openArray[(string, string)]): string =
+  ## Makes a tarball of the given files, the way pomod lays them out.
+  let dir = root/"made"
+  createDir(dir)
+  var args = @["-czf", root/"made.tar.gz", "-C", dir]
+  for (name, content) in files:
+    writeFile(dir/name, content)
+    args.add(name)
+  let process = startProcess("tar", args = args, options = {poUsePath})
+  doAssert process.waitForExit == 0
+  process.close()
+  result = root/"made.tar.gz"
+
+suite "backups":
+  setup:
+    removeDir(root)
+    ensureDirs()
+
+  teardown:
+    removeDir(root)
+
+  test "the data survives a backup and a restore":
+    writeFile(configFile(), "pomodoro_time = 30\n")
+    writeFile(stateFile(), """{"state": "pomodoro"}""")
+    var history = openHistory()
+    history.record(Session(state: tsPomodoro, outcome: soCompleted,
+                           start: fromUnix(1714658400),
+                           stop: fromUnix(1714659900)))
+    history.close()
+    backup(root/"backup.tar.gz")
+    removeFile(configFile())
+    removeFile(stateFile())
+    removeFile(historyFile())
+    writeFile(journalFile(), "")
+    check restore(root/"backup.tar.gz") ==
+      @["config.toml", "state.json", "history.db"]
+    check readFile(configFile()) == "pomodoro_time = 30\n"
+    check readFile(stateFile()) == """{"state": "pomodoro"}"""
+    check not fileExists(journalFile())
+    history = openHistory()
+    check history.sessions.len == 1
+    history.close()
+
+  test "archives that aren't backups are refused":
+    let path = archive([("notes.txt", "hello")])
+    expect BackupError:
+      discard restore(path)
+
+  test "backups made by a newer pomod are refused":
+    let path = archive([
+      ("manifest.json", $(%*{"schema": 2, "files": ["state.json"]})),
+      ("state.json", "{}"),
+    ])
+    writeFile(stateFile(), "{}\n")
+    expect BackupError:
+      discard restore(path)
+    check readFile(stateFile()) == "{}\n"
+
+  test "a broken file keeps anything from being restored":
+    let path = archive([
+      ("manifest.json",
+       $(%*{"schema": 1, "files": ["config.toml", "state.json"]})),
+      ("config.toml", "pomodoro_time = 30\n"),
+      ("state.json", """{"state": """),
+    ])
+    writeFile(configFile(), "")
+    expect BackupError:
+      discard restore(path)
+    check readFile(configFile()) == ""
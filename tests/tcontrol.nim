@@ -0,0 +1,68 @@
+import std/unittest
+
+# the clients' tokens are set on private fields
+include pomod/control
+
+proc reply(line: string): string = "did " & line
+
+suite "the control socket":
+  let path = getTempDir()/"pomod-test-control.sock"
+
+  setup:
+    var server = openControlServer(path)
+    let client = newSocket(AF_UNIX, SOCK_STREAM, IPPROTO_IP, buffered = false)
+    client.connectUnix(path)
+
+  teardown:
+    client.close()
+    server.close()
+
+  test "every line is answered":
+    client.send("status\n\n  pause  \n")
+    server.poll(reply)
+    check client.recvLine(timeout = 1000) == "did status"
+    check client.recvLine(timeout = 1000) == "did pause"
+
+  test "lines are answered once they're complete":
+    client.send("sta")
+    server.poll(reply)
+    check server.clients.len == 1
+    check server.clients[0].buffer == "sta"
+    client.send("tus\n")
+    server.poll(reply)
+    check client.recvLine(timeout = 1000) == "did status"
+
+  test "clients sending overlong lines are dropped":
+    client.send(repeat('x', MaxLineLength + 1))
+    for _ in 1..10:
+      server.poll(reply)
+    check server.clients.len == 0
+
+  test "a token has to be presented first":
+    server.token = "secret"
+    client.send("auth secret\nstatus\n")
+    server.poll(reply)
+    check client.recvLine(timeout = 1000) == "ok"
+    check client.recvLine(timeout = 1000) == "did status"
+
+  test "clients presenting the wrong token are dropped":
+    server.token = "secret"
+    client.send("status\n")
+    server.poll(reply)
+    check client.recvLine(timeout = 1000) == "error: invalid token"
+    check server.clients.len == 0
+
+suite "addresses and tokens":
+  test "host:port addresses":
+    check parseAddress("localhost:7879") == ("localhost", Port(7879))
+    check parseAddress("0.0.0.0:80") == ("0.0.0.0", Port(80))
+    for text in ["localhost", "localhost:http", "localhost:0", ":70000"]:
+      expect ValueError:
+        discard parseAddress(text)
+
+  test "tokens are compared in full":
+    check secureEquals("secret", "secret")
+    check secureEquals("", "")
+    check not secureEquals("secret", "secreT")
+    check not secureEquals("secret", "secret ")
+    check not secureEquals("secret", "")
@@ -0,0 +1,80 @@
+import std/os
+import std/times
+import std/unittest
+
+import db_connector/db_sqlite
+
+import pomod/history
+import pomod/timer
+
+proc session(id: string, start: int64, state = tsPomodoro,
+             outcome = soCompleted): Session =
+  result = Session(id: id, state: state, outcome: outcome,
+                   start: fromUnix(start), stop: fromUnix(start + 25 * 60),
+                   task: "write report", tags: @["client"])
+
+suite "the history":
+  let path = getTempDir()/"pomod-test-history.db"
+
+  setup:
+    var history = openHistory(path)
+
+  teardown:
+    history.close()
+    removeFile(path)
+
+  test "sessions are read back oldest first":
+    let
+      later = session("b9a4c6d2-0000-4000-8000-000000000002", 1714660000)
+      earlier = session("b9a4c6d2-0000-4000-8000-000000000001", 1714658400,
+                        tsShortBreak, soSkipped)
+    history.record(later)
+    history.record(earlier)
+    check history.sessions == @[earlier, later]
+    check history.sessions(since = fromUnix(1714659000)) == @[later]
+    check history.sessions(until = fromUnix(1714659000)) == @[earlier]
+
+  test "sessions without an ID are given one":
+    history.record(session("", 1714658400))
+    let recorded = history.sessions
+    check recorded.len == 1
+    check recorded[0].id.len == 36
+
+  test "sessions are told apart by their ID":
+    let recorded = session("b9a4c6d2-0000-4000-8000-000000000001", 1714658400)
+    history.record(recorded)
+    check recorded in history
+    check session("b9a4c6d2-0000-4000-8000-000000000002",
+                  1714658400) notin history
+    expect DbError:
+      history.record(recorded)
+
+  test "sessions without an ID are told apart by their start":
+    history.record(session("b9a4c6d2-0000-4000-8000-000000000001",
+                           1714658400))
+    check session("", 1714658400) in history
+    check session("", 1714658400, tsShortBreak) notin history
+    check session("", 1714658402) notin history
+
+  test "histories made by a newer pomod are refused":
+    history.close()
+    let db = open(path, "", "", "")
+    db.exec(sql"pragma user_version = 2")
+    db.close()
+    check schemaOf(path) == 2
+    expect DbError:
+      history = openHistory(path)
+    history = History()
+
+  test "a closed history records nothing":
+    let closed = History()
+    check not closed.isOpen
+    closed.record(session("", 1714658400))
+    check closed.sessions.len == 0
+
+  test "sessions can be picked by tag":
+    var other = session("", 1714660000)
+    other.tags = @["writing"]
+    let sessions = [session("", 1714658400), other]
+    check sessions.tagged("writing") == @[other]
+    check sessions.tagged("nothing").len == 0
@@ -0,0 +1,62 @@
+import std/os
+import std/times
+import std/unittest
+
+import pomod/history
+import pomod/historysync
+import pomod/timer
+
+proc session(id: string, start: int64): Session =
+  result = Session(id: id, state: tsPomodoro, outcome: soCompleted,
+                   start: fromUnix(start), stop: fromUnix(start + 25 * 60))
+
+suite "syncing the history":
+  let root = getTempDir()/"pomod-test-sync"
+
+  setup:
+    removeDir(root)
+    createDir(root)
+    let dir = root/"sync"
+    var
+      laptop = openHistory(root/"laptop.db")
+      desktop = openHistory(root/"desktop.db")
+    laptop.record(session("b9a4c6d2-0000-4000-8000-000000000001", 1714658400))
+    desktop.record(session("b9a4c6d2-0000-4000-8000-000000000002",
+                           1714660000))
+
+  teardown:
+    laptop.close()
+    desktop.close()
+    removeDir(root)
+
+  test "each machine gets the other's sessions":
+    let first = laptop.sync(dir, "laptop")
+    check first.machines == 0
+    check first.merged == 0
+    check fileExists(dir/"laptop.json")
+    let second = desktop.sync(dir, "desktop")
+    check second.machines == 1
+    check second.merged == 1
+    check desktop.sessions.len == 2
+    let third = laptop.sync(dir, "laptop")
+    check third.merged == 1
+    check laptop.sessions == desktop.sessions
+
+  test "sessions already in the history aren't merged again":
+    laptop.publish(dir, "laptop")
+    check desktop.merge(dir, "desktop").merged == 1
+    check desktop.merge(dir, "desktop").merged == 0
+    check desktop.sessions.len == 2
+
+  test "the machine's own file is left out":
+    laptop.publish(dir, "laptop")
+    check laptop.merge(dir, "laptop").machines == 0
+
+  test "broken files are reported, and the rest merged":
+    laptop.publish(dir, "laptop")
+    writeFile(dir/"phone.json", "{broken")
+    writeFile(dir/"notes.txt", "not a history")
+    let synced = desktop.merge(dir, "desktop")
+    check synced.machines == 1
+    check synced.merged == 1
+    check synced.errors.len == 1
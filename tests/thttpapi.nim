@@ -0,0 +1,147 @@
+import std/unittest
+
+# the server is bound to a port of the system's choosing, on a private field
+include pomod/httpapi
+
+proc listen(): (HttpServer, Port) =
+  var server = HttpServer(socket: newSocket(buffered = false))
+  server.socket.bindAddr(Port(0), "127.0.0.1")
+  server.socket.listen()
+  result = (server, server.socket.getLocalAddr[1])
+
+proc echoPath(request: HttpRequest): HttpResponse =
+  result = jsonResponse(200, %*{"verb": request.verb, "path": request.path,
+                                "agent": request.header("user-agent")})
+
+proc connect(port: Port): Socket =
+  result = newSocket(buffered = false)
+  result.connect("127.0.0.1", port)
+
+proc response(client: Socket): string =
+  ## Reads the response, up to the server closing the connection.
+  result = client.recv(MaxRequestSize, timeout = 1000)
+
+suite "requests":
+  test "the request line and headers":
+    let request = parseRequest("POST /start?x=1 HTTP/1.1\r\n" &
+                               "Host: localhost\r\nUser-Agent:  curl \r\n")
+    check request.verb == "POST"
+    check request.path == "/start?x=1"
+    check request.header("host") == "localhost"
+    check request.header("user-agent") == "curl"
+    check request.header("accept") == ""
+
+suite "the HTTP server":
+  setup:
+    var (server, port) = listen()
+    let client = connect(port)
+
+  teardown:
+    client.close()
+    server.close()
+
+  test "requests are handed to the handler":
+    client.send("GET /status HTTP/1.1\r\nUser-Agent: curl\r\n\r\n")
+    server.poll(echoPath)
+    let reply = client.response
+    check reply.startsWith("HTTP/1.1 200 OK\r\n")
+    check "Content-Type: application/json\r\n" in reply
+    check reply.endsWith(
+      "\r\n\r\n" & $(%*{"verb": "GET", "path": "/status", "agent": "curl"}))
+    check server.connections.len == 0
+
+  test "errors describe what went wrong":
+    let error = errorResponse(404, "no such endpoint: /nope")
+    check error.code == 404
+    check error.body == """{"error":"no such endpoint: /nope"}"""
+
+  test "requests are answered once their headers are complete":
+    client.send("GET /status HTTP/1.1\r\n")
+    server.poll(echoPath)
+    check server.connections.len == 1
+    client.send("\r\n")
+    server.poll(echoPath)
+    check client.response.startsWith("HTTP/1.1 200 OK\r\n")
+
+  test "malformed requests are a bad request":
+    client.send("\r\n\r\n")
+    server.poll(echoPath)
+    check client.response.startsWith("HTTP/1.1 400 Bad Request\r\n")
+
+  test "oversized requests are dropped":
+    client.send("GET /" & repeat('x', MaxRequestSize))
+    for _ in 1..10:
+      server.poll(echoPath)
+    check server.connections.len == 0
+
+  test "requests that don't arrive in time are dropped":
+    client.send("GET /status HTTP/1.1\r\n")
+    server.poll(echoPath)
+    check server.connections.len == 1
+    server.connections[0].deadline = getMonoTime() - RequestTimeout
+    server.poll(echoPath)
+    check server.connections.len == 0
+    check client.response == ""
+
+suite "WebSockets":
+  setup:
+    var (server, port) = listen()
+
+  teardown:
+    server.close()
+
+  test "frames carry their length":
+    check textFrame("hi") == "\x81\x02hi"
+    check textFrame(repeat('x', 125))[0..1] == "\x81\x7d"
+    check textFrame(repeat('x', 126))[0..3] == "\x81\x7e\x00\x7e"
+    check textFrame(repeat('x', 70000))[0..9] ==
+      "\x81\x7f\x00\x00\x00\x00\x00\x01\x11\x70"
+
+  test "the opening handshake, as in RFC 6455":
+    let client = connect(port)
+    defer: client.close()
+    client.send("GET /events HTTP/1.1\r\nHost: localhost\r\n" &
+                "Upgrade: websocket\r\nConnection: Upgrade\r\n" &
+                "Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n" &
+                "Sec-WebSocket-Version: 13\r\n\r\n")
+    server.poll(echoPath)
+    let expected = "HTTP/1.1 101 Switching Protocols\r\n" &
+                   "Upgrade: websocket\r\nConnection: Upgrade\r\n" &
+                   "Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n" &
+                   "\r\n"
+    check client.recv(expected.len, timeout = 1000) == expected
+    check server.connections.len == 0
+    check server.websockets.len == 1
+    server.broadcast("hi")
+    check client.recv(4, timeout = 1000) == "\x81\x02hi"
+
+  test "without an upgrade, /events is an ordinary request":
+    let client = connect(port)
+    defer: client.close()
+    client.send("GET /events HTTP/1.1\r\n\r\n")
+    server.poll(echoPath)
+    check client.response.startsWith("HTTP/1.1 200 OK\r\n")
+    check server.websockets.len == 0
+
+  test "WebSockets that have gone away are dropped":
+    let client = connect(port)
+    client.send("GET /events HTTP/1.1\r\nUpgrade: websocket\r\n" &
+                "Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\r\n")
+    server.poll(echoPath)
+    check server.websockets.len == 1
+    client.close()
+    server.poll(echoPath)
+    check server.websockets.len == 0
+
+suite "connection limits":
+  test "connections past the limit are turned away":
+    var (server, port) = listen()
+    defer: server.close()
+    var clients: seq[Socket]
+    for _ in 0 .. MaxConnections:
+      clients.add(connect(port))
+    server.poll(echoPath)
+    check server.connections.len == MaxConnections
+    check clients[^1].response == ""
+    for client in clients:
+      client.close()
@@ -0,0 +1,59 @@
+import std/times
+import std/unittest
+
+import pomod/exchange
+import pomod/timer
+
+suite "importing other apps' CSV":
+  test "rows are completed pomodoros":
+    let sessions = importHistory("""Start,End,Description
+2024-05-02 14:00,2024-05-02 14:25,write report
+2024-05-02 14:30:00,2024-05-02 14:55:00,"review, then merge"
+""")
+    check sessions.len == 2
+    check sessions[0].state == tsPomodoro
+    check sessions[0].outcome == soCompleted
+    check sessions[0].task == "write report"
+    check sessions[0].stop - sessions[0].start == initDuration(minutes = 25)
+    check sessions[1].task == "review, then merge"
+
+  test "timestamps in ISO 8601 or seconds since the epoch":
+    let sessions = importHistory("""begin,stop
+2024-05-02T14:00:00Z,2024-05-02T14:25:00Z
+1714658400,1714659900
+""")
+    check sessions.len == 2
+    check sessions[0].start == sessions[1].start
+    check sessions[1].stop - sessions[1].start == initDuration(minutes = 25)
+
+  test "other apps' IDs are left out":
+    let sessions = importHistory("""id,start,end
+7,2024-05-02 14:00,2024-05-02 14:25
+""")
+    check sessions[0].id == ""
+
+  test "the times are required":
+    expect ValueError:
+      discard importHistory("task,duration\nwrite report,25\n")
+    expect ValueError:
+      discard importHistory("start,end\nyesterday,today\n")
+
+suite "pomod's own exports":
+  let sessions = @[
+    Session(id: "b9a4c6d2-0000-4000-8000-000000000001", state: tsPomodoro,
+            outcome: soCompleted, start: fromUnix(1714658400),
+            stop: fromUnix(1714659900), task: "write report",
+            interruptions: 1, tags: @["client", "writing"]),
+    Session(id: "b9a4c6d2-0000-4000-8000-000000000002", state: tsShortBreak,
+            outcome: soSkipped, start: fromUnix(1714659900),
+            stop: fromUnix(1714660000)),
+  ]
+
+  for format in ExchangeFormat:
+    test "sessions survive a round trip through " & $format:
+      let imported = importHistory(exportHistory(sessions, format))
+      check imported == sessions
+
+  test "newer schemas are refused":
+    expect ValueError:
+      discard importHistory("""{"schema": 2, "sessions": []}""")
@@ -0,0 +1,50 @@
+import std/os
+import std/posix
+import std/strutils
+import std/unittest
+
+import pomod/instance
+
+proc openFiles(): int =
+  for _ in walkDir("/proc/self/fd"):
+    inc result
+
+suite "the instance lock":
+  let path = getTempDir()/"pomod-test.pid"
+
+  teardown:
+    removeFile(path)
+
+  test "the pidfile holds the instance's pid":
+    var lock = lockInstance(path)
+    defer: lock.release()
+    check readFile(path).strip == $getpid()
+    check not lock.tookOver
+
+  test "only one instance holds the lock":
+    var lock = lockInstance(path)
+    defer: lock.release()
+    let before = openFiles()
+    try:
+      discard lockInstance(path)
+      fail()
+    except AlreadyRunningError as e:
+      check e.pid == getpid().int
+    check openFiles() == before
+
+  test "released locks can be taken":
+    var lock = lockInstance(path)
+    lock.release()
+    var again = lockInstance(path)
+    again.release()
+
+  test "the pid can be left alone":
+    writeFile(path, "1234\n")
+    var lock = lockInstance(path, writePid = false)
+    defer: lock.release()
+    check readFile(path) == "1234\n"
+
+  test "releasing a lock that isn't held closes nothing":
+    var lock = initInstanceLock()
+    lock.release()
+    check fcntl(0, F_GETFD) != -1
@@ -0,0 +1,58 @@
+import std/json
+import std/os
+import std/strutils
+import std/unittest
+
+import pomod/config
+import pomod/journal
+import pomod/timer
+
+suite "the journal":
+  let path = getTempDir()/"pomod-test-journal.jsonl"
+
+  setup:
+    var timer = initTimer(defaultConfig())
+    var journal = openJournal(path)
+    journal.write(jeStarted, timer)
+    timer.start()
+    journal.write(jeChanged, timer)
+    timer.skip()
+    journal.write(jeChanged, timer)
+
+  teardown:
+    removeFile(path)
+
+  test "the last entry tells what the timer was doing":
+    journal.close()
+    let entry = lastEntry(path)
+    check entry["event"].getStr == "changed"
+    check entry.crashed
+    var recovered = initTimer(defaultConfig())
+    recovered.restore(entry)
+    check recovered.state == tsShortBreak
+    check recovered.running
+    check recovered.stats.day == timer.stats.day
+
+  test "a clean shutdown isn't a crash":
+    journal.write(jeShutdown, timer)
+    journal.close()
+    check not lastEntry(path).crashed
+
+  test "an entry cut off by a crash is ignored":
+    journal.close()
+    let file = open(path, fmAppend)
+    file.write("""{"event": "changed", "state": "pomo""")
+    file.close()
+    check lastEntry(path)["state"].getStr == "short break"
+
+  test "a missing journal has no entries":
+    journal.close()
+    check lastEntry(path & ".missing").isNil
+    check not lastEntry(path & ".missing").crashed
+
+  test "the journal begins anew when opened":
+    journal.close()
+    journal = openJournal(path)
+    journal.write(jeStarted, initTimer(defaultConfig()))
+    journal.close()
+    check readFile(path).count('\n') == 1
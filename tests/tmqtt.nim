@@ -0,0 +1,43 @@
+import std/unittest
+
+# the packets are encoded and decoded by private procs
+include pomod/mqtt
+
+suite "MQTT packets":
+  let
+    body = encodeString("pomod/command") & "start"
+    data = packet(Publish, body)
+
+  test "lengths take up as many bytes as they need":
+    check encodeLength(0) == "\x00"
+    check encodeLength(127) == "\x7f"
+    check encodeLength(128) == "\x80\x01"
+    check encodeLength(16_383) == "\xff\x7f"
+    check encodeLength(2_097_152) == "\x80\x80\x80\x01"
+
+  test "strings are prefixed with their length":
+    check encodeString("MQTT") == "\x00\x04MQTT"
+    check encodeString("") == "\x00\x00"
+
+  test "packets decode to what they were encoded from":
+    check data[0].int == Publish
+    check decodePacket(data) == (Publish, body, data.len)
+    let long = repeat('x', 300)
+    check decodePacket(packet(Publish, long)) == (Publish, long, 303)
+
+  test "the flags are left out of the type":
+    check decodePacket(packet(Publish or 0x01, "x"))[0] == Publish
+
+  test "incomplete packets wait for the rest":
+    for i in 0 ..< data.len:
+      check decodePacket(data[0 ..< i])[2] == 0
+
+  test "packets following each other are decoded one at a time":
+    let ping = packet(PingReq, "")
+    check decodePacket(data & ping) == (Publish, body, data.len)
+    check decodePacket(ping & data) == (PingReq, "", 2)
+
+  test "lengths past four bytes are refused":
+    check decodePacket("\x30\xff\xff\xff")[2] == 0
+    expect MqttError:
+      discard decodePacket("\x30\xff\xff\xff\xff\x01")
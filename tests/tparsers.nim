@@ -0,0 +1,65 @@
+import std/os
+import std/times
+import std/unittest
+
+import pomod/config
+
+suite "lengths":
+  test "minutes by default":
+    check parseLength("25") == initDuration(minutes = 25)
+    check parseLength("25m") == initDuration(minutes = 25)
+    check parseLength("1.5") == initDuration(seconds = 90)
+
+  test "seconds and hours":
+    check parseLength("90s") == initDuration(seconds = 90)
+    check parseLength(" 2h ") == initDuration(hours = 2)
+
+  test "malformed lengths":
+    for text in ["", "m", "-5", "0", "five", "5x"]:
+      expect ValueError:
+        discard parseLength(text)
+
+suite "clock times":
+  test "mm:ss and h:mm:ss":
+    check parseClockTime("12:30") == initDuration(minutes = 12, seconds = 30)
+    check parseClockTime("1:02:03") ==
+      initDuration(hours = 1, minutes = 2, seconds = 3)
+
+  test "lengths are accepted too":
+    check parseClockTime("5m") == initDuration(minutes = 5)
+
+  test "malformed clock times":
+    for text in ["12:60", "1:2:3:4", "0:00", "a:30", ":30"]:
+      expect ValueError:
+        discard parseClockTime(text)
+
+suite "schedules":
+  test "kinds and their aliases":
+    check parseInterval("focus:50") ==
+      Interval(kind: ikFocus, duration: initDuration(minutes = 50))
+    check parseInterval("pomodoro:50").kind == ikFocus
+    check parseInterval("break:10m").kind == ikBreak
+    check parseInterval("short_break:10m").kind == ikBreak
+    check parseInterval("long_break:1h") ==
+      Interval(kind: ikLongBreak, duration: initDuration(hours = 1))
+
+  test "malformed entries":
+    for text in ["focus", "focus:50:10", "nap:20", "focus:", "break:-5"]:
+      expect ValueError:
+        discard parseInterval(text)
+
+  test "schedules in the config file":
+    let path = getTempDir()/"pomod-test-schedule.toml"
+    defer: removeFile(path)
+    writeFile(path, """schedule = ["focus:50", "break:10", "long_break:30"]""")
+    let schedule = loadConfig(path).profile.schedule
+    check schedule.len == 3
+    check schedule[2] ==
+      Interval(kind: ikLongBreak, duration: initDuration(minutes = 30))
+
+  test "broken schedules are config errors":
+    let path = getTempDir()/"pomod-test-schedule.toml"
+    defer: removeFile(path)
+    writeFile(path, """schedule = ["focus:50", "snack:5"]""")
+    expect ConfigError:
+      discard loadConfig(path)
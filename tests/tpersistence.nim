@@ -0,0 +1,94 @@
+import std/json
+import std/os
+import std/times
+import std/unittest
+
+import pomod/config
+import pomod/persistence
+import pomod/timer
+
+proc minutes(count: int): Duration = initDuration(minutes = count)
+
+suite "saving the timer's state":
+  setup:
+    var timer = initTimer(defaultConfig())
+
+  test "a paused timer is picked up where it was left":
+    timer.label = "write report"
+    timer.tags = @["client"]
+    timer.start()
+    timer.skip()
+    timer.skip()
+    timer.setRemaining(minutes(12))
+    timer.pause()
+    var restored = initTimer(defaultConfig())
+    restored.restore(timer.toJson)
+    check restored.state == tsPomodoro
+    check restored.remainingTime == minutes(12)
+    check not restored.running
+    check restored.breakCounter == 1
+    check restored.label == "write report"
+    check restored.tags == @["client"]
+
+  test "a running timer loses the time that has passed since":
+    timer.start()
+    let state = timer.toJson
+    state["saved_at_ms"] = %(getTime().toUnixFloat * 1000 - 60_000)
+    var restored = initTimer(defaultConfig())
+    restored.restore(state)
+    check restored.running
+    check restored.remainingTime <= minutes(24)
+    check restored.remainingTime > minutes(23)
+
+  test "the state survives a round trip through the file":
+    let path = getTempDir()/"pomod-test-state.json"
+    defer: removeFile(path)
+    timer.start()
+    timer.skip()
+    timer.pause()
+    timer.saveState(path)
+    var restored = initTimer(defaultConfig())
+    restored.restoreState(path)
+    check restored.state == tsShortBreak
+    check restored.remainingTime == timer.remainingTime
+
+  test "malformed states are refused":
+    var restored = initTimer(defaultConfig())
+    expect KeyError:
+      restored.restore(%*{"state": "pomodoro"})
+    let state = timer.toJson
+    state["state"] = %"nap"
+    expect ValueError:
+      restored.restore(state)
+
+suite "restoring into a changed config":
+  test "a position past a shorter schedule starts it over":
+    var config = defaultConfig()
+    config.profile.schedule = @[
+      Interval(kind: ikFocus, duration: minutes(50)),
+      Interval(kind: ikBreak, duration: minutes(10)),
+      Interval(kind: ikFocus, duration: minutes(40)),
+    ]
+    var timer = initTimer(config)
+    timer.start()
+    timer.skip()
+    timer.skip()
+    check timer.scheduleIndex == 2
+    let state = timer.toJson
+    config.profile.schedule.setLen(2)
+    var restored = initTimer(config)
+    restored.restore(state)
+    check restored.scheduleIndex == 0
+    restored.skip()
+    check restored.state == tsShortBreak
+    check restored.remainingTime == minutes(10)
+
+  test "a position past a shorter cycle moves on to the long break":
+    var timer = initTimer(defaultConfig())
+    let state = timer.toJson
+    state["break_counter"] = %7
+    timer.restore(state)
+    check timer.breakCounter == 3
+    state["break_counter"] = %(-2)
+    timer.restore(state)
+    check timer.breakCounter == 0
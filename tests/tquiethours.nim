@@ -0,0 +1,68 @@
+import std/options
+import std/os
+import std/times
+import std/unittest
+
+import pomod/config
+import pomod/quiethours
+
+# 2 May 2024 was a Thursday
+proc at(day, hour, minute: int): DateTime =
+  result = dateTime(2024, mMay, MonthdayRange(day), HourRange(hour),
+                    MinuteRange(minute), zone = utc())
+
+suite "quiet hours":
+  let
+    evening = QuietHours(start: 18 * 60, stop: 21 * 60, days: {dThu},
+                         notifications: qmLow)
+    night = QuietHours(start: 22 * 60, stop: 7 * 60, days: {dThu},
+                       notifications: qmSilent)
+
+  test "periods within a day":
+    check at(2, 18, 0) in evening
+    check at(2, 20, 59) in evening
+    check at(2, 21, 0) notin evening
+    check at(2, 17, 59) notin evening
+    check at(3, 19, 0) notin evening
+
+  test "periods past midnight belong to the day they begin on":
+    check at(2, 23, 0) in night
+    check at(3, 6, 59) in night
+    check at(3, 7, 0) notin night
+    # the night before Thursday began on Wednesday
+    check at(2, 6, 0) notin night
+    check at(3, 23, 0) notin night
+
+  test "the quietest of overlapping periods wins":
+    let late = QuietHours(start: 20 * 60, stop: 23 * 60, days: {dThu},
+                          notifications: qmSilent)
+    check @[evening, late].quietMode(at(2, 19, 0)) == some(qmLow)
+    check @[evening, late].quietMode(at(2, 20, 30)) == some(qmSilent)
+    check @[evening, late].quietMode(at(2, 12, 0)).isNone
+
+  test "quiet hours in the config file":
+    let path = getTempDir()/"pomod-test-quiet.toml"
+    defer: removeFile(path)
+    writeFile(path, """
+[[quiet_hours]]
+from = "22:00"
+to = "07:00"
+days = ["weekdays"]
+notifications = "silent"
+""")
+    let periods = loadConfig(path).quietHours
+    check periods.len == 1
+    check periods[0] == QuietHours(start: 22 * 60, stop: 7 * 60,
+                                   days: {dMon .. dFri},
+                                   notifications: qmSilent)
+
+  test "malformed times are config errors":
+    let path = getTempDir()/"pomod-test-quiet.toml"
+    defer: removeFile(path)
+    writeFile(path, """
+[[quiet_hours]]
+from = "late"
+to = "07:00"
+""")
+    expect ConfigError:
+      discard loadConfig(path)
@@ -0,0 +1,46 @@
+import std/strutils
+import std/times
+import std/unittest
+
+import pomod/report
+import pomod/timer
+
+proc pomodoro(day, hour: int, task: string,
+              outcome = soCompleted): Session =
+  let start = dateTime(2024, mMay, MonthdayRange(day), HourRange(hour),
+                       zone = local()).toTime
+  result = Session(state: tsPomodoro, outcome: outcome, start: start,
+                   stop: start + initDuration(minutes = 25), task: task)
+
+suite "weekly reports":
+  let
+    monday = dateTime(2024, mApr, 29, zone = local())
+    sessions = [
+      pomodoro(29, 9, "write report"),
+      pomodoro(2, 9, "write report"),
+      pomodoro(2, 10, "write report"),
+      pomodoro(2, 11, "<b>review</b>"),
+      pomodoro(2, 12, "", soAbandoned),
+    ]
+
+  test "the format goes by the extension":
+    check reportFormat("week.md") == rfMarkdown
+    check reportFormat("week.HTML") == rfHtml
+    check reportFormat("week") == rfMarkdown
+
+  test "totals, days and tasks in Markdown":
+    let report = weeklyReport(sessions, monday, rfMarkdown)
+    check report.startsWith("# pomod weekly report, 2024-04-29 to 2024-05-05")
+    check "- pomodoros completed: 4\n" in report
+    check "- focus time: 1h40m\n" in report
+    check "- abandonment rate: 20%\n" in report
+    check "Mon █ 1\n" in report
+    check "Thu ███ 3\n" in report
+    check "Sun  0\n" in report
+    check "| write report | 3 | 1h15m |\n" in report
+
+  test "tasks are escaped in HTML":
+    let report = weeklyReport(sessions, monday, rfHtml)
+    check "<svg" in report
+    check "&lt;b&gt;review&lt;/b&gt;" in report
+    check "<b>review</b>" notin report
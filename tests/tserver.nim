@@ -0,0 +1,70 @@
+import std/unittest
+
+# requests are handed to the private handler, without a listening server
+include pomod/server
+
+let root = getTempDir()/"pomod-test-server"
+putEnv("XDG_DATA_HOME", root)
+
+proc request(verb, path: string, token = ""): HttpRequest =
+  result = HttpRequest(verb: verb, path: path)
+  if token.len > 0:
+    result.headers.add(("authorization", "Bearer " & token))
+
+suite "the server mode":
+  setup:
+    removeDir(root)
+    var accounts = @[
+      openAccount(UserConfig(name: "alice", token: "alice's token"),
+                  defaultConfig()),
+      openAccount(UserConfig(name: "bob", token: "bob's token"),
+                  defaultConfig()),
+    ]
+
+  teardown:
+    for account in accounts:
+      account.history.close()
+    removeDir(root)
+
+  test "requests need a user's token":
+    check accounts.handle(request("GET", "/status")).code == 401
+    check accounts.handle(request("GET", "/status", "eve's token")).code == 401
+    check accounts.handle(request("GET", "/status", "alice")).code == 401
+    var unprefixed = request("GET", "/status")
+    unprefixed.headers.add(("authorization", "alice's token"))
+    check accounts.handle(unprefixed).code == 401
+
+  test "the token tells the users apart":
+    check accounts.authenticate(request("GET", "/status", "alice's token")) == 0
+    check accounts.authenticate(request("GET", "/status", "bob's token")) == 1
+    let response = accounts.handle(request("GET", "/status", "bob's token"))
+    check response.code == 200
+    check parseJson(response.body)["user"].getStr == "bob"
+
+  test "users control only their own timer":
+    check accounts.handle(request("POST", "/start", "bob's token")).code == 200
+    check accounts[1].timer.running
+    check accounts[1].unsaved
+    check not accounts[0].timer.running
+
+  test "commands that can't be run are a conflict":
+    let response = accounts.handle(request("POST", "/resume", "alice's token"))
+    check response.code == 409
+    check not accounts[0].timer.running
+
+  test "the task is set from the query":
+    let response = accounts.handle(request(
+      "POST", "/task?text=write%20report&tags=client,writing", "bob's token"))
+    check response.code == 200
+    check accounts[1].timer.label == "write report"
+    check accounts[1].timer.tags == @["client", "writing"]
+    check accounts[0].timer.label == ""
+
+  test "verbs and endpoints":
+    check accounts.handle(request("GET", "/start", "bob's token")).code == 405
+    check accounts.handle(request("POST", "/status", "bob's token")).code == 405
+    check accounts.handle(request("GET", "/nope", "bob's token")).code == 404
+
+  test "every user has a directory of their own":
+    check dirExists(usersDir()/"alice")
+    check dirExists(usersDir()/"bob")
@@ -0,0 +1,97 @@
+import std/json
+import std/tables
+import std/times
+import std/unittest
+
+import pomod/stats
+import pomod/timer
+
+# 2 May 2024 was a Thursday
+proc at(day, hour: int, minute = 0): Time =
+  result = dateTime(2024, mMay, MonthdayRange(day), HourRange(hour),
+                    MinuteRange(minute), zone = local()).toTime
+
+proc pomodoro(start: Time, outcome = soCompleted,
+              tags: seq[string] = @[]): Session =
+  result = Session(state: tsPomodoro, outcome: outcome, start: start,
+                   stop: start + initDuration(minutes = 25), tags: tags)
+
+proc pause(start: Time, outcome = soCompleted): Session =
+  result = Session(state: tsShortBreak, outcome: outcome, start: start,
+                   stop: start + initDuration(minutes = 5))
+
+suite "summing up sessions":
+  let summary = summarize([
+    pomodoro(at(2, 9), tags = @["client"]), pause(at(2, 9, 25)),
+    pomodoro(at(2, 9, 30), tags = @["writing", "client"]),
+    pause(at(2, 9, 55), soSkipped),
+    pomodoro(at(2, 14)), pause(at(2, 14, 25)),
+    pomodoro(at(2, 15), soSkipped, @["client"]),
+    pomodoro(at(2, 16), soAbandoned),
+    pomodoro(at(2, 17), soCutShort),
+  ])
+
+  test "pomodoros are counted by how they ended":
+    check summary.completed == 3
+    check summary.skipped == 1
+    check summary.abandoned == 1
+    check summary.cutShort == 1
+    check summary.pomodoros == 6
+    check summary.focused == initDuration(minutes = 75)
+
+  test "breaks taken and skipped":
+    check summary.breaksTaken == 2
+    check summary.breaksSkipped == 1
+    check summary.breakAdherence == 2 / 3
+    check summary.abandonmentRate == 1 / 6
+
+  test "the busiest hours come first":
+    check summary.byHour[9] == 2
+    check summary.busiestHours == @[9, 14]
+    check summary.busiestHours(count = 1) == @[9]
+
+  test "completed pomodoros are grouped by tag":
+    var tags: seq[string]
+    for tag in summary.byTag.keys:
+      tags.add(tag)
+    check tags == @["client", "writing"]
+    check summary.byTag["client"].completed == 2
+    check summary.byTag["client"].focused == initDuration(minutes = 50)
+    check summary.byTag["writing"].completed == 1
+
+  test "nothing to sum up":
+    let empty = summarize(newSeq[Session]())
+    check empty.pomodoros == 0
+    check empty.breakAdherence == 1.0
+    check empty.abandonmentRate == 0.0
+    check empty.busiestHours.len == 0
+
+  test "the summary in JSON":
+    let json = summary.toJson(spWeek)
+    check json["period"].getStr == "week"
+    check json["completed"].getInt == 3
+    check json["focused"].getInt == 75 * 60
+    check json["busiest_hours"] == %[9, 14]
+    check json["by_tag"]["client"]["completed"].getInt == 2
+
+suite "periods":
+  let thursday = dateTime(2024, mMay, 2, 10, zone = local())
+
+  test "today, this week and this month":
+    check spToday.periodStart(thursday) ==
+      dateTime(2024, mMay, 2, zone = local())
+    check spWeek.periodStart(thursday) ==
+      dateTime(2024, mApr, 29, zone = local())
+    check spMonth.periodStart(thursday) ==
+      dateTime(2024, mMay, 1, zone = local())
+
+  test "late nights count towards the day before":
+    let
+      dayStart = initDuration(hours = 4)
+      lateNight = dateTime(2024, mMay, 2, 2, zone = local())
+    check spToday.periodStart(lateNight, dayStart) ==
+      dateTime(2024, mMay, 1, 4, zone = local())
+    check spToday.periodStart(thursday, dayStart) ==
+      dateTime(2024, mMay, 2, 4, zone = local())
+    check spWeek.periodStart(thursday, dayStart) ==
+      dateTime(2024, mApr, 29, 4, zone = local())
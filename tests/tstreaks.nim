@@ -0,0 +1,33 @@
+import std/os
+import std/unittest
+
+import pomod/streaks
+
+suite "streaks":
+  var log: DayLog
+  log.record("2024-04-29", 4)
+  log.record("2024-04-30", 2)
+  log.record("2024-05-01", 3)
+  log.record("2024-05-01", 1)
+
+  test "pomodoros add up over a day":
+    check log.completedOn("2024-05-01") == 4
+    check log.completedOn("2024-05-02") == 0
+
+  test "consecutive days with enough pomodoros":
+    check log.streak("2024-05-01", 1) == 3
+    check log.streak("2024-05-01", 3) == 1
+    check log.streak("2024-05-01", 5) == 0
+
+  test "today only breaks the streak once it's over":
+    check log.streak("2024-05-02", 1) == 3
+    check log.streak("2024-05-03", 1) == 0
+
+  test "the streak carries over month boundaries":
+    check log.streak("2024-05-01", 2) == 3
+
+  test "the log survives saving":
+    let path = getTempDir()/"pomod-test-days.json"
+    defer: removeFile(path)
+    log.save(path)
+    check loadDays(path).days == log.days
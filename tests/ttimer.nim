@@ -0,0 +1,145 @@
+import std/options
+import std/os
+import std/times
+import std/unittest
+
+import pomod/commands
+import pomod/config
+import pomod/dispatch
+import pomod/timer
+import pomod/timers
+
+proc minutes(count: int): Duration = initDuration(minutes = count)
+
+suite "the pomodoro cycle":
+  setup:
+    var timer = initTimer(defaultConfig())
+
+  test "a new timer waits to be started":
+    check timer.state == tsNone
+    check not timer.running
+    timer.start()
+    check timer.state == tsPomodoro
+    check timer.running
+    check timer.remainingTime == minutes(25)
+
+  test "a long break follows every fourth pomodoro":
+    timer.start()
+    var states: seq[TimerState]
+    for _ in 1..8:
+      timer.skip()
+      states.add(timer.state)
+    check states == @[tsShortBreak, tsPomodoro, tsShortBreak, tsPomodoro,
+                      tsShortBreak, tsPomodoro, tsLongBreak, tsPomodoro]
+    check timer.stats.pomodorosSkipped == 4
+    check timer.stats.breaksSkipped == 4
+
+  test "pausing keeps the time left":
+    timer.start()
+    timer.setRemaining(minutes(12))
+    timer.toggle()
+    check not timer.running
+    check timer.remainingTime == minutes(12)
+    timer.toggle()
+    check timer.running
+    check timer.state == tsPomodoro
+
+  test "time running out moves on to the break":
+    timer.start()
+    timer.setRemaining(initDuration(milliseconds = 1))
+    sleep(10)
+    timer.poll()
+    check timer.state == tsShortBreak
+    check timer.stats.pomodorosCompleted == 1
+
+  test "abandoning starts the pomodoro over, waiting to be started":
+    timer.start()
+    timer.setRemaining(minutes(10))
+    timer.abandon()
+    check timer.state == tsPomodoro
+    check not timer.running
+    check timer.remainingTime == minutes(25)
+    check timer.stats.pomodorosAbandoned == 1
+
+  test "jumping to a long break completes the cycle":
+    timer.start()
+    timer.jumpTo(tsLongBreak)
+    check timer.state == tsLongBreak
+    check timer.breakCounter == 0
+    expect ValueError:
+      timer.jumpTo(tsNone)
+
+  test "snoozing a break goes back to the pomodoro, then the same break":
+    timer.start()
+    timer.skip()
+    check timer.canSnooze
+    timer.snooze(minutes(5))
+    check timer.state == tsPomodoro
+    check timer.remainingTime == minutes(5)
+    timer.skip()
+    check timer.state == tsShortBreak
+
+  test "resets keep the counters, the task and the tags":
+    timer.label = "write report"
+    timer.tags = @["client"]
+    timer.start()
+    timer.skip()
+    timer.reset(timer.config)
+    check timer.state == tsNone
+    check timer.stats.pomodorosSkipped == 1
+    check timer.label == "write report"
+    check timer.tags == @["client"]
+
+suite "custom schedules":
+  test "the schedule is followed instead of the cycle":
+    var config = defaultConfig()
+    config.profile.schedule = @[
+      Interval(kind: ikFocus, duration: minutes(50)),
+      Interval(kind: ikBreak, duration: minutes(10)),
+    ]
+    var timer = initTimer(config)
+    timer.start()
+    check timer.remainingTime == minutes(50)
+    timer.skip()
+    check timer.state == tsShortBreak
+    check timer.remainingTime == minutes(10)
+    timer.skip()
+    check timer.remainingTime == minutes(50)
+
+suite "commands":
+  setup:
+    var timer = initTimer(defaultConfig())
+
+  test "resuming needs a started timer":
+    expect CommandError:
+      discard timer.control(Command(kind: ckResume))
+
+  test "strict mode keeps pomodoros from being paused or skipped":
+    timer.config.strict = true
+    timer.start()
+    for kind in [ckPause, ckToggle, ckSkip]:
+      expect StrictModeError:
+        timer.checkStrict(Command(kind: kind))
+    timer.checkStrict(Command(kind: ckAbandon))
+
+  test "the daily limit keeps new pomodoros from starting":
+    timer.config.dailyLimit = 1
+    timer.stats.day = now().dayOf(timer.config.dayStart)
+    timer.stats.completedToday = 1
+    expect LimitError:
+      timer.checkLimit(Command(kind: ckStart))
+    timer.stats.overriddenOn = timer.stats.day
+    timer.checkLimit(Command(kind: ckStart))
+
+  test "commands for the caller are passed on":
+    check not timer.control(Command(kind: ckStatus))
+    check timer.control(Command(kind: ckStart))
+
+suite "countdowns":
+  test "countdowns run on days off":
+    var config = defaultConfig()
+    for day in WeekDay:
+      config.weekdays[day] = OffDay
+    let named = initNamedTimer("tea", config, some(minutes(3)))
+    check named.timer.running
+    check named.timer.remainingTime == minutes(3)